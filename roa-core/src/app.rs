@@ -2,9 +2,11 @@
 mod runtime;
 
 mod future;
+mod graceful;
 mod stream;
 use crate::{
     Chain, Context, Endpoint, Middleware, MiddlewareExt, Request, Response, State,
+    Status,
 };
 use future::SendFuture;
 use futures::io::{AsyncRead, AsyncWrite};
@@ -21,6 +23,7 @@ use std::task::Poll;
 
 use crate::Accept;
 use crate::{Executor, Spawn};
+pub use graceful::{GracefulServer, Shutdown, Signal};
 use std::convert::Infallible;
 pub use stream::AddrStream;
 
@@ -86,6 +89,7 @@ pub struct App<S, T> {
     service: T,
     exec: Executor,
     state: S,
+    error_handler: Option<ErrorHandler<S>>,
 }
 
 /// An implementation of hyper HttpService.
@@ -93,9 +97,16 @@ pub struct HttpService<S, E> {
     endpoint: Arc<E>,
     remote_addr: SocketAddr,
     exec: Executor,
+    error_handler: Option<ErrorHandler<S>>,
     pub(crate) state: S,
 }
 
+/// A handler to render the status thrown by the top middleware.
+///
+/// Status code and headers of the status are already set on response
+/// before the handler is called.
+pub type ErrorHandler<S> = Arc<dyn 'static + Send + Sync + Fn(&mut Context<S>, Status)>;
+
 impl<S, T> App<S, T> {
     /// Map app::service
     fn map_service<U>(self, mapper: impl FnOnce(T) -> U) -> App<S, U> {
@@ -103,13 +114,43 @@ impl<S, T> App<S, T> {
             exec,
             state,
             service,
+            error_handler,
         } = self;
         App {
             service: mapper(service),
             exec,
             state,
+            error_handler,
         }
     }
+
+    /// Set a handler to render the status thrown by the top middleware.
+    ///
+    /// By default, message of status is written to response body as plain text if it's exposed,
+    /// otherwise, the status will be logged.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Result, Status, throw};
+    /// use roa_core::http::StatusCode;
+    ///
+    /// fn handle_error(ctx: &mut Context, status: Status) {
+    ///     ctx.resp.write(format!("error: {}", status.message));
+    /// }
+    ///
+    /// async fn end(_ctx: &mut Context) -> Result {
+    ///     throw!(StatusCode::BAD_REQUEST, "invalid request")
+    /// }
+    ///
+    /// let app = App::new().error_handler(handle_error).end(end);
+    /// ```
+    pub fn error_handler(
+        mut self,
+        handler: impl 'static + Send + Sync + Fn(&mut Context<S>, Status),
+    ) -> Self {
+        self.error_handler = Some(Arc::new(handler));
+        self
+    }
 }
 
 impl<S> App<S, ()> {
@@ -119,6 +160,7 @@ impl<S> App<S, ()> {
             service: (),
             exec: Executor(Arc::new(exec)),
             state,
+            error_handler: None,
         }
     }
 }
@@ -161,17 +203,39 @@ where
             .serve(self)
     }
 
-    /// Make a fake http service for test.
-    #[cfg(test)]
+    /// Make a http service without binding any incoming.
+    ///
+    /// The remote address of this service is `127.0.0.1:0` by default,
+    /// you can change it by `HttpService::set_remote_addr`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Request};
+    /// use roa_core::http::StatusCode;
+    ///
+    /// # async fn serve() {
+    /// let service = App::new().end("Hello, World").http_service();
+    /// let resp = service.serve(Request::default()).await;
+    /// assert_eq!(StatusCode::OK, resp.status);
+    /// # }
+    /// ```
     pub fn http_service(&self) -> HttpService<S, E>
+    where
+        S: Clone,
+    {
+        self.make_service(([127, 0, 0, 1], 0).into())
+    }
+
+    fn make_service(&self, remote_addr: SocketAddr) -> HttpService<S, E>
     where
         S: Clone,
     {
         let endpoint = self.service.clone();
-        let addr = ([127, 0, 0, 1], 0);
         let state = self.state.clone();
         let exec = self.exec.clone();
-        HttpService::new(endpoint, addr.into(), exec, state)
+        let mut service = HttpService::new(endpoint, remote_addr, exec, state);
+        service.error_handler = self.error_handler.clone();
+        service
     }
 }
 
@@ -200,11 +264,8 @@ where
 
     #[inline]
     fn call(&mut self, stream: &AddrStream<IO>) -> Self::Future {
-        let endpoint = self.service.clone();
-        let addr = stream.remote_addr;
-        let state = self.state.clone();
-        let exec = self.exec.clone();
-        Box::pin(async move { Ok(HttpService::new(endpoint, addr, exec, state)) })
+        let service = self.make_service(stream.remote_addr);
+        Box::pin(async move { Ok(service) })
     }
 }
 
@@ -237,6 +298,7 @@ where
 }
 
 impl<S, E> HttpService<S, E> {
+    /// Construct a http service.
     pub fn new(
         endpoint: Arc<E>,
         remote_addr: SocketAddr,
@@ -247,10 +309,18 @@ impl<S, E> HttpService<S, E> {
             endpoint,
             remote_addr,
             exec,
+            error_handler: None,
             state,
         }
     }
 
+    /// Set the remote address of this service.
+    #[inline]
+    pub fn set_remote_addr(&mut self, remote_addr: SocketAddr) -> &mut Self {
+        self.remote_addr = remote_addr;
+        self
+    }
+
     /// Receive a request then return a response.
     /// The entry point of http service.
     pub async fn serve(self, req: Request) -> Response
@@ -262,17 +332,34 @@ impl<S, E> HttpService<S, E> {
             endpoint,
             remote_addr,
             exec,
+            error_handler,
             state,
         } = self;
         let mut ctx = Context::new(req, state, exec, remote_addr);
         if let Err(status) = endpoint.call(&mut ctx).await {
             ctx.resp.status = status.status_code;
-            if status.expose {
-                ctx.resp.write(status.message);
-            } else {
-                ctx.exec
-                    .spawn_blocking(move || log::error!("Uncaught status: {}", status))
-                    .await;
+            ctx.resp.headers.extend(status.headers.clone());
+            match error_handler {
+                Some(handler) => handler(&mut ctx, status),
+                None if status.expose => {
+                    ctx.resp.write(status.message);
+                }
+                None => {
+                    ctx.exec
+                        .spawn_blocking(move || {
+                            // details like "request_id" are logged with the status.
+                            if status.details.is_empty() {
+                                log::error!("Uncaught status: {}", status)
+                            } else {
+                                log::error!(
+                                    "Uncaught status: {}, details: {:?}",
+                                    status,
+                                    status.details
+                                )
+                            }
+                        })
+                        .await
+                }
             }
         }
         ctx.resp
@@ -285,6 +372,7 @@ impl<S: Clone, E> Clone for HttpService<S, E> {
             endpoint: self.endpoint.clone(),
             state: self.state.clone(),
             exec: self.exec.clone(),
+            error_handler: self.error_handler.clone(),
             remote_addr: self.remote_addr,
         }
     }
@@ -292,7 +380,9 @@ impl<S: Clone, E> Clone for HttpService<S, E> {
 
 #[cfg(all(test, feature = "runtime"))]
 mod tests {
-    use crate::{App, Request};
+    use crate::http::header::RETRY_AFTER;
+    use crate::{App, Context, Request, Status};
+    use futures::{AsyncReadExt, TryStreamExt};
     use http::StatusCode;
 
     #[async_std::test]
@@ -302,4 +392,30 @@ mod tests {
         assert_eq!(StatusCode::OK, resp.status);
         Ok(())
     }
+
+    #[async_std::test]
+    async fn error_handler() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(_ctx: &mut Context) -> crate::Result {
+            Err(Status::new(StatusCode::SERVICE_UNAVAILABLE, "busy", true)
+                .header(RETRY_AFTER, "120"))
+        }
+        fn handle_error(ctx: &mut Context, status: Status) {
+            assert_eq!(StatusCode::SERVICE_UNAVAILABLE, ctx.status());
+            ctx.resp.write(format!("error: {}", status.message));
+        }
+        let service = App::new()
+            .error_handler(handle_error)
+            .end(end)
+            .http_service();
+        let resp = service.serve(Request::default()).await;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, resp.status);
+        assert_eq!("120", resp.headers[RETRY_AFTER]);
+        let mut data = String::new();
+        resp.body
+            .into_async_read()
+            .read_to_string(&mut data)
+            .await?;
+        assert_eq!("error: busy", data);
+        Ok(())
+    }
 }
@@ -0,0 +1,177 @@
+use crate::{Accept, AddrStream, App, Endpoint, Executor, State};
+use futures::channel::oneshot::{channel, Receiver, Sender};
+use futures::future::{select, FutureExt, Shared};
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::task::{Context, Poll};
+use hyper::rt;
+use hyper::Server;
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// A server shutting down gracefully, returned by `App::accept_graceful`.
+pub type GracefulServer =
+    Pin<Box<dyn 'static + Send + Future<Output = Result<(), hyper::Error>>>>;
+
+/// A handle to shut down servers gracefully.
+///
+/// All clones of a handle share the same signal.
+///
+/// ### Example
+/// ```rust
+/// use roa_core::Shutdown;
+///
+/// # async fn wait() {
+/// let shutdown = Shutdown::new();
+/// let signal = shutdown.signal();
+/// shutdown.shutdown();
+/// signal.await;
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Shutdown {
+    sender: Arc<Mutex<Option<Sender<()>>>>,
+    receiver: Shared<Receiver<()>>,
+}
+
+/// A future resolved when `Shutdown::shutdown` is called.
+///
+/// It never resolves if all handles are dropped without calling `Shutdown::shutdown`.
+#[derive(Clone)]
+pub struct Signal(Shared<Receiver<()>>);
+
+/// An executor to close connections forcibly once killed.
+#[derive(Clone)]
+struct GracefulExec {
+    exec: Executor,
+    kill: Signal,
+}
+
+impl Shutdown {
+    /// Construct a new handle.
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender: Arc::new(Mutex::new(Some(sender))),
+            receiver: receiver.shared(),
+        }
+    }
+
+    /// Trigger shutdown signal, it only takes effect the first time.
+    pub fn shutdown(&self) {
+        let sender = self
+            .sender
+            .lock()
+            .expect("shutdown handle is poisoned")
+            .take();
+        if let Some(sender) = sender {
+            // signals may be all dropped, do nothing.
+            let _ = sender.send(());
+        }
+    }
+
+    /// Get a signal future.
+    pub fn signal(&self) -> Signal {
+        Signal(self.receiver.clone())
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Future for Signal {
+    type Output = ();
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match futures::ready!(Pin::new(&mut self.0).poll(cx)) {
+            Ok(()) => Poll::Ready(()),
+            // all senders are dropped, never resolve.
+            Err(_) => Poll::Pending,
+        }
+    }
+}
+
+impl<F> rt::Executor<F> for GracefulExec
+where
+    F: 'static + Send + Future,
+{
+    #[inline]
+    fn execute(&self, fut: F) {
+        let kill = self.kill.clone();
+        self.exec.0.spawn(Box::pin(async move {
+            select(Box::pin(fut), kill).await;
+        }));
+    }
+}
+
+impl<S, E> App<S, Arc<E>>
+where
+    E: for<'a> Endpoint<'a, S>,
+{
+    /// Construct a hyper server by an incoming, shutting down gracefully.
+    ///
+    /// Once `signal` resolves, the server stops accepting new connections
+    /// and waits for in-flight connections to finish.
+    /// Then the deadline future is constructed by `deadline`,
+    /// all remaining connections will be closed forcibly when it resolves.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Accept, AddrStream, Shutdown};
+    /// use async_std::net::TcpStream;
+    /// use async_std::task::{sleep, spawn};
+    /// use std::time::Duration;
+    ///
+    /// async fn serve(
+    ///     incoming: impl 'static + Send + Accept<Conn = AddrStream<TcpStream>, Error = std::io::Error>,
+    /// ) -> Result<(), Box<dyn std::error::Error>> {
+    ///     let shutdown = Shutdown::new();
+    ///     let server = App::new().end(()).accept_graceful(
+    ///         incoming,
+    ///         shutdown.signal(),
+    ///         || sleep(Duration::from_secs(30)),
+    ///     );
+    ///     let handle = spawn(server);
+    ///     shutdown.shutdown();
+    ///     handle.await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn accept_graceful<I, IO, F, D>(
+        self,
+        incoming: I,
+        signal: F,
+        deadline: impl 'static + Send + FnOnce() -> D,
+    ) -> GracefulServer
+    where
+        S: State,
+        IO: 'static + Send + Sync + Unpin + AsyncRead + AsyncWrite,
+        I: 'static + Send + Accept<Conn = AddrStream<IO>>,
+        I::Error: Into<Box<dyn Error + Send + Sync>>,
+        F: 'static + Send + Future<Output = ()>,
+        D: 'static + Send + Future<Output = ()>,
+    {
+        let (kill_sender, kill_receiver) = channel();
+        let exec = self.exec.clone();
+        let graceful_exec = GracefulExec {
+            exec: exec.clone(),
+            kill: Signal(kill_receiver.shared()),
+        };
+        let server = Server::builder(incoming)
+            .executor(graceful_exec)
+            .serve(self)
+            .with_graceful_shutdown(async move {
+                signal.await;
+                exec.spawn(async move {
+                    deadline().await;
+                    // server may be complete, do nothing.
+                    let _ = kill_sender.send(());
+                });
+            });
+        Box::pin(server)
+    }
+}
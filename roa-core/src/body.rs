@@ -122,6 +122,58 @@ impl Body {
             body => body.write_stream(once(ok(data.into()))),
         }
     }
+
+    /// Buffer the body into memory, so it can be read and replayed.
+    ///
+    /// A stream body is collected and replaced by `Body::Once`,
+    /// then its data is returned.
+    /// Return `None` if the body is larger than the limit, or an error of the stream,
+    /// the collected data is kept in the body followed by the rest of stream.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::Body;
+    /// use futures::stream;
+    /// use bytes::Bytes;
+    ///
+    /// # #[async_std::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let mut body = Body::empty();
+    /// body.write("Hello, ").write_stream(stream::iter(vec![Ok(Bytes::from("World"))]));
+    /// assert_eq!(None, body.buffer(5).await?);
+    /// assert_eq!(Some(Bytes::from("Hello, World")), body.buffer(1024).await?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn buffer(&mut self, limit: usize) -> io::Result<Option<Bytes>> {
+        let stream = match self {
+            Body::Empty => return Ok(Some(Bytes::new())),
+            Body::Once(bytes) if bytes.len() > limit => return Ok(None),
+            Body::Once(bytes) => return Ok(Some(bytes.clone())),
+            Body::Stream(stream) => stream,
+        };
+        let mut data = BytesMut::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    let rest = mem::take(stream);
+                    *self = Self::stream(once(ok(data.freeze())).chain(rest));
+                    return Err(err);
+                }
+            };
+            data.extend_from_slice(&chunk);
+            if data.len() > limit {
+                let rest = mem::take(stream);
+                *self = Self::stream(once(ok(data.freeze())).chain(rest));
+                return Ok(None);
+            }
+        }
+        let data = data.freeze();
+        *self = Body::Once(data.clone());
+        Ok(Some(data))
+    }
 }
 
 impl Segment {
@@ -232,6 +284,42 @@ mod tests {
         Ok(data)
     }
 
+    #[async_std::test]
+    async fn body_buffer() -> std::io::Result<()> {
+        let mut body = Body::default();
+        assert_eq!(Some(bytes::Bytes::new()), body.buffer(0).await?);
+        body.write("Hello, World");
+        assert_eq!(None, body.buffer(5).await?);
+        body.write_reader(File::open("../assets/author.txt").await?);
+        assert_eq!(None, body.buffer(15).await?);
+        assert_eq!(Some("Hello, WorldHexilee".into()), body.buffer(19).await?);
+        assert!(matches_once(&body));
+        assert_eq!("Hello, WorldHexilee", read_body(body).await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn body_buffer_error() -> std::io::Result<()> {
+        let chunks: Vec<io::Result<&'static str>> = vec![
+            Ok("Hello"),
+            Err(io::ErrorKind::BrokenPipe.into()),
+            Ok(", World"),
+        ];
+        let mut body = Body::default();
+        body.write_stream(futures::stream::iter(chunks).map_ok(bytes::Bytes::from));
+        let err = body.buffer(1024).await.unwrap_err();
+        assert_eq!(io::ErrorKind::BrokenPipe, err.kind());
+        assert_eq!(Some("Hello, World".into()), body.buffer(1024).await?);
+        Ok(())
+    }
+
+    fn matches_once(body: &Body) -> bool {
+        match body {
+            Body::Once(_) => true,
+            _ => false,
+        }
+    }
+
     #[async_std::test]
     async fn body_empty() -> std::io::Result<()> {
         let body = Body::default();
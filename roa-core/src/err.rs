@@ -1,5 +1,8 @@
+use http::header::{HeaderMap, HeaderName, HeaderValue};
 pub use http::StatusCode;
-use std::fmt::{Display, Formatter};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fmt::{Debug, Display, Formatter};
 use std::result::Result as StdResult;
 
 /// Type alias for `StdResult`.
@@ -122,6 +125,15 @@ pub struct Status {
 
     /// if message exposed.
     pub expose: bool,
+
+    /// An optional error code, for clients to identify the error.
+    pub code: Option<String>,
+
+    /// Structured details, like field errors.
+    pub details: BTreeMap<String, String>,
+
+    /// Headers will be set on response if Error is thrown by the top middleware.
+    pub headers: HeaderMap<HeaderValue>,
 }
 
 impl Status {
@@ -132,8 +144,67 @@ impl Status {
             status_code,
             message: message.to_string(),
             expose,
+            code: None,
+            details: BTreeMap::new(),
+            headers: HeaderMap::new(),
         }
     }
+
+    /// Set error code.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{status, Status};
+    /// use roa_core::http::StatusCode;
+    ///
+    /// let status = status!(StatusCode::BAD_REQUEST, "invalid user")
+    ///     .code("INVALID_USER")
+    ///     .detail("name", "name is too long");
+    /// assert_eq!(Some("INVALID_USER"), status.code.as_deref());
+    /// assert_eq!("name is too long", status.details["name"]);
+    /// ```
+    #[inline]
+    pub fn code(mut self, code: impl ToString) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    /// Add a detail.
+    #[inline]
+    pub fn detail(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.details.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Append a response header.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{status, Status};
+    /// use roa_core::http::StatusCode;
+    /// use roa_core::http::header::RETRY_AFTER;
+    ///
+    /// let status = status!(StatusCode::SERVICE_UNAVAILABLE, "busy")
+    ///     .header(RETRY_AFTER, "120");
+    /// assert_eq!("120", status.headers[RETRY_AFTER]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if name or value is invalid.
+    pub fn header<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        K::Error: Debug,
+        V: TryInto<HeaderValue>,
+        V::Error: Debug,
+    {
+        self.headers.append(
+            name.try_into().expect("invalid header name"),
+            value.try_into().expect("invalid header value"),
+        );
+        self
+    }
 }
 
 impl<E> From<E> for Status
@@ -1,6 +1,14 @@
 use crate::{async_trait, Context, Endpoint, Middleware, Next, Result};
+use http::Method;
 use std::sync::Arc;
 
+#[cfg(feature = "tracing")]
+use futures::Future;
+#[cfg(feature = "tracing")]
+use std::pin::Pin;
+#[cfg(feature = "tracing")]
+use std::task::{self, Poll};
+
 /// A set of method to chain middleware/endpoint to middleware
 /// or make middleware shared.
 pub trait MiddlewareExt<S>: Sized + for<'a> Middleware<'a, S> {
@@ -43,6 +51,50 @@ pub trait EndpointExt<S>: Sized + for<'a> Endpoint<'a, S> {
 impl<S, T> MiddlewareExt<S> for T where T: for<'a> Middleware<'a, S> {}
 impl<S, T> EndpointExt<S> for T where T: for<'a> Endpoint<'a, S> {}
 
+/// Instrument future of a middleware or an endpoint with a span
+/// named by its kind, recording its type as field `name`,
+/// only if feature "tracing" is enabled.
+macro_rules! instrument {
+    ($kind:literal, $ty:ty, $future:expr) => {{
+        #[cfg(feature = "tracing")]
+        let future = Traced {
+            future: $future,
+            span: None,
+            new_span: || {
+                tracing::debug_span!($kind, name = std::any::type_name::<$ty>())
+            },
+        };
+        #[cfg(not(feature = "tracing"))]
+        let future = $future;
+        future
+    }};
+}
+
+/// A future instrumented by a span created on the first poll,
+/// so the span is a child of the span polling it rather than constructing it.
+#[cfg(feature = "tracing")]
+struct Traced<F> {
+    future: F,
+    span: Option<tracing::Span>,
+    new_span: fn() -> tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+impl<F> Future for Traced<F>
+where
+    F: Future + Unpin,
+{
+    type Output = F::Output;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<F::Output> {
+        let this = &mut *self;
+        let new_span = this.new_span;
+        let _entered = this.span.get_or_insert_with(new_span).enter();
+        Pin::new(&mut this.future).poll(cx)
+    }
+}
+
 /// A middleware composing and executing other middlewares in a stack-like manner.
 pub struct Chain<T, U>(T, U);
 
@@ -61,7 +113,8 @@ where
     #[inline]
     async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
         let ptr = ctx as *mut Context<S>;
-        let mut next = self.1.handle(unsafe { &mut *ptr }, next);
+        let mut next =
+            instrument!("middleware", U, self.1.handle(unsafe { &mut *ptr }, next));
         self.0.handle(ctx, &mut next).await
     }
 }
@@ -93,6 +146,11 @@ where
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
         self.0.call(ctx).await
     }
+
+    #[inline]
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        self.0.allowed_methods()
+    }
 }
 
 #[async_trait(?Send)]
@@ -104,9 +162,14 @@ where
     #[inline]
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
         let ptr = ctx as *mut Context<S>;
-        let mut next = self.1.call(unsafe { &mut *ptr });
+        let mut next = instrument!("endpoint", U, self.1.call(unsafe { &mut *ptr }));
         self.0.handle(ctx, &mut next).await
     }
+
+    #[inline]
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        self.1.allowed_methods()
+    }
 }
 
 #[cfg(all(test, feature = "runtime"))]
@@ -8,13 +8,17 @@ mod context;
 mod err;
 mod executor;
 mod group;
+mod limit;
 mod middleware;
 mod request;
+mod responder;
 mod response;
 mod state;
 
 #[doc(inline)]
-pub use app::{AddrStream, App};
+pub use app::{
+    AddrStream, App, ErrorHandler, GracefulServer, HttpService, Shutdown, Signal,
+};
 
 #[doc(inline)]
 pub use executor::{Executor, JoinHandle, Spawn};
@@ -26,7 +30,10 @@ pub use context::{Context, Variable};
 pub use err::{Result, Status};
 
 #[doc(inline)]
-pub use middleware::{Endpoint, Middleware, Next};
+pub use middleware::{Endpoint, EndpointFn, Middleware, Next};
+
+#[doc(inline)]
+pub use responder::{Html, Redirect, Responder};
 
 #[doc(inline)]
 pub use group::{Boxed, Chain, EndpointExt, MiddlewareExt, Shared};
@@ -35,7 +42,10 @@ pub use group::{Boxed, Chain, EndpointExt, MiddlewareExt, Shared};
 pub use state::State;
 
 #[doc(inline)]
-pub use request::Request;
+pub use request::{MalformedBody, Request};
+
+#[doc(inline)]
+pub use limit::{BodyLimit, BodyLimitError};
 
 #[doc(inline)]
 pub use response::Response;
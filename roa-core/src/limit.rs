@@ -0,0 +1,310 @@
+use crate::request::find_source;
+use crate::{async_trait, Context, Middleware, Next, Result, Status};
+use bytes::Bytes;
+use futures::{Future, Stream};
+use futures_timer::Delay;
+use http::StatusCode;
+use hyper::Body;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::pin::Pin;
+use std::task::{self, Poll};
+use std::time::Duration;
+
+/// Limits of request body, enforced by `Request::stream` and `Request::reader`.
+///
+/// It's also a middleware to set limits of the following middlewares and endpoints,
+/// a limit not set keeps the value set by the previous middleware.
+///
+/// ### Example
+/// ```rust
+/// use roa_core::{App, BodyLimit, BodyLimitError, Context, Result};
+/// use futures::AsyncReadExt;
+/// use std::time::Duration;
+///
+/// async fn end(ctx: &mut Context) -> Result {
+///     let mut data = String::new();
+///     // throw 413 PAYLOAD TOO LARGE or 408 REQUEST TIMEOUT if body exceeds limits.
+///     ctx.req
+///         .reader()
+///         .read_to_string(&mut data)
+///         .await
+///         .map_err(BodyLimitError::status_of)?;
+///     Ok(())
+/// }
+///
+/// let limit = BodyLimit::new()
+///     .size(1024 * 1024)
+///     .timeout(Duration::from_secs(10));
+/// let app = App::new().gate(limit).end(end);
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct BodyLimit {
+    size: Option<usize>,
+    timeout: Option<Duration>,
+}
+
+/// Error of reading request body beyond `BodyLimit`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BodyLimitError {
+    /// Body is larger than the size limit.
+    TooLarge(usize),
+
+    /// Body is not received in time.
+    Timeout(Duration),
+}
+
+/// Request body with limits.
+pub(crate) struct LimitedBody {
+    body: Body,
+    size: Option<usize>,
+    content_length: Option<usize>,
+    received: usize,
+    delay: Option<(Duration, Delay)>,
+}
+
+impl BodyLimit {
+    /// Construct a limit without any restriction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set max size of body in bytes.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Set timeout to receive the whole body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Get max size of body.
+    pub fn max_size(&self) -> Option<usize> {
+        self.size
+    }
+
+    /// Get timeout to receive the whole body.
+    pub fn max_duration(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Override limits by another.
+    fn merge(self, other: Self) -> Self {
+        Self {
+            size: other.size.or(self.size),
+            timeout: other.timeout.or(self.timeout),
+        }
+    }
+
+    /// Wrap body with limits.
+    pub(crate) fn wrap(self, body: Body, content_length: Option<usize>) -> LimitedBody {
+        LimitedBody {
+            body,
+            size: self.size,
+            content_length,
+            received: 0,
+            delay: self.timeout.map(|timeout| (timeout, Delay::new(timeout))),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for BodyLimit {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        ctx.req.body_limit = ctx.req.body_limit.merge(*self);
+        next.await
+    }
+}
+
+impl BodyLimitError {
+    /// Find limit error in an io error returned by `Request::stream` or `Request::reader`.
+    ///
+    /// Sources of the error are also searched,
+    /// as a replaced raw body may wrap the limit error in other errors.
+    pub fn from_io(err: &io::Error) -> Option<&Self> {
+        find_source(err)
+    }
+
+    /// Convert an io error of reading body to status,
+    /// 413 PAYLOAD TOO LARGE or 408 REQUEST TIMEOUT if it's caused by limits.
+    pub fn status_of(err: io::Error) -> Status {
+        match Self::from_io(&err) {
+            Some(limit_err) => limit_err.status(),
+            None => err.into(),
+        }
+    }
+
+    /// Get status of this error.
+    pub fn status(&self) -> Status {
+        let status_code = match self {
+            BodyLimitError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            BodyLimitError::Timeout(_) => StatusCode::REQUEST_TIMEOUT,
+        };
+        Status::new(status_code, self, true)
+    }
+}
+
+impl Display for BodyLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyLimitError::TooLarge(size) => {
+                f.write_fmt(format_args!("request body is larger than {} bytes", size))
+            }
+            BodyLimitError::Timeout(timeout) => f.write_fmt(format_args!(
+                "request body is not received in {:?}",
+                timeout
+            )),
+        }
+    }
+}
+
+impl Error for BodyLimitError {}
+
+impl From<BodyLimitError> for io::Error {
+    fn from(err: BodyLimitError) -> Self {
+        let kind = match err {
+            BodyLimitError::TooLarge(_) => io::ErrorKind::InvalidData,
+            BodyLimitError::Timeout(_) => io::ErrorKind::TimedOut,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+impl LimitedBody {
+    /// Check size, a body with a large content length is rejected before receiving.
+    fn check_size(&self) -> io::Result<()> {
+        let received = self.received.max(self.content_length.unwrap_or(0));
+        match self.size {
+            Some(size) if received > size => Err(BodyLimitError::TooLarge(size).into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Stream for LimitedBody {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Err(err) = self.check_size() {
+            return Poll::Ready(Some(Err(err)));
+        }
+        if let Some((timeout, delay)) = &mut self.delay {
+            if Pin::new(delay).poll(cx).is_ready() {
+                return Poll::Ready(Some(Err(BodyLimitError::Timeout(*timeout).into())));
+            }
+        }
+        match futures::ready!(Pin::new(&mut self.body).poll_next(cx)) {
+            None => {
+                // the whole body is received
+                self.delay = None;
+                Poll::Ready(None)
+            }
+            Some(Err(err)) => {
+                Poll::Ready(Some(Err(io::Error::new(io::ErrorKind::Other, err))))
+            }
+            Some(Ok(data)) => {
+                self.received += data.len();
+                Poll::Ready(Some(self.check_size().map(|_| data)))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "runtime"))]
+mod tests {
+    use super::{BodyLimit, BodyLimitError};
+    use crate::{App, Context, Request, Result};
+    use futures::{stream, AsyncReadExt};
+    use http::header::CONTENT_LENGTH;
+    use http::StatusCode;
+    use hyper::Body;
+    use std::time::Duration;
+
+    async fn read(ctx: &mut Context) -> Result {
+        let mut data = Vec::new();
+        ctx.req
+            .reader()
+            .read_to_end(&mut data)
+            .await
+            .map_err(BodyLimitError::status_of)?;
+        ctx.resp.write(data);
+        Ok(())
+    }
+
+    async fn serve(limit: BodyLimit, req: Request) -> StatusCode {
+        let service = App::new().gate(limit).end(read).http_service();
+        service.serve(req).await.status
+    }
+
+    #[async_std::test]
+    async fn size() {
+        let limit = BodyLimit::new().size(5);
+        let req = Request::from(http::Request::new(Body::from("Hello")));
+        assert_eq!(StatusCode::OK, serve(limit, req).await);
+        let req = Request::from(http::Request::new(Body::from("Hello, World")));
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, serve(limit, req).await);
+    }
+
+    #[async_std::test]
+    async fn content_length() {
+        let limit = BodyLimit::new().size(5);
+        let mut req = Request::from(http::Request::new(Body::from("Hello")));
+        req.headers
+            .insert(CONTENT_LENGTH, "1048576".parse().unwrap());
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, serve(limit, req).await);
+    }
+
+    #[async_std::test]
+    async fn chunked() {
+        let limit = BodyLimit::new().size(10);
+        let chunks: Vec<std::io::Result<&'static str>> =
+            vec![Ok("Hello"), Ok(", "), Ok("World")];
+        let body = Body::wrap_stream(stream::iter(chunks));
+        let req = Request::from(http::Request::new(body));
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, serve(limit, req).await);
+    }
+
+    #[async_std::test]
+    async fn timeout() {
+        let limit = BodyLimit::new().timeout(Duration::from_millis(100));
+        let (_sender, body) = Body::channel();
+        let req = Request::from(http::Request::new(body));
+        assert_eq!(StatusCode::REQUEST_TIMEOUT, serve(limit, req).await);
+    }
+
+    #[async_std::test]
+    async fn merge() {
+        let limit = BodyLimit::new().size(5).timeout(Duration::from_secs(1));
+        let service = App::new()
+            .gate(limit)
+            .gate(BodyLimit::new().size(20))
+            .end(read)
+            .http_service();
+        let req = Request::from(http::Request::new(Body::from("Hello, World")));
+        assert_eq!(StatusCode::OK, service.clone().serve(req).await.status);
+
+        let (_sender, body) = Body::channel();
+        let req = Request::from(http::Request::new(body));
+        assert_eq!(StatusCode::REQUEST_TIMEOUT, service.serve(req).await.status);
+    }
+
+    #[async_std::test]
+    async fn wrapped() {
+        let chunks: Vec<std::io::Result<&'static str>> =
+            vec![Ok("Hello"), Err(BodyLimitError::TooLarge(5).into())];
+        let mut req = Request::default();
+        req.set_raw_body(Body::wrap_stream(stream::iter(chunks)));
+        assert_eq!(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            serve(BodyLimit::new(), req).await
+        );
+    }
+}
@@ -1,6 +1,6 @@
-use crate::{async_trait, throw, Context, Result, Status};
+use crate::{async_trait, throw, Context, Responder, Result, Status};
 use http::header::LOCATION;
-use http::{StatusCode, Uri};
+use http::{Method, StatusCode, Uri};
 use std::future::Future;
 
 /// ### Middleware
@@ -96,6 +96,18 @@ where
 ///
 /// let app = App::new().end(endpoint);
 /// ```
+///
+/// It can also return any `Responder`.
+///
+/// ```rust
+/// use roa_core::{App, Context, Result};
+///
+/// async fn endpoint(ctx: &mut Context) -> Result<String> {
+///     Ok("Hello, world".to_string())
+/// }
+///
+/// let app = App::new().end(endpoint);
+/// ```
 /// - Ok endpoint
 ///
 /// `()` is an endpoint always return `Ok(())`
@@ -164,18 +176,51 @@ where
 pub trait Endpoint<'a, S = ()>: 'static + Sync + Send {
     /// Call this endpoint.
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result;
+
+    /// Http methods allowed by this endpoint, `None` means all methods are allowed.
+    #[inline]
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        None
+    }
 }
 
-#[async_trait(?Send)]
-impl<'a, S, T, F> Endpoint<'a, S> for T
+/// A functional endpoint, implemented for async functions with signature:
+/// `async fn(&mut Context<S>) -> impl Responder<S>`.
+pub trait EndpointFn<'a, S>: 'static + Sync + Send {
+    /// Output of the future, will be written to response.
+    type Output;
+
+    /// Future returned by this function.
+    type Future: 'a + Future<Output = Self::Output>;
+
+    /// Call this function.
+    fn call(&self, ctx: &'a mut Context<S>) -> Self::Future;
+}
+
+impl<'a, S, T, F> EndpointFn<'a, S> for T
 where
     S: 'a,
     T: 'static + Send + Sync + Fn(&'a mut Context<S>) -> F,
-    F: 'a + Future<Output = Result>,
+    F: 'a + Future,
+{
+    type Output = F::Output;
+    type Future = F;
+    #[inline]
+    fn call(&self, ctx: &'a mut Context<S>) -> F {
+        (self)(ctx)
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, T, F, R> Endpoint<'a, S> for T
+where
+    S: 'a,
+    T: Fn(&'a mut Context<S>) -> F + for<'b> EndpointFn<'b, S, Output = R>,
+    R: Responder<S>,
 {
     #[inline]
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
-        (self)(ctx).await
+        EndpointFn::call(self, ctx).await.respond(ctx)
     }
 }
 
@@ -1,8 +1,12 @@
+use crate::{BodyLimit, BodyLimitError, Status};
 use bytes::Bytes;
 use futures::stream::TryStreamExt;
 use futures::{AsyncRead, Stream};
-use http::{HeaderMap, HeaderValue, Method, Uri, Version};
+use http::header::CONTENT_LENGTH;
+use http::{HeaderMap, HeaderValue, Method, StatusCode, Uri, Version};
 use hyper::Body;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::io;
 
 /// Http request type of roa.
@@ -20,30 +24,132 @@ pub struct Request {
     pub headers: HeaderMap<HeaderValue>,
 
     body: Body,
+    pub(crate) body_limit: BodyLimit,
 }
 
+/// Error of a request body failing to be decoded, like a corrupt compressed body.
+///
+/// A replaced raw body should wrap its decoding errors in it,
+/// then `Request::body_error_status` converts them to 400 BAD REQUEST.
+#[derive(Debug)]
+pub struct MalformedBody(Box<dyn Error + Sync + Send>);
+
 impl Request {
-    /// Get raw hyper body.
+    /// Get raw hyper body, without limits.
     #[inline]
     pub fn raw_body(&mut self) -> Body {
         std::mem::take(&mut self.body)
     }
-    /// Get body as Stream.
+
+    /// Replace raw hyper body, like decoding it by "Content-Encoding".
+    /// The new body is still limited by `BodyLimit`.
+    #[inline]
+    pub fn set_raw_body(&mut self, body: Body) {
+        self.body = body;
+    }
+
+    /// Get limits of body.
+    #[inline]
+    pub fn body_limit(&self) -> BodyLimit {
+        self.body_limit
+    }
+
+    /// Get body as Stream, limited by `BodyLimit`.
     /// This method will consume inner body.
     #[inline]
     pub fn stream(
         &mut self,
     ) -> impl Stream<Item = io::Result<Bytes>> + Sync + Send + Unpin + 'static {
-        self.raw_body()
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+        let content_length = self
+            .headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        self.body_limit.wrap(self.raw_body(), content_length)
     }
 
-    /// Get body as AsyncRead.
+    /// Get body as AsyncRead, limited by `BodyLimit`.
     /// This method will consume inner body.
     #[inline]
     pub fn reader(&mut self) -> impl AsyncRead + Sync + Send + Unpin + 'static {
         self.stream().into_async_read()
     }
+
+    /// Convert an io error of reading body by `Request::stream` or `Request::reader`
+    /// to status, 413 PAYLOAD TOO LARGE or 408 REQUEST TIMEOUT if it's caused by limits,
+    /// 400 BAD REQUEST if it's caused by `MalformedBody`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Request, Result};
+    /// use futures::AsyncReadExt;
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     let mut data = String::new();
+    ///     ctx.req
+    ///         .reader()
+    ///         .read_to_string(&mut data)
+    ///         .await
+    ///         .map_err(Request::body_error_status)?;
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(end);
+    /// ```
+    pub fn body_error_status(err: io::Error) -> Status {
+        if BodyLimitError::from_io(&err).is_some() {
+            return BodyLimitError::status_of(err);
+        }
+        match find_source::<MalformedBody>(&err) {
+            Some(malformed) => Status::new(StatusCode::BAD_REQUEST, malformed, true),
+            None => err.into(),
+        }
+    }
+}
+
+impl MalformedBody {
+    /// Construct an error by the decoding error.
+    pub fn new(err: impl Into<Box<dyn Error + Sync + Send>>) -> Self {
+        Self(err.into())
+    }
+}
+
+impl Display for MalformedBody {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("malformed request body: {}", self.0))
+    }
+}
+
+impl Error for MalformedBody {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+impl From<MalformedBody> for io::Error {
+    fn from(err: MalformedBody) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Find an error in an io error and its sources.
+///
+/// Sources are also searched, as a replaced raw body may wrap the error in other errors.
+pub(crate) fn find_source<T: Error + 'static>(err: &io::Error) -> Option<&T> {
+    let mut source = err.get_ref().map(|inner| inner as &(dyn Error + 'static));
+    while let Some(err) = source {
+        if let Some(target) = err.downcast_ref() {
+            return Some(target);
+        }
+        source = match err.downcast_ref::<io::Error>() {
+            // source of io error skips its inner error
+            Some(io_err) => io_err
+                .get_ref()
+                .map(|inner| inner as &(dyn Error + 'static)),
+            None => err.source(),
+        };
+    }
+    None
 }
 
 impl From<http::Request<Body>> for Request {
@@ -56,6 +162,7 @@ impl From<http::Request<Body>> for Request {
             version: parts.version,
             headers: parts.headers,
             body,
+            body_limit: BodyLimit::default(),
         }
     }
 }
@@ -69,8 +176,9 @@ impl Default for Request {
 
 #[cfg(all(test, feature = "runtime"))]
 mod tests {
-    use crate::{App, Context, Request, Status};
-    use futures::AsyncReadExt;
+    use super::MalformedBody;
+    use crate::{App, BodyLimit, BodyLimitError, Context, Request, Status};
+    use futures::{stream, AsyncReadExt};
     use http::StatusCode;
     use hyper::Body;
 
@@ -90,4 +198,32 @@ mod tests {
         assert_eq!(StatusCode::OK, resp.status);
         Ok(())
     }
+
+    async fn read(ctx: &mut Context) -> Result<(), Status> {
+        let mut data = Vec::new();
+        ctx.req
+            .reader()
+            .read_to_end(&mut data)
+            .await
+            .map_err(Request::body_error_status)?;
+        Ok(())
+    }
+
+    async fn status_of(err: std::io::Error) -> StatusCode {
+        let chunks: Vec<std::io::Result<&'static str>> = vec![Ok("Hello"), Err(err)];
+        let mut req = Request::default();
+        req.set_raw_body(Body::wrap_stream(stream::iter(chunks)));
+        let service = App::new().gate(BodyLimit::new()).end(read).http_service();
+        service.serve(req).await.status
+    }
+
+    #[async_std::test]
+    async fn body_error() {
+        let malformed = MalformedBody::new("corrupt data").into();
+        assert_eq!(StatusCode::BAD_REQUEST, status_of(malformed).await);
+        let too_large = BodyLimitError::TooLarge(5).into();
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, status_of(too_large).await);
+        let broken = std::io::ErrorKind::BrokenPipe.into();
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, status_of(broken).await);
+    }
 }
@@ -0,0 +1,333 @@
+use crate::{Context, Result, Status};
+use bytes::Bytes;
+use http::header::{HeaderValue, CONTENT_TYPE, LOCATION};
+use http::StatusCode;
+
+const TEXT_PLAIN: &str = "text/plain; charset=utf-8";
+const TEXT_HTML: &str = "text/html; charset=utf-8";
+const OCTET_STREAM: &str = "application/octet-stream";
+
+/// ### Responder
+///
+/// A type that can be written to response, returned by functional endpoints.
+///
+/// #### Build-in responders
+///
+/// - `()` does nothing.
+/// - `Result<T, E>` responds `T` or throws `E`.
+/// - `Option<T>` responds `T` or throws 404 NOT FOUND.
+/// - `String` and `&'static str` are written as "text/plain; charset=utf-8".
+/// - `Bytes` and `Vec<u8>` are written as "application/octet-stream".
+/// - `Html<T>` is written as "text/html; charset=utf-8".
+/// - `StatusCode` sets response status.
+/// - `(StatusCode, T)` responds `T` and sets response status.
+/// - `Redirect` redirects to an uri.
+/// - `Status` is thrown.
+///
+/// ```rust
+/// use roa_core::{App, Context, Html, Result};
+/// use roa_core::http::StatusCode;
+///
+/// async fn index(ctx: &mut Context) -> Result<Html<&'static str>> {
+///     Ok(Html("<h1>Hello, world</h1>"))
+/// }
+///
+/// async fn create(ctx: &mut Context) -> (StatusCode, String) {
+///     (StatusCode::CREATED, "created".to_string())
+/// }
+///
+/// let app = App::new().end(index);
+/// let app = App::new().end(create);
+/// ```
+///
+/// #### Custom responder
+///
+/// ```rust
+/// use roa_core::{App, Context, Responder, Result};
+///
+/// struct User {
+///     name: String,
+/// }
+///
+/// impl<S> Responder<S> for User {
+///     fn respond(self, ctx: &mut Context<S>) -> Result {
+///         ctx.resp.write(format!("Hello, {}", self.name));
+///         Ok(())
+///     }
+/// }
+///
+/// async fn get(ctx: &mut Context) -> Result<User> {
+///     Ok(User { name: "Hexilee".to_string() })
+/// }
+///
+/// let app = App::new().end(get);
+/// ```
+pub trait Responder<S = ()> {
+    /// Write self to response.
+    fn respond(self, ctx: &mut Context<S>) -> Result;
+}
+
+/// A responder to write body as "text/html; charset=utf-8".
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Html<T>(pub T);
+
+/// A responder to redirect to an uri.
+///
+/// ### Example
+/// ```rust
+/// use roa_core::{App, Context, Redirect};
+///
+/// async fn login(ctx: &mut Context) -> Redirect {
+///     Redirect::to("/login")
+/// }
+///
+/// let app = App::new().end(login);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Redirect {
+    status_code: StatusCode,
+    location: String,
+}
+
+impl Redirect {
+    /// Redirect with 303 SEE OTHER.
+    #[inline]
+    pub fn to(uri: impl ToString) -> Self {
+        Self::with_status_code(StatusCode::SEE_OTHER, uri)
+    }
+
+    /// Redirect with 307 TEMPORARY REDIRECT.
+    #[inline]
+    pub fn temporary(uri: impl ToString) -> Self {
+        Self::with_status_code(StatusCode::TEMPORARY_REDIRECT, uri)
+    }
+
+    /// Redirect with 308 PERMANENT REDIRECT.
+    #[inline]
+    pub fn permanent(uri: impl ToString) -> Self {
+        Self::with_status_code(StatusCode::PERMANENT_REDIRECT, uri)
+    }
+
+    #[inline]
+    fn with_status_code(status_code: StatusCode, uri: impl ToString) -> Self {
+        Self {
+            status_code,
+            location: uri.to_string(),
+        }
+    }
+}
+
+/// Write body with a static content type.
+#[inline]
+fn write<S>(ctx: &mut Context<S>, content_type: &'static str, data: impl Into<Bytes>) {
+    ctx.resp.write(data);
+    ctx.resp
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+}
+
+impl<S> Responder<S> for () {
+    #[inline]
+    fn respond(self, _ctx: &mut Context<S>) -> Result {
+        Ok(())
+    }
+}
+
+impl<S, T, E> Responder<S> for std::result::Result<T, E>
+where
+    T: Responder<S>,
+    E: Into<Status>,
+{
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        match self {
+            Ok(data) => data.respond(ctx),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl<S, T> Responder<S> for Option<T>
+where
+    T: Responder<S>,
+{
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        match self {
+            Some(data) => data.respond(ctx),
+            None => Err(Status::new(StatusCode::NOT_FOUND, "", true)),
+        }
+    }
+}
+
+impl<S> Responder<S> for Status {
+    #[inline]
+    fn respond(self, _ctx: &mut Context<S>) -> Result {
+        Err(self)
+    }
+}
+
+impl<S> Responder<S> for StatusCode {
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        ctx.resp.status = self;
+        Ok(())
+    }
+}
+
+impl<S, T> Responder<S> for (StatusCode, T)
+where
+    T: Responder<S>,
+{
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        let (status_code, data) = self;
+        data.respond(ctx)?;
+        ctx.resp.status = status_code;
+        Ok(())
+    }
+}
+
+impl<S> Responder<S> for String {
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        write(ctx, TEXT_PLAIN, self);
+        Ok(())
+    }
+}
+
+impl<S> Responder<S> for &'static str {
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        write(ctx, TEXT_PLAIN, self);
+        Ok(())
+    }
+}
+
+impl<S> Responder<S> for Bytes {
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        write(ctx, OCTET_STREAM, self);
+        Ok(())
+    }
+}
+
+impl<S> Responder<S> for Vec<u8> {
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        write(ctx, OCTET_STREAM, self);
+        Ok(())
+    }
+}
+
+impl<S, T> Responder<S> for Html<T>
+where
+    T: Into<Bytes>,
+{
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        write(ctx, TEXT_HTML, self.0);
+        Ok(())
+    }
+}
+
+impl<S> Responder<S> for Redirect {
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        ctx.resp.headers.insert(LOCATION, self.location.parse()?);
+        ctx.resp.status = self.status_code;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Html, Redirect};
+    use crate::{status, App, Context, Endpoint, Request, Result};
+    use bytes::Bytes;
+    use futures::{AsyncReadExt, TryStreamExt};
+    use http::header::{CONTENT_TYPE, LOCATION};
+    use http::StatusCode;
+
+    async fn read(endpoint: impl for<'a> Endpoint<'a>) -> (StatusCode, String, String) {
+        let service = App::new().end(endpoint).http_service();
+        let resp = service.serve(Request::default()).await;
+        let content_type = resp
+            .headers
+            .get(CONTENT_TYPE)
+            .map(|value| value.to_str().unwrap().to_string())
+            .unwrap_or_default();
+        let mut data = String::new();
+        resp.body
+            .into_async_read()
+            .read_to_string(&mut data)
+            .await
+            .unwrap();
+        (resp.status, content_type, data)
+    }
+
+    #[async_std::test]
+    async fn text() {
+        async fn end(_ctx: &mut Context) -> &'static str {
+            "Hello, world"
+        }
+        let (status, content_type, data) = read(end).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("text/plain; charset=utf-8", content_type);
+        assert_eq!("Hello, world", data);
+    }
+
+    #[async_std::test]
+    async fn html() {
+        async fn end(_ctx: &mut Context) -> Result<Html<String>> {
+            Ok(Html("<h1>Hello</h1>".to_string()))
+        }
+        let (_, content_type, data) = read(end).await;
+        assert_eq!("text/html; charset=utf-8", content_type);
+        assert_eq!("<h1>Hello</h1>", data);
+    }
+
+    #[async_std::test]
+    async fn bytes_with_status() {
+        async fn end(_ctx: &mut Context) -> (StatusCode, Bytes) {
+            (StatusCode::CREATED, Bytes::from_static(b"created"))
+        }
+        let (status, content_type, data) = read(end).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!("application/octet-stream", content_type);
+        assert_eq!("created", data);
+    }
+
+    #[async_std::test]
+    async fn option() {
+        async fn end(ctx: &mut Context) -> Option<String> {
+            ctx.load::<String>("user").map(|user| user.to_string())
+        }
+        let (status, _, _) = read(end).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+
+    #[async_std::test]
+    async fn error() {
+        async fn end(_ctx: &mut Context) -> Result<String> {
+            Err(status!(StatusCode::BAD_REQUEST, "invalid"))
+        }
+        let (status, _, data) = read(end).await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        assert_eq!("invalid", data);
+    }
+
+    #[async_std::test]
+    async fn redirect() {
+        async fn end(_ctx: &mut Context) -> Redirect {
+            Redirect::temporary("/target")
+        }
+        let resp = App::new()
+            .end(end)
+            .http_service()
+            .serve(Request::default())
+            .await;
+        assert_eq!(StatusCode::TEMPORARY_REDIRECT, resp.status);
+        assert_eq!("/target", resp.headers[LOCATION].to_str().unwrap());
+    }
+}
@@ -9,9 +9,8 @@ use actix_multipart::Multipart as ActixMultipart;
 use actix_multipart::MultipartError as ActixMultipartError;
 use bytes::Bytes;
 use futures::Stream;
-use hyper::Body;
 use roa_core::http::{header::CONTENT_TYPE, StatusCode};
-use roa_core::{Context, Status};
+use roa_core::{BodyLimitError, Context, Status};
 use std::fmt::{self, Display, Formatter};
 use std::io;
 use std::ops::Deref;
@@ -20,7 +19,7 @@ use std::task::{self, Poll};
 
 /// A context extensio nwrapped `actix_multipart::Multipart`.
 pub trait MultipartForm {
-    /// Read request body as multipart form.
+    /// Read request body as multipart form, limited by `BodyLimit`.
     fn form(&mut self) -> Multipart;
 }
 
@@ -30,10 +29,7 @@ impl<S> MultipartForm for Context<S> {
         if let Some(value) = self.req.headers.get(CONTENT_TYPE) {
             map.insert(CONTENT_TYPE, value.clone())
         }
-        Multipart(ActixMultipart::new(
-            &map,
-            WrapStream(Some(self.req.raw_body())),
-        ))
+        Multipart(ActixMultipart::new(&map, WrapStream(self.req.stream())))
     }
 }
 
@@ -47,35 +43,36 @@ pub struct Field(ActixField);
 #[derive(Debug)]
 pub struct MultipartError(ActixMultipartError);
 
-/// A wrapper for hyper::Body.
-struct WrapStream(Option<Body>);
+/// A wrapper for request body stream.
+struct WrapStream<S>(S);
 
-impl Stream for WrapStream {
+impl<S> Stream for WrapStream<S>
+where
+    S: Stream<Item = io::Result<Bytes>> + Unpin,
+{
     type Item = Result<Bytes, PayloadError>;
 
     fn poll_next(
         mut self: Pin<&mut Self>,
         cx: &mut task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        match &mut self.0 {
+        match futures::ready!(Pin::new(&mut self.0).poll_next(cx)) {
             None => Poll::Ready(None),
-            Some(body) => match futures::ready!(Pin::new(body).poll_next(cx)) {
-                None => {
-                    self.0 = None;
-                    self.poll_next(cx)
+            Some(item) => Poll::Ready(Some(item.map_err(|err| {
+                let incomplete = err
+                    .get_ref()
+                    .and_then(|inner| inner.downcast_ref::<hyper::Error>())
+                    .map(hyper::Error::is_incomplete_message)
+                    .unwrap_or(false);
+                if incomplete {
+                    PayloadError::Incomplete(Some(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        err,
+                    )))
+                } else {
+                    PayloadError::Io(err)
                 }
-                Some(item) => Poll::Ready(Some(match item {
-                    Ok(data) => Ok(data),
-                    Err(err) => Err(if err.is_incomplete_message() {
-                        PayloadError::Incomplete(Some(io::Error::new(
-                            io::ErrorKind::UnexpectedEof,
-                            err,
-                        )))
-                    } else {
-                        PayloadError::Io(io::Error::new(io::ErrorKind::Other, err))
-                    }),
-                })),
-            },
+            }))),
         }
     }
 }
@@ -137,6 +134,11 @@ impl Deref for Field {
 impl From<MultipartError> for Status {
     #[inline]
     fn from(err: MultipartError) -> Self {
+        if let ActixMultipartError::Payload(PayloadError::Io(io_err)) = &err.0 {
+            if let Some(limit_err) = BodyLimitError::from_io(io_err) {
+                return limit_err.status();
+            }
+        }
         Status::new(StatusCode::BAD_REQUEST, err, true)
     }
 }
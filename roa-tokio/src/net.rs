@@ -196,8 +196,10 @@ mod tests {
     use super::TcpIncoming;
     use crate::Exec;
     use roa::http::StatusCode;
-    use roa::App;
+    use roa::{App, Shutdown};
     use std::error::Error;
+    use std::time::Duration;
+    use tokio::time::delay_for;
 
     #[tokio::test]
     async fn incoming() -> Result<(), Box<dyn Error>> {
@@ -209,4 +211,23 @@ mod tests {
         assert_eq!(StatusCode::OK, resp.status());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn graceful() -> Result<(), Box<dyn Error>> {
+        let app = App::with_exec((), Exec).end(());
+        let incoming = TcpIncoming::bind("127.0.0.1:0")?;
+        let addr = incoming.local_addr();
+        let shutdown = Shutdown::new();
+        let server = app.accept_graceful(incoming, shutdown.signal(), || {
+            delay_for(Duration::from_secs(10))
+        });
+        let handle = tokio::spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        drop(resp);
+        shutdown.shutdown();
+        handle.await??;
+        assert!(reqwest::get(&format!("http://{}", addr)).await.is_err());
+        Ok(())
+    }
 }
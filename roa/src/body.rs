@@ -60,6 +60,9 @@
 //!     // deserialize as x-form-urlencoded.
 //!     let user: User = ctx.read_form().await?;
 //!
+//!     // deserialize by "Content-Type".
+//!     let user: User = ctx.read_auto().await?;
+//!
 //!     // serialize object and write it to body,
 //!     // set "Content-Type"
 //!     ctx.write_json(&user)?;
@@ -79,50 +82,194 @@
 //!     // render html template, based on [askama](https://github.com/djc/askama).
 //!     // set "Content-Type"
 //!     ctx.render(&user)?;
+//!
+//!     // write json, html or text by "Accept",
+//!     // set "Content-Type"
+//!     ctx.write_negotiated(&user)?;
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ### Streaming json
+//!
+//! `write_json_stream` writes a stream of objects without buffering them,
+//! as NDJSON or a json array, and `read_json_stream` decodes NDJSON body incrementally.
+//!
+//! ```rust
+//! use roa::{Context, Result};
+//! use roa::body::PowerBody;
+//! use futures::stream::{self, StreamExt};
+//!
+//! async fn export(ctx: &mut Context) -> Result {
+//!     ctx.write_json_stream(stream::iter(0..1_000_000))
+//! }
+//!
+//! async fn import(ctx: &mut Context) -> Result {
+//!     let mut items = ctx.read_json_stream::<u64>()?;
+//!     while let Some(item) = items.next().await {
+//!         println!("item: {}", item?);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ### Other formats
+//!
+//! MessagePack, CBOR, YAML and Protobuf are supported by
+//! `read_msgpack`/`write_msgpack`, `read_cbor`/`write_cbor`, `read_yaml`/`write_yaml`
+//! and `read_protobuf`/`write_protobuf`, with features "msgpack", "cbor", "yaml" and "protobuf".
+//! Protobuf messages are based on [prost](https://github.com/danburkert/prost).
+//!
+//! ### Content-Type and charset
+//!
+//! `read_*` methods throw 415 UNSUPPORTED MEDIA TYPE
+//! if "Content-Type" of request is set but mismatched,
+//! and throw 400 BAD REQUEST if body fails to decode.
+//! Text formats are decoded by the "charset" parameter, UTF-8 by default.
 
-use crate::{async_trait, http, Context, Result, State};
+use crate::{async_trait, http, Context, Request, Result, State};
 use bytes::Bytes;
+#[cfg(feature = "json")]
+use futures::Stream;
 use futures::{AsyncRead, AsyncReadExt};
 use lazy_static::lazy_static;
 
 #[cfg(feature = "template")]
 use askama::Template;
 #[cfg(feature = "file")]
-mod file;
+pub(crate) mod file;
+#[cfg(feature = "json")]
+mod json_stream;
+#[cfg(any(
+    feature = "json",
+    feature = "urlencoded",
+    feature = "msgpack",
+    feature = "cbor",
+    feature = "yaml",
+    feature = "protobuf"
+))]
+mod media;
 #[cfg(feature = "file")]
 pub use file::DispositionType;
 #[cfg(feature = "file")]
 use file::{write_file, Path};
-#[cfg(any(feature = "json", feature = "urlencoded"))]
+#[cfg(feature = "json")]
+pub use json_stream::JsonStream;
+#[cfg(any(
+    feature = "json",
+    feature = "urlencoded",
+    feature = "msgpack",
+    feature = "cbor",
+    feature = "yaml"
+))]
 use serde::de::DeserializeOwned;
 
 use http::{header, HeaderValue};
-#[cfg(feature = "json")]
+#[cfg(feature = "protobuf")]
+use prost::Message;
+#[cfg(any(
+    feature = "json",
+    feature = "msgpack",
+    feature = "cbor",
+    feature = "yaml"
+))]
 use serde::Serialize;
 
 /// A context extension to read/write body more simply.
 #[async_trait]
 pub trait PowerBody {
     /// read request body as Bytes.
+    ///
+    /// Throw 413 PAYLOAD TOO LARGE or 408 REQUEST TIMEOUT if body exceeds `BodyLimit`.
     async fn read(&mut self) -> Result<Vec<u8>>;
 
     /// read request body as "json".
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" is set but not json.
     #[cfg(feature = "json")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
     async fn read_json<B>(&mut self) -> Result<B>
     where
         B: DeserializeOwned;
 
+    /// read request body as a stream of "application/x-ndjson", decoded incrementally.
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" is set but not ndjson.
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    fn read_json_stream<B>(&mut self) -> Result<JsonStream<B>>
+    where
+        B: DeserializeOwned;
+
     /// read request body as "urlencoded form".
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" is set but not urlencoded form.
     #[cfg(feature = "urlencoded")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "urlencoded")))]
     async fn read_form<B>(&mut self) -> Result<B>
     where
         B: DeserializeOwned;
 
+    /// read request body as "application/msgpack".
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" is set but not msgpack.
+    #[cfg(feature = "msgpack")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "msgpack")))]
+    async fn read_msgpack<B>(&mut self) -> Result<B>
+    where
+        B: DeserializeOwned;
+
+    /// read request body as "application/cbor".
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" is set but not cbor.
+    #[cfg(feature = "cbor")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "cbor")))]
+    async fn read_cbor<B>(&mut self) -> Result<B>
+    where
+        B: DeserializeOwned;
+
+    /// read request body as "application/yaml".
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" is set but not yaml.
+    #[cfg(feature = "yaml")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "yaml")))]
+    async fn read_yaml<B>(&mut self) -> Result<B>
+    where
+        B: DeserializeOwned;
+
+    /// read request body as "application/protobuf".
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" is set but not protobuf.
+    #[cfg(feature = "protobuf")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "protobuf")))]
+    async fn read_protobuf<B>(&mut self) -> Result<B>
+    where
+        B: Message + Default;
+
+    /// read request body by "Content-Type", as any enabled format except protobuf.
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" is missing or not supported.
+    #[cfg(any(
+        feature = "json",
+        feature = "urlencoded",
+        feature = "msgpack",
+        feature = "cbor",
+        feature = "yaml"
+    ))]
+    #[cfg_attr(
+        feature = "docs",
+        doc(cfg(any(
+            feature = "json",
+            feature = "urlencoded",
+            feature = "msgpack",
+            feature = "cbor",
+            feature = "yaml"
+        )))
+    )]
+    async fn read_auto<B>(&mut self) -> Result<B>
+    where
+        B: DeserializeOwned;
+
     /// write object to response body as "application/json"
     #[cfg(feature = "json")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
@@ -130,6 +277,61 @@ pub trait PowerBody {
     where
         B: Serialize;
 
+    /// write a stream of objects to response body by `Accept` of request, as
+    /// - "application/x-ndjson", an object per line,
+    /// - or "application/json", a json array.
+    ///
+    /// Throw 406 NOT ACCEPTABLE if none of them is acceptable.
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    fn write_json_stream<St, B>(&mut self, stream: St) -> Result
+    where
+        St: 'static + Stream<Item = B> + Sync + Send,
+        B: Serialize;
+
+    /// write object to response body as "application/msgpack"
+    #[cfg(feature = "msgpack")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "msgpack")))]
+    fn write_msgpack<B>(&mut self, data: &B) -> Result
+    where
+        B: Serialize;
+
+    /// write object to response body as "application/cbor"
+    #[cfg(feature = "cbor")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "cbor")))]
+    fn write_cbor<B>(&mut self, data: &B) -> Result
+    where
+        B: Serialize;
+
+    /// write object to response body as "application/yaml"
+    #[cfg(feature = "yaml")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "yaml")))]
+    fn write_yaml<B>(&mut self, data: &B) -> Result
+    where
+        B: Serialize;
+
+    /// write message to response body as "application/protobuf"
+    #[cfg(feature = "protobuf")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "protobuf")))]
+    fn write_protobuf<B>(&mut self, data: &B) -> Result
+    where
+        B: Message;
+
+    /// write object to response body by `Accept` of request, as
+    /// - "application/json",
+    /// - "text/html; charset=utf-8", rendered by template,
+    /// - or "text/plain; charset=utf-8", pretty-printed json.
+    ///
+    /// Throw 406 NOT ACCEPTABLE if none of them is acceptable.
+    #[cfg(all(feature = "json", feature = "template"))]
+    #[cfg_attr(
+        feature = "docs",
+        doc(cfg(all(feature = "json", feature = "template")))
+    )]
+    fn write_negotiated<B>(&mut self, data: &B) -> Result
+    where
+        B: Serialize + Template;
+
     /// write object to response body as "text/html; charset=utf-8"
     #[cfg(feature = "template")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "template")))]
@@ -148,6 +350,9 @@ pub trait PowerBody {
         B: 'static + AsyncRead + Unpin + Sync + Send;
 
     /// write object to response body as extension name of file
+    ///
+    /// It supports conditional GET by "ETag" and "Last-Modified",
+    /// and partial content by "Range".
     #[cfg(feature = "file")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "file")))]
     async fn write_file<P>(&mut self, path: P, typ: DispositionType) -> Result
@@ -162,22 +367,137 @@ lazy_static! {
     static ref TEXT_HTML: HeaderValue =
         HeaderValue::from_static("text/html; charset=utf-8");
     static ref TEXT_PLAIN: HeaderValue = HeaderValue::from_static("text/plain");
+    static ref TEXT_PLAIN_UTF8: HeaderValue =
+        HeaderValue::from_static("text/plain; charset=utf-8");
+    static ref APPLICATION_NDJSON: HeaderValue =
+        HeaderValue::from_static("application/x-ndjson; charset=utf-8");
     static ref APPLICATION_OCTET_STREM: HeaderValue =
         HeaderValue::from_static("application/octet-stream");
+    static ref APPLICATION_MSGPACK: HeaderValue =
+        HeaderValue::from_static("application/msgpack");
+    static ref APPLICATION_CBOR: HeaderValue =
+        HeaderValue::from_static("application/cbor");
+    static ref APPLICATION_YAML: HeaderValue =
+        HeaderValue::from_static("application/yaml; charset=utf-8");
+    static ref APPLICATION_PROTOBUF: HeaderValue =
+        HeaderValue::from_static("application/protobuf");
+}
+
+/// Max capacity preallocated by `PowerBody::read`, 8 KiB.
+const MAX_SIZE_HINT: usize = 8 * 1024;
+
+/// Media types supported by `PowerBody::read_auto`.
+#[cfg(any(
+    feature = "json",
+    feature = "urlencoded",
+    feature = "msgpack",
+    feature = "cbor",
+    feature = "yaml"
+))]
+const SUPPORTED_MEDIA_TYPES: &[&str] = &[
+    #[cfg(feature = "json")]
+    "`application/json`",
+    #[cfg(feature = "urlencoded")]
+    "`application/x-www-form-urlencoded`",
+    #[cfg(feature = "msgpack")]
+    "`application/msgpack`",
+    #[cfg(feature = "cbor")]
+    "`application/cbor`",
+    #[cfg(feature = "yaml")]
+    "`application/yaml`",
+];
+
+/// Check "Content-Type" of request, then read body.
+#[cfg(any(
+    feature = "json",
+    feature = "urlencoded",
+    feature = "msgpack",
+    feature = "cbor",
+    feature = "yaml",
+    feature = "protobuf"
+))]
+async fn read_media<S: State>(
+    ctx: &mut Context<S>,
+    matches: fn(&mime::Mime) -> bool,
+    expect: &str,
+) -> Result<(Vec<u8>, Option<mime::Mime>)> {
+    let media = media::parse(ctx.get(header::CONTENT_TYPE))?;
+    media::check(media.as_ref(), matches, expect)?;
+    let data = ctx.read().await?;
+    Ok((data, media))
+}
+
+/// Deserialize json body.
+#[cfg(feature = "json")]
+fn parse_json<B: DeserializeOwned>(
+    data: &[u8],
+    media: Option<&mime::Mime>,
+) -> Result<B> {
+    use crate::status;
+    use http::StatusCode;
+    let text = media::decode(data, media)?;
+    serde_json::from_str(&text).map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+}
+
+/// Deserialize msgpack body.
+#[cfg(feature = "msgpack")]
+fn parse_msgpack<B: DeserializeOwned>(data: &[u8]) -> Result<B> {
+    use crate::status;
+    use http::StatusCode;
+    rmp_serde::from_read_ref(data).map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+}
+
+/// Deserialize cbor body.
+#[cfg(feature = "cbor")]
+fn parse_cbor<B: DeserializeOwned>(data: &[u8]) -> Result<B> {
+    use crate::status;
+    use http::StatusCode;
+    serde_cbor::from_slice(data).map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+}
+
+/// Deserialize yaml body.
+#[cfg(feature = "yaml")]
+fn parse_yaml<B: DeserializeOwned>(
+    data: &[u8],
+    media: Option<&mime::Mime>,
+) -> Result<B> {
+    use crate::status;
+    use http::StatusCode;
+    let text = media::decode(data, media)?;
+    serde_yaml::from_str(&text).map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+}
+
+/// Deserialize urlencoded form body.
+#[cfg(feature = "urlencoded")]
+fn parse_form<B: DeserializeOwned>(
+    data: &[u8],
+    media: Option<&mime::Mime>,
+) -> Result<B> {
+    use crate::status;
+    use http::StatusCode;
+    let data = media::decode_form(data, media)?;
+    serde_urlencoded::from_bytes(&data)
+        .map_err(|err| status!(StatusCode::BAD_REQUEST, err))
 }
 
 #[async_trait]
 impl<S: State> PowerBody for Context<S> {
     #[inline]
     async fn read(&mut self) -> Result<Vec<u8>> {
-        let size_hint = self
+        let size_hint: Option<usize> = self
             .get(header::CONTENT_LENGTH)
             .and_then(|value| value.parse().ok());
+        // "Content-Length" is given by client, never trust it to allocate;
+        // the buffer grows as the body is received.
         let mut data = match size_hint {
-            Some(hint) => Vec::with_capacity(hint),
+            Some(hint) => Vec::with_capacity(hint.min(MAX_SIZE_HINT)),
             None => Vec::new(),
         };
-        self.req.reader().read_to_end(&mut data).await?;
+        self.req
+            .reader()
+            .read_to_end(&mut data)
+            .await
+            .map_err(Request::body_error_status)?;
         Ok(data)
     }
 
@@ -187,24 +507,136 @@ impl<S: State> PowerBody for Context<S> {
     where
         B: DeserializeOwned,
     {
-        use crate::status;
-        use http::StatusCode;
-        let data = self.read().await?;
-        serde_json::from_slice(&data)
-            .map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+        let (data, media) =
+            read_media(self, media::is_json, "`application/json`").await?;
+        parse_json(&data, media.as_ref())
+    }
+
+    #[cfg(feature = "json")]
+    #[inline]
+    fn read_json_stream<B>(&mut self) -> Result<JsonStream<B>>
+    where
+        B: DeserializeOwned,
+    {
+        let media = media::parse(self.get(header::CONTENT_TYPE))?;
+        media::check(media.as_ref(), media::is_ndjson, "`application/x-ndjson`")?;
+        Ok(JsonStream::new(self.req.stream()))
     }
 
     #[cfg(feature = "urlencoded")]
     #[inline]
     async fn read_form<B>(&mut self) -> Result<B>
+    where
+        B: DeserializeOwned,
+    {
+        let (data, media) =
+            read_media(self, media::is_form, "`application/x-www-form-urlencoded`")
+                .await?;
+        parse_form(&data, media.as_ref())
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[inline]
+    async fn read_msgpack<B>(&mut self) -> Result<B>
+    where
+        B: DeserializeOwned,
+    {
+        let (data, _) =
+            read_media(self, media::is_msgpack, "`application/msgpack`").await?;
+        parse_msgpack(&data)
+    }
+
+    #[cfg(feature = "cbor")]
+    #[inline]
+    async fn read_cbor<B>(&mut self) -> Result<B>
+    where
+        B: DeserializeOwned,
+    {
+        let (data, _) = read_media(self, media::is_cbor, "`application/cbor`").await?;
+        parse_cbor(&data)
+    }
+
+    #[cfg(feature = "yaml")]
+    #[inline]
+    async fn read_yaml<B>(&mut self) -> Result<B>
+    where
+        B: DeserializeOwned,
+    {
+        let (data, media) =
+            read_media(self, media::is_yaml, "`application/yaml`").await?;
+        parse_yaml(&data, media.as_ref())
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[inline]
+    async fn read_protobuf<B>(&mut self) -> Result<B>
+    where
+        B: Message + Default,
+    {
+        use crate::status;
+        use http::StatusCode;
+        let (data, _) =
+            read_media(self, media::is_protobuf, "`application/protobuf`").await?;
+        B::decode(data.as_slice()).map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+    }
+
+    #[cfg(any(
+        feature = "json",
+        feature = "urlencoded",
+        feature = "msgpack",
+        feature = "cbor",
+        feature = "yaml"
+    ))]
+    #[inline]
+    async fn read_auto<B>(&mut self) -> Result<B>
     where
         B: DeserializeOwned,
     {
         use crate::status;
         use http::StatusCode;
-        let data = self.read().await?;
-        serde_urlencoded::from_bytes(&data)
-            .map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+        let media = media::parse(self.get(header::CONTENT_TYPE))?.ok_or_else(|| {
+            status!(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "missing content type of request body"
+            )
+        })?;
+        #[cfg(feature = "json")]
+        {
+            if media::is_json(&media) {
+                let data = self.read().await?;
+                return parse_json(&data, Some(&media));
+            }
+        }
+        #[cfg(feature = "urlencoded")]
+        {
+            if media::is_form(&media) {
+                let data = self.read().await?;
+                return parse_form(&data, Some(&media));
+            }
+        }
+        #[cfg(feature = "msgpack")]
+        {
+            if media::is_msgpack(&media) {
+                let data = self.read().await?;
+                return parse_msgpack(&data);
+            }
+        }
+        #[cfg(feature = "cbor")]
+        {
+            if media::is_cbor(&media) {
+                let data = self.read().await?;
+                return parse_cbor(&data);
+            }
+        }
+        #[cfg(feature = "yaml")]
+        {
+            if media::is_yaml(&media) {
+                let data = self.read().await?;
+                return parse_yaml(&data, Some(&media));
+            }
+        }
+        let expect = format!("one of {}", SUPPORTED_MEDIA_TYPES.join(", "));
+        Err(media::unsupported(&media, &expect))
     }
 
     #[cfg(feature = "json")]
@@ -220,6 +652,123 @@ impl<S: State> PowerBody for Context<S> {
         Ok(())
     }
 
+    #[cfg(feature = "json")]
+    #[inline]
+    fn write_json_stream<St, B>(&mut self, stream: St) -> Result
+    where
+        St: 'static + Stream<Item = B> + Sync + Send,
+        B: Serialize,
+    {
+        use crate::negotiate::Negotiate;
+        use crate::throw;
+        use http::StatusCode;
+        self.resp
+            .headers
+            .append(header::VARY, HeaderValue::from_static("accept"));
+        match self.accepts(&["application/x-ndjson", "application/json"]) {
+            Some("application/x-ndjson") => {
+                self.resp.write_stream(json_stream::ndjson(stream));
+                self.resp
+                    .headers
+                    .insert(header::CONTENT_TYPE, APPLICATION_NDJSON.clone());
+            }
+            Some(_) => {
+                self.resp.write_stream(json_stream::array(stream));
+                self.resp
+                    .headers
+                    .insert(header::CONTENT_TYPE, APPLICATION_JSON.clone());
+            }
+            None => throw!(
+                StatusCode::NOT_ACCEPTABLE,
+                "acceptable media types: `application/x-ndjson`, `application/json`"
+            ),
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[inline]
+    fn write_msgpack<B>(&mut self, data: &B) -> Result
+    where
+        B: Serialize,
+    {
+        self.resp.write(rmp_serde::to_vec_named(data)?);
+        self.resp
+            .headers
+            .insert(header::CONTENT_TYPE, APPLICATION_MSGPACK.clone());
+        Ok(())
+    }
+
+    #[cfg(feature = "cbor")]
+    #[inline]
+    fn write_cbor<B>(&mut self, data: &B) -> Result
+    where
+        B: Serialize,
+    {
+        self.resp.write(serde_cbor::to_vec(data)?);
+        self.resp
+            .headers
+            .insert(header::CONTENT_TYPE, APPLICATION_CBOR.clone());
+        Ok(())
+    }
+
+    #[cfg(feature = "yaml")]
+    #[inline]
+    fn write_yaml<B>(&mut self, data: &B) -> Result
+    where
+        B: Serialize,
+    {
+        self.resp.write(serde_yaml::to_string(data)?);
+        self.resp
+            .headers
+            .insert(header::CONTENT_TYPE, APPLICATION_YAML.clone());
+        Ok(())
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[inline]
+    fn write_protobuf<B>(&mut self, data: &B) -> Result
+    where
+        B: Message,
+    {
+        let mut buf = Vec::with_capacity(data.encoded_len());
+        data.encode(&mut buf)?;
+        self.resp.write(buf);
+        self.resp
+            .headers
+            .insert(header::CONTENT_TYPE, APPLICATION_PROTOBUF.clone());
+        Ok(())
+    }
+
+    #[cfg(all(feature = "json", feature = "template"))]
+    #[inline]
+    fn write_negotiated<B>(&mut self, data: &B) -> Result
+    where
+        B: Serialize + Template,
+    {
+        use crate::negotiate::Negotiate;
+        use crate::throw;
+        use http::StatusCode;
+        self.resp
+            .headers
+            .append(header::VARY, HeaderValue::from_static("accept"));
+        match self.accepts(&["json", "html", "text"]) {
+            Some("json") => self.write_json(data),
+            Some("html") => self.render(data),
+            Some(_) => {
+                self.resp.write(serde_json::to_string_pretty(data)?);
+                self.resp
+                    .headers
+                    .insert(header::CONTENT_TYPE, TEXT_PLAIN_UTF8.clone());
+                Ok(())
+            }
+            None => throw!(
+                StatusCode::NOT_ACCEPTABLE,
+                "acceptable media types: `application/json`, `text/html`, `text/plain`"
+            ),
+        }
+    }
+
     #[cfg(feature = "template")]
     #[inline]
     fn render<B>(&mut self, data: &B) -> Result
@@ -270,11 +819,12 @@ mod tests {
     use super::PowerBody;
     use crate::http;
     use crate::tcp::Listener;
-    use crate::{App, Context};
+    use crate::test::Client;
+    use crate::{App, BodyLimit, Context};
     use askama::Template;
     use async_std::fs::File;
     use async_std::task::spawn;
-    use http::header::CONTENT_TYPE;
+    use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
     use http::StatusCode;
     use serde::{Deserialize, Serialize};
     use std::error::Error;
@@ -304,6 +854,352 @@ mod tests {
         name: "Hexilee",
     };
 
+    #[async_std::test]
+    async fn read_limit() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.read().await?;
+            Ok(())
+        }
+        let app = App::new().gate(BodyLimit::new().size(5)).end(test);
+        let client = Client::new(app);
+        let resp = client.post("/").body("Hello").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let resp = client.post("/").body("Hello, World").send().await;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn read_huge_content_length() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let data = ctx.read().await?;
+            assert!(data.capacity() <= super::MAX_SIZE_HINT);
+            ctx.resp.write(data);
+            Ok(())
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client
+            .post("/")
+            .header(CONTENT_LENGTH, usize::max_value().to_string())
+            .body("Hello")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hello", resp.text().await?);
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[async_std::test]
+    async fn read_json_media_type() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let user: UserDto = ctx.read_json().await?;
+            ctx.write(user.name);
+            Ok(())
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "text/plain")
+            .body(r#"{"id":0,"name":"Hexilee"}"#)
+            .send()
+            .await;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status);
+
+        let resp = client
+            .post("/")
+            .body(r#"{"id":0,"name":"Hexilee"}"#)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        // {"id":0,"name":"你好"} in GBK
+        let mut body = br#"{"id":0,"name":""#.to_vec();
+        body.extend_from_slice(&[0xc4, 0xe3, 0xba, 0xc3]);
+        body.extend_from_slice(br#""}"#);
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/json; charset=gbk")
+            .body(body)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("你好", resp.text().await?);
+        Ok(())
+    }
+
+    #[cfg(all(feature = "json", feature = "urlencoded"))]
+    #[async_std::test]
+    async fn read_auto() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let user: UserDto = ctx.read_auto().await?;
+            assert_eq!(USER, user);
+            Ok(())
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client.post("/").json(&USER).send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let resp = client.post("/").form(&USER).send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/problem+json")
+            .body(r#"{"id":0,"name":"Hexilee"}"#)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        let resp = client.post("/").body("id=0&name=Hexilee").send().await;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status);
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "text/plain")
+            .body("Hexilee")
+            .send()
+            .await;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status);
+        // other formats may be enabled
+        assert!(resp.text().await?.starts_with(
+            "unsupported media type `text/plain`, expect one of `application/json`, \
+             `application/x-www-form-urlencoded`"
+        ));
+        Ok(())
+    }
+
+    #[cfg(all(feature = "json", feature = "template"))]
+    #[async_std::test]
+    async fn write_negotiated() -> Result<(), Box<dyn Error>> {
+        use http::header::ACCEPT;
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.write_negotiated(&USER)
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!(
+            "application/json; charset=utf-8",
+            resp.headers[CONTENT_TYPE]
+        );
+
+        let resp = client
+            .get("/")
+            .header(ACCEPT, "text/html,application/xhtml+xml,*/*;q=0.8")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("text/html; charset=utf-8", resp.headers[CONTENT_TYPE]);
+
+        let resp = client.get("/").header(ACCEPT, "text/plain").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("text/plain; charset=utf-8", resp.headers[CONTENT_TYPE]);
+        assert!(resp.text().await?.contains(r#""name": "Hexilee""#));
+
+        let resp = client.get("/").header(ACCEPT, "image/png").send().await;
+        assert_eq!(StatusCode::NOT_ACCEPTABLE, resp.status);
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[async_std::test]
+    async fn json_stream() -> Result<(), Box<dyn Error>> {
+        use futures::stream::{self, TryStreamExt};
+        use http::header::ACCEPT;
+        async fn export(ctx: &mut Context) -> crate::Result {
+            ctx.write_json_stream(stream::iter(vec![USER, USER]))
+        }
+        let client = Client::new(App::new().end(export));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!(
+            "application/x-ndjson; charset=utf-8",
+            resp.headers[CONTENT_TYPE]
+        );
+        let line = r#"{"id":0,"name":"Hexilee"}"#;
+        assert_eq!(format!("{}\n{}\n", line, line), resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(ACCEPT, "application/json")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let users: Vec<UserDto> = resp.json().await?;
+        assert_eq!(2, users.len());
+
+        async fn import(ctx: &mut Context) -> crate::Result {
+            let users: Vec<UserDto> = ctx.read_json_stream()?.try_collect().await?;
+            assert!(users.iter().all(|user| USER == *user));
+            ctx.write(users.len().to_string());
+            Ok(())
+        }
+        let client = Client::new(App::new().end(import));
+        let chunks: Vec<std::io::Result<&'static str>> = vec![
+            Ok(r#"{"id":0,"na"#),
+            Ok(r#"me":"Hexilee"}"#),
+            Ok("\n"),
+            Ok(r#"{"id":0,"name":"Hexilee"}"#),
+        ];
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/x-ndjson")
+            .body(hyper::Body::wrap_stream(stream::iter(chunks)))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("2", resp.text().await?);
+
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/json")
+            .body("[]")
+            .send()
+            .await;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status);
+        Ok(())
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[async_std::test]
+    async fn msgpack() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let user: UserDto = ctx.read_msgpack().await?;
+            assert_eq!(USER, user);
+            ctx.write_msgpack(&USER)
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/msgpack")
+            .body(rmp_serde::to_vec_named(&USER)?)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("application/msgpack", resp.headers[CONTENT_TYPE]);
+        let user: UserDto = rmp_serde::from_read_ref(&resp.bytes().await?)?;
+        assert_eq!(USER, user);
+
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/msgpack")
+            .body("invalid")
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        Ok(())
+    }
+
+    #[cfg(feature = "cbor")]
+    #[async_std::test]
+    async fn cbor() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let user: UserDto = ctx.read_cbor().await?;
+            assert_eq!(USER, user);
+            ctx.write_cbor(&USER)
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/cbor")
+            .body(serde_cbor::to_vec(&USER)?)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("application/cbor", resp.headers[CONTENT_TYPE]);
+        let user: UserDto = serde_cbor::from_slice(&resp.bytes().await?)?;
+        assert_eq!(USER, user);
+
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_cbor::to_vec(&USER)?)
+            .send()
+            .await;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status);
+        let resp = client.post("/").body("invalid").send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        Ok(())
+    }
+
+    #[cfg(feature = "yaml")]
+    #[async_std::test]
+    async fn yaml() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let user: UserDto = ctx.read_yaml().await?;
+            assert_eq!(USER, user);
+            ctx.write_yaml(&USER)
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/x-yaml")
+            .body("id: 0\nname: Hexilee\n")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!(
+            "application/yaml; charset=utf-8",
+            resp.headers[CONTENT_TYPE]
+        );
+        let user: UserDto = serde_yaml::from_str(&resp.text().await?)?;
+        assert_eq!(USER, user);
+
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/yaml")
+            .body("invalid")
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        Ok(())
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[async_std::test]
+    async fn protobuf() -> Result<(), Box<dyn Error>> {
+        use prost::Message;
+
+        #[derive(Clone, PartialEq, Message)]
+        struct UserMessage {
+            #[prost(uint64, tag = "1")]
+            id: u64,
+            #[prost(string, tag = "2")]
+            name: String,
+        }
+
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let mut user: UserMessage = ctx.read_protobuf().await?;
+            assert_eq!("Hexilee", user.name);
+            user.id += 1;
+            ctx.write_protobuf(&user)
+        }
+        let user = UserMessage {
+            id: 0,
+            name: "Hexilee".to_string(),
+        };
+        let mut body = Vec::new();
+        user.encode(&mut body)?;
+        let client = Client::new(App::new().end(test));
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/x-protobuf")
+            .body(body)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("application/protobuf", resp.headers[CONTENT_TYPE]);
+        let user = UserMessage::decode(resp.bytes().await?)?;
+        assert_eq!(1, user.id);
+
+        let resp = client
+            .post("/")
+            .header(CONTENT_TYPE, "application/protobuf")
+            .body("invalid")
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        Ok(())
+    }
+
     #[cfg(feature = "json")]
     #[tokio::test]
     async fn read_json() -> Result<(), Box<dyn Error>> {
@@ -1,15 +1,32 @@
 mod content_disposition;
 mod help;
-use crate::{http, Context, Result, State};
+mod range;
+
+use crate::http::header::{
+    ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE,
+    RANGE,
+};
+use crate::http::{HeaderMap, HeaderValue, Method, StatusCode};
+use crate::{status, Context, Result, State};
 
 pub use async_std::path::Path;
 pub use content_disposition::DispositionType;
 
 use async_std::fs::File;
 use content_disposition::ContentDisposition;
+use futures::{AsyncReadExt, AsyncSeekExt};
+use headers::{ETag, HeaderMapExt, IfModifiedSince, IfNoneMatch, IfRange, LastModified};
 use std::convert::TryInto;
+use std::io::SeekFrom;
+use std::ops::RangeInclusive;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Write file to response body then set "Content-Type" and "Context-Disposition".
+///
+/// "Content-Length", "Last-Modified" and "ETag" are set by metadata of the file.
+/// For GET and HEAD requests, it responds 304 NOT MODIFIED
+/// if "If-None-Match" or "If-Modified-Since" matches,
+/// and serves "Range" by 206 PARTIAL CONTENT or 416 RANGE NOT SATISFIABLE.
 #[inline]
 pub async fn write_file<S: State>(
     ctx: &mut Context<S>,
@@ -17,24 +34,282 @@ pub async fn write_file<S: State>(
     typ: DispositionType,
 ) -> Result {
     let path = path.as_ref();
-    ctx.resp.write_reader(File::open(path).await?);
+    let filename = path
+        .file_name()
+        .map(|filename| filename.to_string_lossy().into_owned());
+    write_file_as(ctx, path, filename.as_deref(), typ).await
+}
 
-    if let Some(filename) = path.file_name() {
-        ctx.resp.headers.insert(
-            http::header::CONTENT_TYPE,
-            mime_guess::from_path(&filename)
-                .first_or_octet_stream()
-                .as_ref()
-                .parse()
-                .map_err(help::bug_report)?,
-        );
+/// Write file like `write_file`,
+/// but "Content-Type" and "Context-Disposition" are set by the filename.
+pub(crate) async fn write_file_as<S: State>(
+    ctx: &mut Context<S>,
+    path: &Path,
+    filename: Option<&str>,
+    typ: DispositionType,
+) -> Result {
+    let file = File::open(path).await?;
+    let metadata = file.metadata().await?;
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = etag(len, modified)?;
+    let last_modified = modified.map(LastModified::from);
 
-        let name = filename.to_string_lossy();
-        let content_disposition = ContentDisposition::new(typ, Some(&name));
+    let mut content_type = mime_guess::mime::APPLICATION_OCTET_STREAM;
+    if let Some(name) = filename {
+        content_type = mime_guess::from_path(name).first_or_octet_stream();
         ctx.resp.headers.insert(
-            http::header::CONTENT_DISPOSITION,
-            content_disposition.try_into()?,
+            CONTENT_TYPE,
+            content_type.as_ref().parse().map_err(help::bug_report)?,
         );
+
+        let content_disposition = ContentDisposition::new(typ, Some(name));
+        ctx.resp
+            .headers
+            .insert(CONTENT_DISPOSITION, content_disposition.try_into()?);
+    }
+    ctx.resp
+        .headers
+        .insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    ctx.resp.headers.typed_insert(etag.clone());
+    if let Some(last_modified) = last_modified {
+        ctx.resp.headers.typed_insert(last_modified);
+    }
+
+    if ctx.method() != Method::GET && ctx.method() != Method::HEAD {
+        return write_whole(ctx, file, len);
+    }
+    if !is_modified(&ctx.req.headers, &etag, modified) {
+        ctx.resp.status = StatusCode::NOT_MODIFIED;
+        return Ok(());
+    }
+    match ranges(&ctx.req.headers, &etag, last_modified.as_ref(), len) {
+        None => write_whole(ctx, file, len),
+        Some(ranges) if ranges.is_empty() => {
+            Err(status!(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(CONTENT_RANGE, range::unsatisfied(len)))
+        }
+        Some(ranges) if ranges.len() == 1 => {
+            let range = &ranges[0];
+            ctx.resp.status = StatusCode::PARTIAL_CONTENT;
+            ctx.resp.headers.insert(
+                CONTENT_RANGE,
+                range::content_range(range, len)
+                    .parse()
+                    .map_err(help::bug_report)?,
+            );
+            ctx.resp
+                .headers
+                .insert(CONTENT_LENGTH, range_len(range).into());
+            ctx.resp.write_reader(read_range(file, range).await?);
+            Ok(())
+        }
+        Some(ranges) => {
+            let boundary = boundary(len);
+            let mut content_length = 0;
+            for range in ranges.iter() {
+                let header =
+                    range::part_header(&boundary, content_type.as_ref(), range, len);
+                content_length += header.len() as u64 + range_len(range);
+                ctx.resp.write(header);
+                let file = File::open(path).await?;
+                ctx.resp.write_reader(read_range(file, range).await?);
+            }
+            let tail = range::tail(&boundary);
+            content_length += tail.len() as u64;
+            ctx.resp.write(tail);
+            ctx.resp.status = StatusCode::PARTIAL_CONTENT;
+            ctx.resp.headers.insert(
+                CONTENT_TYPE,
+                format!("multipart/byteranges; boundary={}", boundary)
+                    .parse()
+                    .map_err(help::bug_report)?,
+            );
+            ctx.resp
+                .headers
+                .insert(CONTENT_LENGTH, content_length.into());
+            Ok(())
+        }
     }
+}
+
+/// Write the whole file.
+fn write_whole<S>(ctx: &mut Context<S>, file: File, len: u64) -> Result {
+    ctx.resp.headers.insert(CONTENT_LENGTH, len.into());
+    ctx.resp.write_reader(file);
     Ok(())
 }
+
+/// Generate a strong ETag by length and modified time of the file.
+fn etag(len: u64, modified: Option<SystemTime>) -> Result<ETag> {
+    let modified = modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", len, modified)
+        .parse()
+        .map_err(help::bug_report)
+}
+
+/// Generate boundary of "multipart/byteranges".
+fn boundary(len: u64) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.subsec_nanos())
+        .unwrap_or(0);
+    format!("roa-byteranges-{:x}-{:x}", len, nanos)
+}
+
+/// Is the file modified since the version cached by client?
+fn is_modified(headers: &HeaderMap, etag: &ETag, modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = headers.typed_get::<IfNoneMatch>() {
+        return if_none_match.precondition_passes(etag);
+    }
+    match (headers.typed_get::<IfModifiedSince>(), modified) {
+        (Some(since), Some(modified)) => since.is_modified(modified),
+        _ => true,
+    }
+}
+
+/// Get satisfiable ranges requested by client,
+/// None if "Range" is not set, is invalid or "If-Range" mismatches.
+fn ranges(
+    headers: &HeaderMap,
+    etag: &ETag,
+    last_modified: Option<&LastModified>,
+    len: u64,
+) -> Option<Vec<RangeInclusive<u64>>> {
+    let value = headers.get(RANGE)?.to_str().ok()?;
+    if let Some(if_range) = headers.typed_get::<IfRange>() {
+        if if_range.is_modified(Some(etag), last_modified) {
+            return None;
+        }
+    }
+    range::parse(value, len)
+}
+
+/// Length of a range.
+fn range_len(range: &RangeInclusive<u64>) -> u64 {
+    range.end() - range.start() + 1
+}
+
+/// Seek to start of a range and limit the reader by its length.
+async fn read_range(
+    mut file: File,
+    range: &RangeInclusive<u64>,
+) -> Result<futures::io::Take<File>> {
+    file.seek(SeekFrom::Start(*range.start())).await?;
+    Ok(file.take(range_len(range)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_file, DispositionType};
+    use crate::http::header::{
+        CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+    };
+    use crate::http::StatusCode;
+    use crate::test::Client;
+    use crate::{App, Context};
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        write_file(ctx, "../assets/author.txt", DispositionType::Inline).await
+    }
+
+    #[async_std::test]
+    async fn conditional() -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().end(end));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("7", resp.headers[CONTENT_LENGTH]);
+        let etag = resp.headers[ETAG].clone();
+        let last_modified = resp.headers[LAST_MODIFIED].clone();
+        assert_eq!("Hexilee", resp.text().await?);
+
+        let resp = client.get("/").header(IF_NONE_MATCH, etag).send().await;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status);
+        assert_eq!("", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(IF_MODIFIED_SINCE, last_modified.clone())
+            .send()
+            .await;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status);
+
+        let resp = client
+            .get("/")
+            .header(IF_NONE_MATCH, "\"other\"")
+            .header(IF_MODIFIED_SINCE, last_modified)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn range() -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().end(end));
+        let resp = client.get("/").header(RANGE, "bytes=0-2").send().await;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        assert_eq!("bytes 0-2/7", resp.headers[CONTENT_RANGE]);
+        assert_eq!("3", resp.headers[CONTENT_LENGTH]);
+        assert_eq!("Hex", resp.text().await?);
+
+        let resp = client.get("/").header(RANGE, "bytes=-4").send().await;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        assert_eq!("ilee", resp.text().await?);
+
+        let resp = client.get("/").header(RANGE, "bytes=7-").send().await;
+        assert_eq!(StatusCode::RANGE_NOT_SATISFIABLE, resp.status);
+        assert_eq!("bytes */7", resp.headers[CONTENT_RANGE]);
+
+        let resp = client.get("/").header(RANGE, "bytes=3-1").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(RANGE, "bytes=0-2")
+            .header(IF_RANGE, "\"other\"")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        let etag = client.get("/").send().await.headers[ETAG].clone();
+        let resp = client
+            .get("/")
+            .header(RANGE, "bytes=0-2")
+            .header(IF_RANGE, etag)
+            .send()
+            .await;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn multipart_range() -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().end(end));
+        let resp = client.get("/").header(RANGE, "bytes=0-0, 3-").send().await;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        let content_type = resp.headers[CONTENT_TYPE].to_str()?.to_string();
+        let boundary = content_type
+            .trim_start_matches("multipart/byteranges; boundary=")
+            .to_string();
+        let content_length: usize = resp.headers[CONTENT_LENGTH].to_str()?.parse()?;
+        let body = resp.text().await?;
+        assert_eq!(content_length, body.len());
+        assert_eq!(
+            format!(
+                "\r\n--{0}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-0/7\r\n\r\nH\
+                 \r\n--{0}\r\nContent-Type: text/plain\r\nContent-Range: bytes 3-6/7\r\n\r\nilee\
+                 \r\n--{0}--\r\n",
+                boundary
+            ),
+            body
+        );
+        Ok(())
+    }
+}
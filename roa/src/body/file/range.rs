@@ -0,0 +1,163 @@
+use std::ops::RangeInclusive;
+
+/// Max count of ranges in a request, to prevent from amplification.
+const MAX_RANGES: usize = 32;
+
+/// Parse value of "Range" for a file with the length.
+///
+/// Return None if the header is malformed or the unit is not "bytes",
+/// then it should be ignored; or satisfiable ranges, maybe empty.
+///
+/// Overlapping or adjacent ranges are coalesced and sorted as RFC 7233 recommends,
+/// so no byte is sent more than once.
+pub fn parse(value: &str, len: u64) -> Option<Vec<RangeInclusive<u64>>> {
+    let mut parts = value.trim().splitn(2, '=');
+    let unit = parts.next()?.trim();
+    let specs = parts.next()?;
+    if !unit.eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+    let mut ranges = Vec::new();
+    let mut count = 0;
+    for spec in specs
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+    {
+        count += 1;
+        if count > MAX_RANGES {
+            return None;
+        }
+        let mut bounds = spec.splitn(2, '-');
+        let first = bounds.next()?.trim();
+        let last = bounds.next()?.trim();
+        let range = if first.is_empty() {
+            // suffix range
+            let suffix: u64 = last.parse().ok()?;
+            if suffix == 0 || len == 0 {
+                continue;
+            }
+            len.saturating_sub(suffix)..=len - 1
+        } else {
+            let first: u64 = first.parse().ok()?;
+            let last = if last.is_empty() {
+                u64::max_value()
+            } else {
+                last.parse().ok()?
+            };
+            if first > last {
+                return None;
+            }
+            if first >= len {
+                continue;
+            }
+            first..=last.min(len - 1)
+        };
+        ranges.push(range);
+    }
+    if count == 0 {
+        None
+    } else {
+        Some(coalesce(ranges))
+    }
+}
+
+/// Sort ranges and merge the overlapping or adjacent ones.
+fn coalesce(mut ranges: Vec<RangeInclusive<u64>>) -> Vec<RangeInclusive<u64>> {
+    ranges.sort_by_key(|range| *range.start());
+    let mut coalesced: Vec<RangeInclusive<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match coalesced.last_mut() {
+            // ends are less than length of file, never overflow
+            Some(last) if *range.start() <= *last.end() + 1 => {
+                if range.end() > last.end() {
+                    *last = *last.start()..=*range.end();
+                }
+            }
+            _ => coalesced.push(range),
+        }
+    }
+    coalesced
+}
+
+/// Value of "Content-Range" for a satisfiable range.
+pub fn content_range(range: &RangeInclusive<u64>, len: u64) -> String {
+    format!("bytes {}-{}/{}", range.start(), range.end(), len)
+}
+
+/// Value of "Content-Range" for unsatisfiable ranges.
+pub fn unsatisfied(len: u64) -> String {
+    format!("bytes */{}", len)
+}
+
+/// Header of a part in a "multipart/byteranges" body.
+pub fn part_header(
+    boundary: &str,
+    content_type: &str,
+    range: &RangeInclusive<u64>,
+    len: u64,
+) -> String {
+    format!(
+        "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+        boundary,
+        content_type,
+        content_range(range, len)
+    )
+}
+
+/// Tail of a "multipart/byteranges" body.
+pub fn tail(boundary: &str) -> String {
+    format!("\r\n--{}--\r\n", boundary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, part_header, tail};
+
+    #[test]
+    fn parse_range() {
+        assert_eq!(Some(vec![0..=499]), parse("bytes=0-499", 1000));
+        assert_eq!(Some(vec![500..=999]), parse("bytes=500-", 1000));
+        assert_eq!(Some(vec![900..=999]), parse("bytes=-100", 1000));
+        assert_eq!(Some(vec![0..=999]), parse("bytes=-2000", 1000));
+        assert_eq!(Some(vec![900..=999]), parse("bytes=900-1500", 1000));
+        assert_eq!(
+            Some(vec![0..=0, 10..=19]),
+            parse("bytes=0-0, 10-19, 2000-", 1000)
+        );
+        assert_eq!(Some(vec![]), parse("bytes=1000-", 1000));
+        assert_eq!(Some(vec![]), parse("bytes=-0", 1000));
+        assert_eq!(Some(vec![]), parse("bytes=-10", 0));
+        assert_eq!(None, parse("bytes=10-1", 1000));
+        assert_eq!(None, parse("bytes=a-b", 1000));
+        assert_eq!(None, parse("bytes=", 1000));
+        assert_eq!(None, parse("items=0-1", 1000));
+        assert_eq!(None, parse("0-1", 1000));
+        let many = format!("bytes={}", vec!["0-0"; 33].join(","));
+        assert_eq!(None, parse(&many, 1000));
+    }
+
+    #[test]
+    fn coalesce_range() {
+        let duplicate = format!("bytes={}", vec!["0-"; 32].join(","));
+        assert_eq!(Some(vec![0..=999]), parse(&duplicate, 1000));
+        assert_eq!(
+            Some(vec![0..=299, 500..=599]),
+            parse("bytes=500-599, 0-99, 90-199, 200-299", 1000)
+        );
+        assert_eq!(
+            Some(vec![0..=99, 150..=999]),
+            parse("bytes=10-19, 0-99, -850", 1000)
+        );
+        assert_eq!(Some(vec![0..=0, 2..=2]), parse("bytes=2-2, 0-0", 1000));
+    }
+
+    #[test]
+    fn multipart() {
+        assert_eq!(
+            "\r\n--b\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/7\r\n\r\n",
+            part_header("b", "text/plain", &(0..=1), 7)
+        );
+        assert_eq!("\r\n--b--\r\n", tail("b"));
+    }
+}
@@ -0,0 +1,187 @@
+use crate::http::StatusCode;
+use crate::{status, Request, Result};
+use bytes::Bytes;
+use futures::stream::{self, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{self, Poll};
+
+/// Body stream of request.
+type BodyStream = Box<dyn Stream<Item = io::Result<Bytes>> + Sync + Send + Unpin>;
+
+/// A stream decoding NDJSON request body incrementally, returned by `PowerBody::read_json_stream`.
+///
+/// Each non-empty line is deserialized as an item,
+/// a line fails to deserialize yields 400 BAD REQUEST.
+pub struct JsonStream<T> {
+    body: BodyStream,
+    buf: Vec<u8>,
+    scanned: usize,
+    done: bool,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonStream<T> {
+    /// Construct from body stream.
+    pub(crate) fn new(
+        body: impl 'static + Stream<Item = io::Result<Bytes>> + Sync + Send + Unpin,
+    ) -> Self {
+        Self {
+            body: Box::new(body),
+            buf: Vec::new(),
+            scanned: 0,
+            done: false,
+            _item: PhantomData,
+        }
+    }
+
+    /// Take the next complete line from buffer.
+    fn next_line(&mut self) -> Option<Vec<u8>> {
+        match self.buf[self.scanned..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+        {
+            Some(offset) => {
+                let line = self.buf.drain(..=self.scanned + offset).collect();
+                self.scanned = 0;
+                Some(line)
+            }
+            None => {
+                self.scanned = self.buf.len();
+                None
+            }
+        }
+    }
+}
+
+/// Deserialize a line, None if it's blank.
+fn parse_line<T: DeserializeOwned>(line: &[u8]) -> Option<Result<T>> {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return None;
+    }
+    Some(
+        serde_json::from_slice(line)
+            .map_err(|err| status!(StatusCode::BAD_REQUEST, err)),
+    )
+}
+
+impl<T: DeserializeOwned> Stream for JsonStream<T> {
+    type Item = Result<T>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            while let Some(line) = self.next_line() {
+                if let Some(item) = parse_line(&line) {
+                    return Poll::Ready(Some(item));
+                }
+            }
+            if self.done {
+                // the last line may not end with a newline
+                let line = std::mem::take(&mut self.buf);
+                self.scanned = 0;
+                return Poll::Ready(parse_line(&line));
+            }
+            match futures::ready!(Pin::new(&mut self.body).poll_next(cx)) {
+                Some(Ok(data)) => self.buf.extend_from_slice(&data),
+                Some(Err(err)) => {
+                    self.done = true;
+                    self.buf.clear();
+                    return Poll::Ready(Some(Err(Request::body_error_status(err))));
+                }
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+/// Serialize an item to bytes, with a prefix and a suffix.
+fn to_bytes<T: Serialize>(item: &T, prefix: &[u8], suffix: &[u8]) -> io::Result<Bytes> {
+    let mut data = prefix.to_vec();
+    serde_json::to_writer(&mut data, item)?;
+    data.extend_from_slice(suffix);
+    Ok(data.into())
+}
+
+/// Serialize items as NDJSON.
+pub fn ndjson<S, T>(
+    stream: S,
+) -> impl 'static + Stream<Item = io::Result<Bytes>> + Sync + Send
+where
+    S: 'static + Stream<Item = T> + Sync + Send,
+    T: Serialize,
+{
+    stream.map(|item| to_bytes(&item, b"", b"\n"))
+}
+
+/// Serialize items as a JSON array.
+pub fn array<S, T>(
+    stream: S,
+) -> impl 'static + Stream<Item = io::Result<Bytes>> + Sync + Send
+where
+    S: 'static + Stream<Item = T> + Sync + Send,
+    T: Serialize,
+{
+    let items = stream.enumerate().map(|(index, item)| {
+        let prefix: &[u8] = if index == 0 { b"" } else { b"," };
+        to_bytes(&item, prefix, b"")
+    });
+    stream::iter(Some(Ok(Bytes::from_static(b"["))))
+        .chain(items)
+        .chain(stream::iter(Some(Ok(Bytes::from_static(b"]")))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{array, ndjson, JsonStream};
+    use bytes::Bytes;
+    use futures::stream::{self, StreamExt, TryStreamExt};
+    use std::io;
+
+    async fn collect(
+        stream: impl futures::Stream<Item = io::Result<Bytes>>,
+    ) -> io::Result<String> {
+        let chunks: Vec<Bytes> = stream.try_collect().await?;
+        Ok(chunks
+            .iter()
+            .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+            .collect())
+    }
+
+    #[async_std::test]
+    async fn write() -> io::Result<()> {
+        let items = || stream::iter(vec![1, 2, 3]);
+        assert_eq!("1\n2\n3\n", collect(ndjson(items())).await?);
+        assert_eq!("[1,2,3]", collect(array(items())).await?);
+        assert_eq!("[]", collect(array(stream::iter(Vec::<u8>::new()))).await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn read() {
+        let chunks: Vec<io::Result<Bytes>> = vec![
+            Ok(Bytes::from_static(b"1\n\n[2")),
+            Ok(Bytes::from_static(b"]\n")),
+            Ok(Bytes::from_static(b"3")),
+        ];
+        let items: Vec<serde_json::Value> = JsonStream::new(stream::iter(chunks))
+            .map(|item| item.unwrap())
+            .collect()
+            .await;
+        assert_eq!(vec![1.into(), serde_json::json!([2]), 3.into()], items);
+
+        let chunks: Vec<io::Result<Bytes>> = vec![Ok(Bytes::from_static(b"1\nx\n"))];
+        let items: Vec<crate::Result<u8>> =
+            JsonStream::new(stream::iter(chunks)).collect().await;
+        assert_eq!(2, items.len());
+        assert_eq!(
+            crate::http::StatusCode::BAD_REQUEST,
+            items[1].as_ref().unwrap_err().status_code
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use crate::http::StatusCode;
+use crate::{status, Result};
+#[cfg(any(feature = "json", feature = "urlencoded", feature = "yaml"))]
+use encoding_rs::{Encoding, UTF_8};
+use mime::Mime;
+#[cfg(any(feature = "json", feature = "urlencoded", feature = "yaml"))]
+use std::borrow::Cow;
+
+/// Parse media type of request body, None if "Content-Type" is not set.
+pub fn parse(content_type: Option<&str>) -> Result<Option<Mime>> {
+    match content_type {
+        None => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(|err| {
+            status!(
+                StatusCode::BAD_REQUEST,
+                format!("{}\ninvalid content type `{}`", err, value)
+            )
+        }),
+    }
+}
+
+/// Is it "application/json" or "application/*+json"?
+#[cfg(feature = "json")]
+pub fn is_json(media: &Mime) -> bool {
+    media.type_() == mime::APPLICATION
+        && (media.subtype() == mime::JSON || media.suffix() == Some(mime::JSON))
+}
+
+/// Is it "application/x-ndjson" or "application/ndjson"?
+#[cfg(feature = "json")]
+pub fn is_ndjson(media: &Mime) -> bool {
+    media.type_() == mime::APPLICATION
+        && (media.subtype() == "x-ndjson" || media.subtype() == "ndjson")
+}
+
+/// Is it "application/x-www-form-urlencoded"?
+#[cfg(feature = "urlencoded")]
+pub fn is_form(media: &Mime) -> bool {
+    media.type_() == mime::APPLICATION && media.subtype() == mime::WWW_FORM_URLENCODED
+}
+
+/// Is it "application/msgpack" or "application/x-msgpack"?
+#[cfg(feature = "msgpack")]
+pub fn is_msgpack(media: &Mime) -> bool {
+    media.type_() == mime::APPLICATION
+        && (media.subtype() == "msgpack" || media.subtype() == "x-msgpack")
+}
+
+/// Is it "application/cbor"?
+#[cfg(feature = "cbor")]
+pub fn is_cbor(media: &Mime) -> bool {
+    media.type_() == mime::APPLICATION && media.subtype() == "cbor"
+}
+
+/// Is it "application/yaml", "application/x-yaml" or "text/yaml"?
+#[cfg(feature = "yaml")]
+pub fn is_yaml(media: &Mime) -> bool {
+    (media.type_() == mime::APPLICATION || media.type_() == mime::TEXT)
+        && (media.subtype() == "yaml" || media.subtype() == "x-yaml")
+}
+
+/// Is it "application/protobuf" or "application/x-protobuf"?
+#[cfg(feature = "protobuf")]
+pub fn is_protobuf(media: &Mime) -> bool {
+    media.type_() == mime::APPLICATION
+        && (media.subtype() == "protobuf" || media.subtype() == "x-protobuf")
+}
+
+/// Check media type, throw 415 UNSUPPORTED MEDIA TYPE if mismatched.
+///
+/// A request without "Content-Type" is always accepted.
+pub fn check(media: Option<&Mime>, matches: fn(&Mime) -> bool, expect: &str) -> Result {
+    match media {
+        Some(media) if !matches(media) => Err(unsupported(media, expect)),
+        _ => Ok(()),
+    }
+}
+
+/// Construct a 415 UNSUPPORTED MEDIA TYPE.
+pub fn unsupported(media: &Mime, expect: &str) -> crate::Status {
+    status!(
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        format!(
+            "unsupported media type `{}`, expect {}",
+            media.essence_str(),
+            expect
+        )
+    )
+}
+
+/// Get encoding by the charset parameter, UTF-8 by default.
+///
+/// Throw 415 UNSUPPORTED MEDIA TYPE if the charset is unknown.
+#[cfg(any(feature = "json", feature = "urlencoded", feature = "yaml"))]
+fn encoding(media: Option<&Mime>) -> Result<&'static Encoding> {
+    match media.and_then(|media| media.get_param(mime::CHARSET)) {
+        None => Ok(UTF_8),
+        Some(charset) => {
+            Encoding::for_label(charset.as_str().as_bytes()).ok_or_else(|| {
+                status!(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!("unsupported charset `{}`", charset)
+                )
+            })
+        }
+    }
+}
+
+/// Decode bytes by an encoding, throw 400 BAD REQUEST if they are malformed.
+#[cfg(any(feature = "json", feature = "urlencoded", feature = "yaml"))]
+fn decode_with<'a>(encoding: &'static Encoding, data: &'a [u8]) -> Result<Cow<'a, str>> {
+    let text = if encoding == UTF_8 {
+        std::str::from_utf8(data).ok().map(Cow::Borrowed)
+    } else {
+        encoding.decode_without_bom_handling_and_without_replacement(data)
+    };
+    text.ok_or_else(|| {
+        status!(
+            StatusCode::BAD_REQUEST,
+            format!("body is not valid {}", encoding.name())
+        )
+    })
+}
+
+/// Decode text body by the charset declared in "Content-Type".
+#[cfg(any(feature = "json", feature = "yaml"))]
+pub fn decode<'a>(data: &'a [u8], media: Option<&Mime>) -> Result<Cow<'a, str>> {
+    decode_with(encoding(media)?, data)
+}
+
+/// Transcode a urlencoded form to UTF-8 by the charset declared in "Content-Type".
+///
+/// Percent-encoded bytes are decoded by the charset, as browsers encode them with it.
+#[cfg(feature = "urlencoded")]
+pub fn decode_form<'a>(data: &'a [u8], media: Option<&Mime>) -> Result<Cow<'a, [u8]>> {
+    use percent_encoding::percent_decode;
+    use url::form_urlencoded::Serializer;
+
+    let encoding = encoding(media)?;
+    if encoding == UTF_8 {
+        return Ok(Cow::Borrowed(data));
+    }
+    let decode_component = |input: &[u8]| -> Result<String> {
+        let input: Vec<u8> = input
+            .iter()
+            .map(|byte| if *byte == b'+' { b' ' } else { *byte })
+            .collect();
+        let bytes: Cow<[u8]> = percent_decode(&input).into();
+        decode_with(encoding, &bytes).map(Cow::into_owned)
+    };
+    let mut serializer = Serializer::new(String::new());
+    for pair in data.split(|byte| *byte == b'&') {
+        if pair.is_empty() {
+            continue;
+        }
+        let mut parts = pair.splitn(2, |byte| *byte == b'=');
+        let name = decode_component(parts.next().unwrap_or(&[]))?;
+        let value = decode_component(parts.next().unwrap_or(&[]))?;
+        serializer.append_pair(&name, &value);
+    }
+    Ok(Cow::Owned(serializer.finish().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::http::StatusCode;
+
+    #[cfg(all(feature = "json", feature = "urlencoded"))]
+    #[test]
+    fn media_type() -> crate::Result {
+        use super::{check, is_form, is_json};
+        assert!(parse(None)?.is_none());
+        let json = parse(Some("application/json; charset=utf-8"))?.unwrap();
+        assert!(is_json(&json));
+        assert!(!is_form(&json));
+        assert!(is_json(&parse(Some("application/problem+json"))?.unwrap()));
+        assert!(is_form(
+            &parse(Some("application/x-www-form-urlencoded"))?.unwrap()
+        ));
+        let status = parse(Some("json")).unwrap_err();
+        assert_eq!(StatusCode::BAD_REQUEST, status.status_code);
+
+        let text = parse(Some("text/plain"))?.unwrap();
+        assert!(check(None, is_json, "`application/json`").is_ok());
+        let status = check(Some(&text), is_json, "`application/json`").unwrap_err();
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, status.status_code);
+        assert_eq!(
+            "unsupported media type `text/plain`, expect `application/json`",
+            status.message
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn charset() -> crate::Result {
+        use super::decode;
+        assert_eq!("你好", decode("你好".as_bytes(), None)?);
+        let gbk = parse(Some("text/plain; charset=gbk"))?;
+        assert_eq!("你好", decode(&[0xc4, 0xe3, 0xba, 0xc3], gbk.as_ref())?);
+        let latin1 = parse(Some("text/plain; charset=ISO-8859-1"))?;
+        assert_eq!("café", decode(b"caf\xe9", latin1.as_ref())?);
+
+        let status = decode(b"caf\xe9", None).unwrap_err();
+        assert_eq!(StatusCode::BAD_REQUEST, status.status_code);
+        let unknown = parse(Some("text/plain; charset=unknown"))?;
+        let status = decode(b"", unknown.as_ref()).unwrap_err();
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, status.status_code);
+        Ok(())
+    }
+
+    #[cfg(feature = "urlencoded")]
+    #[test]
+    fn form_charset() -> crate::Result {
+        use super::decode_form;
+        let gbk = parse(Some("application/x-www-form-urlencoded; charset=gbk"))?;
+        assert_eq!(
+            b"name=%E4%BD%A0%E5%A5%BD&id=1".as_ref(),
+            decode_form(b"name=%C4%E3%BA%C3&id=1", gbk.as_ref())?.as_ref()
+        );
+        assert_eq!(
+            b"name=a+b".as_ref(),
+            decode_form(b"name=a+b", gbk.as_ref())?.as_ref()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,647 @@
+//! This module provides a middleware `Cache`,
+//! which caches responses on server side.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::cache::Cache;
+//! use roa::http::header::CACHE_CONTROL;
+//! use roa::{App, Context};
+//! use std::time::Duration;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     ctx.resp.headers.insert(CACHE_CONTROL, "max-age=60".parse()?);
+//!     ctx.resp.write("Hello, World");
+//!     Ok(())
+//! }
+//!
+//! let app = App::new()
+//!     .gate(Cache::new().ttl(Duration::from_secs(10)))
+//!     .end(end);
+//! ```
+
+mod store;
+
+pub use store::{CacheEntry, CacheStore, CachedResponse, LruStore};
+
+use crate::http::header::{
+    HeaderName, AGE, AUTHORIZATION, COOKIE, HOST, SET_COOKIE, VARY,
+};
+use crate::http::{HeaderMap, HeaderValue, Method, StatusCode};
+use crate::{async_trait, Body, Context, Middleware, Next, Result};
+use futures::channel::oneshot::{self, Sender};
+use headers::{CacheControl, HeaderMapExt};
+use log::error;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Default capacity of `LruStore` used by `Cache::new`.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Default max size of cacheable bodies, 1 MiB.
+const DEFAULT_MAX_SIZE: usize = 1 << 20;
+
+/// Keys in flight and waiters of them.
+type InFlight = Mutex<HashMap<String, Vec<Sender<()>>>>;
+
+/// A middleware to cache responses of GET and HEAD requests.
+///
+/// Responses are stored by method, host, uri and values of request headers
+/// listed in their "Vary", and are served without calling following
+/// middlewares and endpoints until they expire.
+///
+/// A response is cacheable if:
+///
+/// - its status is cacheable by default, like 200 OK or 404 NOT FOUND;
+/// - its "Cache-Control" contains none of "no-store", "no-cache" and "private";
+/// - it has no "Set-Cookie" and its "Vary" is not "*";
+/// - its body is not larger than `max_size`;
+/// - it's public or has "s-maxage" if the request has "Authorization";
+/// - it's public if the request has "Cookie".
+///
+/// Likewise, a request with "Authorization" or "Cookie" is only served
+/// by a stored response under the same conditions.
+///
+/// Time to live is "s-maxage" or "max-age" of the response.
+/// Responses without them are not cached, unless a default `ttl` is set.
+/// A request with "no-store" bypasses cache, and a request with "no-cache"
+/// refreshes it.
+///
+/// Concurrent misses of the same resource are coalesced:
+/// only one of them calls following middlewares and endpoints,
+/// the others wait and are served by its response.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::cache::{Cache, LruStore};
+/// use roa::{App, Context};
+///
+/// async fn end(ctx: &mut Context) -> roa::Result {
+///     ctx.resp.write("Hello, World");
+///     Ok(())
+/// }
+///
+/// let app = App::new()
+///     .gate(Cache::with_store(LruStore::new(256)).max_size(64 * 1024))
+///     .end(end);
+/// ```
+#[derive(Debug)]
+pub struct Cache<C = LruStore> {
+    store: C,
+    ttl: Option<Duration>,
+    max_size: usize,
+    in_flight: InFlight,
+}
+
+impl Cache {
+    /// Construct a middleware with an `LruStore` of 1024 entries.
+    pub fn new() -> Self {
+        Self::with_store(LruStore::new(DEFAULT_CAPACITY))
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: CacheStore> Cache<C> {
+    /// Construct a middleware with a store.
+    pub fn with_store(store: C) -> Self {
+        Self {
+            store,
+            ttl: None,
+            max_size: DEFAULT_MAX_SIZE,
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set default time to live of responses without "max-age" or "s-maxage",
+    /// which are not cached by default.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Set max size of cacheable bodies, 1 MiB by default.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Get an entry, errors of store are logged and ignored.
+    async fn get(&self, key: &str) -> Option<CacheEntry> {
+        match self.store.get(key).await {
+            Ok(entry) => entry,
+            Err(status) => {
+                error!("cache store error: {}", status);
+                None
+            }
+        }
+    }
+
+    /// Set an entry, errors of store are logged and ignored.
+    async fn set(&self, key: String, entry: CacheEntry, ttl: Duration) {
+        if let Err(status) = self.store.set(key, entry, ttl).await {
+            error!("cache store error: {}", status);
+        }
+    }
+
+    /// Look up a response matching the request.
+    async fn lookup(&self, key: &str, headers: &HeaderMap) -> Option<CachedResponse> {
+        let resp = match self.get(key).await? {
+            CacheEntry::Response(resp) => resp,
+            CacheEntry::Vary(names) => {
+                match self.get(&variant(key, &names, headers)).await? {
+                    CacheEntry::Response(resp) => resp,
+                    CacheEntry::Vary(_) => return None,
+                }
+            }
+        };
+        if reusable(headers, &resp.headers) {
+            Some(resp)
+        } else {
+            None
+        }
+    }
+
+    /// Store the response if it's cacheable.
+    async fn store<S>(&self, key: String, ctx: &mut Context<S>) -> Result {
+        let ttl = match self.freshness(ctx) {
+            Some(ttl) => ttl,
+            None => return Ok(()),
+        };
+        let vary = match vary(&ctx.resp.headers) {
+            Some(vary) => vary,
+            None => return Ok(()),
+        };
+        let body = match ctx.resp.body.buffer(self.max_size).await? {
+            Some(body) => body,
+            None => return Ok(()),
+        };
+        let resp = CacheEntry::Response(CachedResponse {
+            status: ctx.resp.status,
+            headers: ctx.resp.headers.clone(),
+            body,
+            stored_at: SystemTime::now(),
+        });
+        if vary.is_empty() {
+            self.set(key, resp, ttl).await;
+        } else {
+            let variant = variant(&key, &vary, &ctx.req.headers);
+            self.set(key, CacheEntry::Vary(vary), ttl).await;
+            self.set(variant, resp, ttl).await;
+        }
+        Ok(())
+    }
+
+    /// Get time to live of the response, None if it's not cacheable.
+    fn freshness<S>(&self, ctx: &Context<S>) -> Option<Duration> {
+        if !cacheable(ctx.resp.status) || ctx.resp.headers.contains_key(SET_COOKIE) {
+            return None;
+        }
+        let cache_control = ctx.resp.headers.typed_get::<CacheControl>();
+        if let Some(cache_control) = &cache_control {
+            if cache_control.no_store()
+                || cache_control.no_cache()
+                || cache_control.private()
+            {
+                return None;
+            }
+        }
+        let shared = cache_control.as_ref().and_then(CacheControl::s_max_age);
+        let public = cache_control.as_ref().map_or(false, CacheControl::public);
+        if ctx.req.headers.contains_key(AUTHORIZATION) && shared.is_none() && !public {
+            return None;
+        }
+        // responses to requests with cookies may be personalised
+        if ctx.req.headers.contains_key(COOKIE) && !public {
+            return None;
+        }
+        let ttl = shared
+            .or_else(|| cache_control.as_ref().and_then(CacheControl::max_age))
+            .or(self.ttl)?;
+        if ttl == Duration::from_secs(0) {
+            None
+        } else {
+            Some(ttl)
+        }
+    }
+
+    /// Mark the key in flight, or wait for the request in flight.
+    fn enter(&self, key: &str) -> Option<oneshot::Receiver<()>> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|err| err.into_inner());
+        match in_flight.get_mut(key) {
+            Some(waiters) => {
+                let (sender, receiver) = oneshot::channel();
+                waiters.push(sender);
+                Some(receiver)
+            }
+            None => {
+                in_flight.insert(key.to_string(), Vec::new());
+                None
+            }
+        }
+    }
+}
+
+/// A guard to remove the key in flight and wake up waiters when dropped.
+struct Leader<'a> {
+    in_flight: &'a InFlight,
+    key: &'a str,
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|err| err.into_inner());
+        for waiter in in_flight.remove(self.key).into_iter().flatten() {
+            let _ = waiter.send(());
+        }
+    }
+}
+
+/// Can a stored response be reused for a request with credentials?
+///
+/// A request with "Authorization" or "Cookie" may expect a personalised response,
+/// so only public responses, or shared ones for "Authorization", are reused.
+fn reusable(req_headers: &HeaderMap, resp_headers: &HeaderMap) -> bool {
+    let authorization = req_headers.contains_key(AUTHORIZATION);
+    let cookie = req_headers.contains_key(COOKIE);
+    if !authorization && !cookie {
+        return true;
+    }
+    let cache_control = match resp_headers.typed_get::<CacheControl>() {
+        Some(cache_control) => cache_control,
+        None => return false,
+    };
+    cache_control.public() || (!cookie && cache_control.s_max_age().is_some())
+}
+
+/// Is the status cacheable by default?
+fn cacheable(status: StatusCode) -> bool {
+    match status {
+        StatusCode::OK
+        | StatusCode::NON_AUTHORITATIVE_INFORMATION
+        | StatusCode::NO_CONTENT
+        | StatusCode::MULTIPLE_CHOICES
+        | StatusCode::MOVED_PERMANENTLY
+        | StatusCode::PERMANENT_REDIRECT
+        | StatusCode::NOT_FOUND
+        | StatusCode::METHOD_NOT_ALLOWED
+        | StatusCode::GONE
+        | StatusCode::URI_TOO_LONG
+        | StatusCode::NOT_IMPLEMENTED => true,
+        _ => false,
+    }
+}
+
+/// Get request headers listed in "Vary", None if it contains "*".
+fn vary(headers: &HeaderMap) -> Option<Vec<HeaderName>> {
+    let mut names = Vec::new();
+    for value in headers.get_all(VARY) {
+        let value = value.to_str().ok()?;
+        for name in value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            if name == "*" {
+                return None;
+            }
+            let name = name.parse().ok()?;
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    Some(names)
+}
+
+/// Generate key of a request by method, host and uri.
+///
+/// Host is the authority of uri or "Host" of the request,
+/// as uri of a HTTP/1.1 request is usually only a path.
+fn key<S>(ctx: &Context<S>) -> String {
+    let host = ctx
+        .uri()
+        .authority()
+        .map(|authority| authority.as_str())
+        .or_else(|| {
+            ctx.req
+                .headers
+                .get(HOST)
+                .and_then(|value| value.to_str().ok())
+        })
+        .unwrap_or("");
+    let path = ctx
+        .uri()
+        .path_and_query()
+        .map_or("/", |path_and_query| path_and_query.as_str());
+    format!("{} {}{}", ctx.method(), host.to_ascii_lowercase(), path)
+}
+
+/// Generate key of a variant by values of request headers.
+fn variant(key: &str, names: &[HeaderName], headers: &HeaderMap) -> String {
+    let mut variant = key.to_string();
+    for name in names {
+        variant.push('\n');
+        variant.push_str(name.as_str());
+        variant.push(':');
+        let values: Vec<_> = headers
+            .get_all(name)
+            .iter()
+            .map(|value| String::from_utf8_lossy(value.as_bytes()))
+            .collect();
+        variant.push_str(&values.join(","));
+    }
+    variant
+}
+
+/// Respond with a cached response.
+fn serve<S>(ctx: &mut Context<S>, cached: CachedResponse) {
+    let age = SystemTime::now()
+        .duration_since(cached.stored_at)
+        .map(|age| age.as_secs())
+        .unwrap_or(0);
+    ctx.resp.status = cached.status;
+    ctx.resp.headers.extend(cached.headers);
+    ctx.resp.headers.insert(AGE, HeaderValue::from(age));
+    ctx.resp.body = Body::once(cached.body);
+}
+
+#[async_trait(?Send)]
+impl<'a, S, C: CacheStore> Middleware<'a, S> for Cache<C> {
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        if ctx.method() != Method::GET && ctx.method() != Method::HEAD {
+            return next.await;
+        }
+        let request_control = ctx.req.headers.typed_get::<CacheControl>();
+        if request_control
+            .as_ref()
+            .map_or(false, CacheControl::no_store)
+        {
+            return next.await;
+        }
+        let key = key(ctx);
+        if request_control
+            .as_ref()
+            .map_or(false, CacheControl::no_cache)
+        {
+            next.await?;
+            return self.store(key, ctx).await;
+        }
+        if let Some(cached) = self.lookup(&key, &ctx.req.headers).await {
+            serve(ctx, cached);
+            return Ok(());
+        }
+        if let Some(receiver) = self.enter(&key) {
+            let _ = receiver.await;
+            if let Some(cached) = self.lookup(&key, &ctx.req.headers).await {
+                serve(ctx, cached);
+                return Ok(());
+            }
+            next.await?;
+            return self.store(key, ctx).await;
+        }
+        let _leader = Leader {
+            in_flight: &self.in_flight,
+            key: &key,
+        };
+        next.await?;
+        self.store(key.clone(), ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cache;
+    use crate::http::header::{
+        ACCEPT_LANGUAGE, AGE, CACHE_CONTROL, COOKIE, HOST, SET_COOKIE, VARY,
+    };
+    use crate::http::StatusCode;
+    use crate::test::Client;
+    use crate::{App, Context};
+    use async_std::task::sleep;
+    use futures::future::join_all;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[async_std::test]
+    async fn hit() -> Result<(), Box<dyn std::error::Error>> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let count = COUNTER.fetch_add(1, Ordering::SeqCst);
+            ctx.resp
+                .headers
+                .insert(CACHE_CONTROL, "max-age=60".parse()?);
+            ctx.resp.write(count.to_string());
+            Ok(())
+        }
+        let client = Client::new(App::new().gate(Cache::new()).end(end));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert!(resp.headers.get(AGE).is_none());
+        assert_eq!("0", resp.text().await?);
+
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("0", resp.headers[AGE]);
+        assert_eq!("0", resp.text().await?);
+
+        let resp = client.get("/other").send().await;
+        assert_eq!("1", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(CACHE_CONTROL, "no-cache")
+            .send()
+            .await;
+        assert_eq!("2", resp.text().await?);
+        let resp = client.get("/").send().await;
+        assert_eq!("2", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(CACHE_CONTROL, "no-store")
+            .send()
+            .await;
+        assert_eq!("3", resp.text().await?);
+
+        let resp = client.post("/").send().await;
+        assert_eq!("4", resp.text().await?);
+        let resp = client.post("/").send().await;
+        assert_eq!("5", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn uncacheable() -> Result<(), Box<dyn std::error::Error>> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let count = COUNTER.fetch_add(1, Ordering::SeqCst);
+            match ctx.uri().path() {
+                "/no-store" => {
+                    ctx.resp.headers.insert(CACHE_CONTROL, "no-store".parse()?)
+                }
+                "/private" => ctx
+                    .resp
+                    .headers
+                    .insert(CACHE_CONTROL, "private, max-age=60".parse()?),
+                "/cookie" => ctx.resp.headers.insert(SET_COOKIE, "id=1".parse()?),
+                "/vary" => ctx.resp.headers.insert(VARY, "*".parse()?),
+                "/default" => None,
+                _ => ctx.resp.headers.insert(CACHE_CONTROL, "max-age=0".parse()?),
+            };
+            ctx.resp.write(count.to_string());
+            Ok(())
+        }
+        let client = Client::new(App::new().gate(Cache::new()).end(end));
+        for path in &[
+            "/no-store",
+            "/private",
+            "/cookie",
+            "/vary",
+            "/default",
+            "/stale",
+        ] {
+            let first = client.get(*path).send().await.text().await?;
+            let second = client.get(*path).send().await.text().await?;
+            assert_ne!(first, second);
+        }
+
+        async fn large(ctx: &mut Context) -> crate::Result {
+            ctx.resp.write_reader(&b"Hello, World"[..]);
+            Ok(())
+        }
+        let cache = Cache::new().ttl(Duration::from_secs(60)).max_size(5);
+        let client = Client::new(App::new().gate(cache).end(large));
+        let resp = client.get("/").send().await;
+        assert_eq!("Hello, World", resp.text().await?);
+        let resp = client.get("/").send().await;
+        assert!(resp.headers.get(AGE).is_none());
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn vary() -> Result<(), Box<dyn std::error::Error>> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        async fn end(ctx: &mut Context) -> crate::Result {
+            COUNTER.fetch_add(1, Ordering::SeqCst);
+            let lang = ctx
+                .req
+                .headers
+                .get(ACCEPT_LANGUAGE)
+                .map(|value| value.to_str().unwrap_or("").to_string())
+                .unwrap_or_default();
+            ctx.resp.headers.insert(VARY, "accept-language".parse()?);
+            ctx.resp.write(lang);
+            Ok(())
+        }
+        let cache = Cache::new().ttl(Duration::from_secs(60));
+        let client = Client::new(App::new().gate(cache).end(end));
+        for _ in 0..2 {
+            for lang in &["en", "zh"] {
+                let resp = client.get("/").header(ACCEPT_LANGUAGE, *lang).send().await;
+                assert_eq!(*lang, resp.text().await?);
+            }
+        }
+        assert_eq!(2, COUNTER.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn stampede() -> Result<(), Box<dyn std::error::Error>> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        async fn end(ctx: &mut Context) -> crate::Result {
+            COUNTER.fetch_add(1, Ordering::SeqCst);
+            sleep(Duration::from_millis(100)).await;
+            ctx.resp
+                .headers
+                .insert(CACHE_CONTROL, "max-age=60".parse()?);
+            ctx.resp.write("Hello, World");
+            Ok(())
+        }
+        let client = Client::new(App::new().gate(Cache::new()).end(end));
+        let responses = join_all((0..8).map(|_| client.get("/").send())).await;
+        for resp in responses {
+            assert_eq!(StatusCode::OK, resp.status);
+            assert_eq!("Hello, World", resp.text().await?);
+        }
+        assert_eq!(1, COUNTER.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn cookie() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let user = ctx
+                .req
+                .headers
+                .get(COOKIE)
+                .map(|value| value.to_str().unwrap_or("").to_string())
+                .unwrap_or_else(|| "anonymous".to_string());
+            let cache_control = if ctx.uri().path() == "/public" {
+                "public, max-age=60"
+            } else {
+                "max-age=60"
+            };
+            ctx.resp
+                .headers
+                .insert(CACHE_CONTROL, cache_control.parse()?);
+            ctx.resp.write(user);
+            Ok(())
+        }
+        let client = Client::new(App::new().gate(Cache::new()).end(end));
+        let resp = client.get("/").header(COOKIE, "user=alice").send().await;
+        assert_eq!("user=alice", resp.text().await?);
+        let resp = client.get("/").send().await;
+        assert!(resp.headers.get(AGE).is_none());
+        assert_eq!("anonymous", resp.text().await?);
+        let resp = client.get("/").send().await;
+        assert!(resp.headers.get(AGE).is_some());
+        assert_eq!("anonymous", resp.text().await?);
+        let resp = client.get("/").header(COOKIE, "user=bob").send().await;
+        assert_eq!("user=bob", resp.text().await?);
+
+        let resp = client
+            .get("/public")
+            .header(COOKIE, "user=alice")
+            .send()
+            .await;
+        assert_eq!("user=alice", resp.text().await?);
+        let resp = client
+            .get("/public")
+            .header(COOKIE, "user=bob")
+            .send()
+            .await;
+        assert!(resp.headers.get(AGE).is_some());
+        assert_eq!("user=alice", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn host() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let host = ctx.req.headers[HOST].to_str()?.to_string();
+            ctx.resp
+                .headers
+                .insert(CACHE_CONTROL, "max-age=60".parse()?);
+            ctx.resp.write(host);
+            Ok(())
+        }
+        let client = Client::new(App::new().gate(Cache::new()).end(end));
+        let resp = client.get("/").header(HOST, "a.example.com").send().await;
+        assert_eq!("a.example.com", resp.text().await?);
+        let resp = client.get("/").header(HOST, "b.example.com").send().await;
+        assert!(resp.headers.get(AGE).is_none());
+        assert_eq!("b.example.com", resp.text().await?);
+        let resp = client.get("/").header(HOST, "A.example.com").send().await;
+        assert!(resp.headers.get(AGE).is_some());
+        assert_eq!("a.example.com", resp.text().await?);
+        Ok(())
+    }
+}
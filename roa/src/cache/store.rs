@@ -0,0 +1,206 @@
+use crate::http::header::HeaderName;
+use crate::http::{HeaderMap, HeaderValue, StatusCode};
+use crate::{async_trait, Result};
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A response stored in cache.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// Status code.
+    pub status: StatusCode,
+
+    /// Headers.
+    pub headers: HeaderMap<HeaderValue>,
+
+    /// Body.
+    pub body: Bytes,
+
+    /// The time it's stored.
+    pub stored_at: SystemTime,
+}
+
+/// An entry of cache.
+#[derive(Debug, Clone)]
+pub enum CacheEntry {
+    /// "Vary" of a resource,
+    /// its responses are stored by keys with values of these request headers.
+    Vary(Vec<HeaderName>),
+
+    /// A response.
+    Response(CachedResponse),
+}
+
+/// A backend to store cache entries.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::cache::{CacheEntry, CacheStore};
+/// use roa::{async_trait, Result};
+/// use std::collections::HashMap;
+/// use std::sync::Mutex;
+/// use std::time::Duration;
+///
+/// /// A store never evicting entries.
+/// #[derive(Default)]
+/// struct HashStore(Mutex<HashMap<String, CacheEntry>>);
+///
+/// #[async_trait]
+/// impl CacheStore for HashStore {
+///     async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+///         Ok(self.0.lock().unwrap().get(key).cloned())
+///     }
+///
+///     async fn set(&self, key: String, entry: CacheEntry, _ttl: Duration) -> Result {
+///         self.0.lock().unwrap().insert(key, entry);
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait CacheStore: 'static + Sync + Send {
+    /// Get an entry, None if it doesn't exist or expires.
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>>;
+
+    /// Set an entry with time to live.
+    async fn set(&self, key: String, entry: CacheEntry, ttl: Duration) -> Result;
+}
+
+/// An in-memory store evicting the least recently used entry
+/// when the number of entries exceeds the capacity.
+#[derive(Debug)]
+pub struct LruStore {
+    capacity: usize,
+    inner: Mutex<Lru>,
+}
+
+/// State of `LruStore`.
+#[derive(Debug, Default)]
+struct Lru {
+    tick: u64,
+    entries: HashMap<String, (CacheEntry, Instant, u64)>,
+    recency: BTreeMap<u64, String>,
+}
+
+impl LruStore {
+    /// Construct a store with capacity of entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// Get the number of entries, including expired ones not evicted yet.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Is the store empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        // entries are always consistent, even if another thread panicked.
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Lru {
+    /// Get the next tick of recency.
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn remove(&mut self, key: &str) -> Option<(CacheEntry, Instant, u64)> {
+        let removed = self.entries.remove(key);
+        if let Some((_, _, tick)) = &removed {
+            self.recency.remove(tick);
+        }
+        removed
+    }
+}
+
+#[async_trait]
+impl CacheStore for LruStore {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        let mut lru = self.lock();
+        let tick = lru.next_tick();
+        let (entry, expires, last_tick) = match lru.entries.get_mut(key) {
+            None => return Ok(None),
+            Some((entry, expires, last_tick)) => (entry.clone(), *expires, last_tick),
+        };
+        if expires <= Instant::now() {
+            lru.remove(key);
+            return Ok(None);
+        }
+        let last_tick = std::mem::replace(last_tick, tick);
+        lru.recency.remove(&last_tick);
+        lru.recency.insert(tick, key.to_string());
+        Ok(Some(entry))
+    }
+
+    async fn set(&self, key: String, entry: CacheEntry, ttl: Duration) -> Result {
+        let mut lru = self.lock();
+        let tick = lru.next_tick();
+        lru.remove(&key);
+        lru.recency.insert(tick, key.clone());
+        lru.entries.insert(key, (entry, Instant::now() + ttl, tick));
+        while lru.entries.len() > self.capacity {
+            let oldest = match lru.recency.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            if let Some(key) = lru.recency.remove(&oldest) {
+                lru.entries.remove(&key);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheEntry, CacheStore, LruStore};
+    use std::time::Duration;
+
+    fn entry() -> CacheEntry {
+        CacheEntry::Vary(Vec::new())
+    }
+
+    #[async_std::test]
+    async fn evict() -> crate::Result {
+        let store = LruStore::new(2);
+        let ttl = Duration::from_secs(60);
+        store.set("a".to_string(), entry(), ttl).await?;
+        store.set("b".to_string(), entry(), ttl).await?;
+        assert!(store.get("a").await?.is_some());
+        store.set("c".to_string(), entry(), ttl).await?;
+        assert_eq!(2, store.len());
+        assert!(store.get("a").await?.is_some());
+        assert!(store.get("b").await?.is_none());
+        assert!(store.get("c").await?.is_some());
+
+        store.set("a".to_string(), entry(), ttl).await?;
+        assert_eq!(2, store.len());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn expire() -> crate::Result {
+        let store = LruStore::new(2);
+        store
+            .set("a".to_string(), entry(), Duration::from_millis(10))
+            .await?;
+        assert!(store.get("a").await?.is_some());
+        async_std::task::sleep(Duration::from_millis(20)).await;
+        assert!(store.get("a").await?.is_none());
+        assert!(store.is_empty());
+        Ok(())
+    }
+}
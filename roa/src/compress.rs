@@ -14,7 +14,7 @@
 //! }
 //!
 //! # fn main() -> Result<(), Box<dyn Error>> {
-//! let mut app = App::new().gate(Compress(Level::Fastest)).end(end);
+//! let mut app = App::new().gate(Compress::new().level(Level::Fastest)).end(end);
 //! let (addr, server) = app.run()?;
 //! // server.await
 //! Ok(())
@@ -23,59 +23,283 @@
 
 pub use async_compression::Level;
 
-use crate::http::{header::CONTENT_ENCODING, StatusCode};
-use crate::{async_trait, Context, Middleware, Next, Result, Status};
-use accept_encoding::{parse, Encoding};
+use crate::http::header::{
+    HeaderName, ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH,
+    CONTENT_RANGE, CONTENT_TYPE, ETAG, VARY,
+};
+use crate::http::{HeaderMap, HeaderValue, StatusCode};
+use crate::negotiate::Negotiate;
+use crate::{async_trait, Body, Context, Middleware, Next, Result, State};
 use async_compression::stream::{BrotliEncoder, GzipEncoder, ZlibEncoder, ZstdEncoder};
 
+/// Supported content codings, in order of precedence.
+const ENCODINGS: &[&str] = &["gzip", "deflate", "br", "zstd", "identity"];
+
+/// Default minimum size of `Body::Once` to compress, in bytes.
+const DEFAULT_THRESHOLD: usize = 1024;
+
+/// Media types already compressed, not compressed by default.
+const COMPRESSED_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/avif",
+    "audio/*",
+    "video/*",
+    "font/woff",
+    "font/woff2",
+    "application/zip",
+    "application/gzip",
+    "application/x-gzip",
+    "application/zstd",
+    "application/x-bzip2",
+    "application/x-7z-compressed",
+    "application/x-rar-compressed",
+];
+
 /// A middleware to negotiate with client and compress response body automatically,
 /// supports gzip, deflate, brotli, zstd and identity.
-#[derive(Debug, Copy, Clone)]
-pub struct Compress(pub Level);
+///
+/// A response is not compressed if:
+///
+/// - it already has "Content-Encoding", is partial, or is marked by
+/// "Cache-Control: no-transform";
+/// - it's 304 NOT MODIFIED, 204 NO CONTENT, or its body is empty;
+/// - its body is `Body::Once` smaller than the threshold;
+/// - its "Content-Type" is denied, or not allowed if there are allowed types;
+/// - "Accept-Encoding" is missing or prefers identity.
+///
+/// "Vary: accept-encoding" is appended to the response,
+/// and a strong "ETag" is weakened if the body is compressed.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::compress::{Compress, Level};
+///
+/// let compress = Compress::new()
+///     .level(Level::Best)
+///     .threshold(256)
+///     .encodings(&["br", "gzip"])
+///     .allow("text/*")
+///     .allow("application/json")
+///     .deny("text/event-stream");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Compress {
+    level: Level,
+    threshold: usize,
+    encodings: Vec<&'static str>,
+    allowed: Vec<String>,
+    denied: Vec<String>,
+}
 
 impl Default for Compress {
     fn default() -> Self {
-        Self(Level::Default)
+        Self {
+            level: Level::Default,
+            threshold: DEFAULT_THRESHOLD,
+            encodings: ENCODINGS.to_vec(),
+            allowed: Vec::new(),
+            denied: COMPRESSED_TYPES.iter().map(|typ| typ.to_string()).collect(),
+        }
+    }
+}
+
+impl Compress {
+    /// Construct a middleware with default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set compression level, `Level::Default` by default.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set minimum size of `Body::Once` to compress, 1024 bytes by default.
+    ///
+    /// Size of a streaming body is unknown, so it's always compressed.
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Set content codings in order of preference,
+    /// which is used if client accepts them equally.
+    ///
+    /// "identity" is appended if it's not in the list.
+    ///
+    /// ### Panic
+    ///
+    /// It panics if any of the content codings is not supported.
+    pub fn encodings(mut self, encodings: &[&str]) -> Self {
+        self.encodings = encodings
+            .iter()
+            .map(|encoding| {
+                *ENCODINGS
+                    .iter()
+                    .find(|supported| supported.eq_ignore_ascii_case(encoding))
+                    .unwrap_or_else(|| {
+                        panic!("unsupported content coding: {}", encoding)
+                    })
+            })
+            .collect();
+        if !self.encodings.contains(&"identity") {
+            self.encodings.push("identity");
+        }
+        self
+    }
+
+    /// Allow a media range, like "text/html", "text/*" or "*/*".
+    ///
+    /// Once any media range is allowed,
+    /// only responses with allowed "Content-Type" are compressed.
+    pub fn allow(mut self, media_range: &str) -> Self {
+        self.allowed.push(media_range.trim().to_ascii_lowercase());
+        self
+    }
+
+    /// Deny a media range, like "image/png", "video/*" or "*/*".
+    ///
+    /// Responses with denied "Content-Type" are never compressed.
+    /// Media types already compressed, like images, audio, video and archives,
+    /// are denied by default.
+    pub fn deny(mut self, media_range: &str) -> Self {
+        self.denied.push(media_range.trim().to_ascii_lowercase());
+        self
+    }
+
+    /// Is the media type of response acceptable?
+    fn accepts_type(&self, headers: &HeaderMap) -> bool {
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .split(';')
+                    .next()
+                    .unwrap_or("")
+                    .trim()
+                    .to_ascii_lowercase()
+            });
+        let content_type = match content_type {
+            Some(content_type) => content_type,
+            None => return self.allowed.is_empty(),
+        };
+        let matches = |range: &String| media_matches(range, &content_type);
+        (self.allowed.is_empty() || self.allowed.iter().any(matches))
+            && !self.denied.iter().any(matches)
+    }
+
+    /// Is the body large enough to compress?
+    fn accepts_body(&self, body: &Body) -> bool {
+        match body {
+            Body::Empty => false,
+            Body::Once(bytes) => !bytes.is_empty() && bytes.len() >= self.threshold,
+            Body::Stream(_) => true,
+        }
+    }
+}
+
+/// Does a lowercase media range match a lowercase media type?
+fn media_matches(range: &str, media_type: &str) -> bool {
+    if range == "*/*" || range == media_type {
+        return true;
+    }
+    range.ends_with("/*")
+        && media_type.len() > range.len() - 1
+        && media_type.starts_with(&range[..range.len() - 1])
+}
+
+/// Does a comma-separated header contain the token?
+fn has_token(headers: &HeaderMap, name: HeaderName, token: &str) -> bool {
+    headers.get_all(name).iter().any(|value| {
+        value.to_str().ok().map_or(false, |value| {
+            value
+                .split(',')
+                .any(|item| item.trim().eq_ignore_ascii_case(token))
+        })
+    })
+}
+
+/// Is the response neither encoded, partial nor forbidden to be transformed?
+fn compressible(headers: &HeaderMap) -> bool {
+    !has_token(headers, CACHE_CONTROL, "no-transform")
+        && !headers.contains_key(CONTENT_ENCODING)
+        && !headers.contains_key(CONTENT_RANGE)
+}
+
+/// Append "Vary: accept-encoding" if it's not varied by "Accept-Encoding".
+fn vary(headers: &mut HeaderMap) {
+    if !has_token(headers, VARY, "accept-encoding") && !has_token(headers, VARY, "*") {
+        headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
+/// Weaken a strong "ETag", as the compressed body is not byte-for-byte identical.
+fn weaken(headers: &mut HeaderMap) {
+    let weak = match headers.get(ETAG).and_then(|value| value.to_str().ok()) {
+        Some(etag) if etag.starts_with('"') => format!("W/{}", etag),
+        _ => return,
+    };
+    if let Ok(value) = weak.parse() {
+        headers.insert(ETAG, value);
     }
 }
 
 #[async_trait(?Send)]
-impl<'a, S> Middleware<'a, S> for Compress {
-    #[allow(clippy::trivially_copy_pass_by_ref)]
+impl<'a, S: State> Middleware<'a, S> for Compress {
     #[inline]
     async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
         next.await?;
-        let level = self.0;
-        let best_encoding = parse(&ctx.req.headers)
-            .map_err(|err| Status::new(StatusCode::BAD_REQUEST, err, true))?;
+        if !compressible(&ctx.resp.headers) || !self.accepts_type(&ctx.resp.headers) {
+            return Ok(());
+        }
+        vary(&mut ctx.resp.headers);
+        let encoding = if ctx.req.headers.contains_key(ACCEPT_ENCODING) {
+            ctx.accepts_encoding(&self.encodings).unwrap_or("identity")
+        } else {
+            "identity"
+        };
+        if encoding == "identity" {
+            return Ok(());
+        }
+        weaken(&mut ctx.resp.headers);
+        if ctx.resp.status == StatusCode::NOT_MODIFIED
+            || ctx.resp.status == StatusCode::NO_CONTENT
+            || !self.accepts_body(&ctx.resp.body)
+        {
+            // no body to compress, or not worth compressing
+            return Ok(());
+        }
+        let level = self.level;
         let body = std::mem::take(&mut ctx.resp.body);
-        let content_encoding = match best_encoding {
-            None | Some(Encoding::Gzip) => {
+        match encoding {
+            "gzip" => {
                 ctx.resp
                     .write_stream(GzipEncoder::with_quality(body, level));
-                Encoding::Gzip.to_header_value()
             }
-            Some(Encoding::Deflate) => {
+            "deflate" => {
                 ctx.resp
                     .write_stream(ZlibEncoder::with_quality(body, level));
-                Encoding::Deflate.to_header_value()
             }
-            Some(Encoding::Brotli) => {
+            "br" => {
                 ctx.resp
                     .write_stream(BrotliEncoder::with_quality(body, level));
-                Encoding::Brotli.to_header_value()
             }
-            Some(Encoding::Zstd) => {
+            _ => {
                 ctx.resp
                     .write_stream(ZstdEncoder::with_quality(body, level));
-                Encoding::Zstd.to_header_value()
-            }
-            Some(Encoding::Identity) => {
-                ctx.resp.body = body;
-                Encoding::Identity.to_header_value()
             }
         };
-        ctx.resp.headers.append(CONTENT_ENCODING, content_encoding);
+        // length of the encoded body is unknown
+        ctx.resp.headers.remove(CONTENT_LENGTH);
+        ctx.resp
+            .headers
+            .append(CONTENT_ENCODING, HeaderValue::from_static(encoding));
         Ok(())
     }
 }
@@ -150,7 +374,7 @@ mod tests {
     async fn compress() -> Result<(), Box<dyn std::error::Error>> {
         let app = App::new()
             .gate(Assert(202)) // compressed to 202 bytes
-            .gate(Compress(Level::Fastest))
+            .gate(Compress::new().level(Level::Fastest))
             .gate(Assert(236)) // the size of assets/welcome.html is 236 bytes.
             .end(end);
         let (addr, server) = app.run()?;
@@ -165,4 +389,200 @@ mod tests {
         assert_eq!(236, resp.text().await?.len());
         Ok(())
     }
+
+    #[async_std::test]
+    async fn negotiate_encoding() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::http::header::CONTENT_ENCODING;
+        use crate::test::Client;
+        let client = Client::new(
+            App::new()
+                .gate(Compress::new().level(Level::Fastest))
+                .end(end),
+        );
+        for (accept_encoding, encoding) in &[
+            ("gzip", "gzip"),
+            ("gzip;q=0.5, br", "br"),
+            ("deflate, zstd;q=0.1", "deflate"),
+        ] {
+            let resp = client
+                .get("/")
+                .header(ACCEPT_ENCODING, *accept_encoding)
+                .send()
+                .await;
+            assert_eq!(StatusCode::OK, resp.status);
+            assert_eq!(*encoding, resp.headers[CONTENT_ENCODING]);
+        }
+        let resp = client
+            .get("/")
+            .header(ACCEPT_ENCODING, "br;q=0, identity")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert!(resp.headers.get(CONTENT_ENCODING).is_none());
+
+        let resp = client.get("/").send().await;
+        assert!(resp.headers.get(CONTENT_ENCODING).is_none());
+        assert_eq!(236, resp.bytes().await?.len());
+
+        let client = Client::new(
+            App::new()
+                .gate(Compress::new().encodings(&["zstd", "br"]))
+                .end(end),
+        );
+        for (accept_encoding, encoding) in &[("*", "zstd"), ("gzip, br", "br")] {
+            let resp = client
+                .get("/")
+                .header(ACCEPT_ENCODING, *accept_encoding)
+                .send()
+                .await;
+            assert_eq!(*encoding, resp.headers[CONTENT_ENCODING]);
+        }
+        let resp = client.get("/").header(ACCEPT_ENCODING, "gzip").send().await;
+        assert!(resp.headers.get(CONTENT_ENCODING).is_none());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn filter() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::http::header::{CONTENT_ENCODING, CONTENT_TYPE, VARY};
+        use crate::test::Client;
+        async fn write(ctx: &mut Context) -> crate::Result {
+            let content_type = match ctx.uri().path() {
+                "/png" => "image/png",
+                "/json" => "application/json",
+                _ => "text/plain; charset=utf-8",
+            };
+            ctx.resp.headers.insert(CONTENT_TYPE, content_type.parse()?);
+            ctx.resp.write(vec![b'a'; 512]);
+            Ok(())
+        }
+        let client = Client::new(App::new().gate(Compress::new()).end(write));
+        let resp = client.get("/").header(ACCEPT_ENCODING, "gzip").send().await;
+        assert!(resp.headers.get(CONTENT_ENCODING).is_none());
+        assert_eq!("accept-encoding", resp.headers[VARY]);
+        assert_eq!(512, resp.bytes().await?.len());
+
+        let client =
+            Client::new(App::new().gate(Compress::new().threshold(256)).end(write));
+        for (path, compressed) in &[("/", true), ("/json", true), ("/png", false)] {
+            let resp = client
+                .get(*path)
+                .header(ACCEPT_ENCODING, "gzip")
+                .send()
+                .await;
+            assert_eq!(*compressed, resp.headers.get(CONTENT_ENCODING).is_some());
+        }
+
+        let client = Client::new(
+            App::new()
+                .gate(
+                    Compress::new()
+                        .threshold(0)
+                        .allow("text/*")
+                        .deny("text/plain"),
+                )
+                .end(write),
+        );
+        for path in &["/", "/json", "/png"] {
+            let resp = client
+                .get(*path)
+                .header(ACCEPT_ENCODING, "gzip")
+                .send()
+                .await;
+            assert!(resp.headers.get(CONTENT_ENCODING).is_none());
+        }
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn skip_untransformable() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::http::header::{CACHE_CONTROL, CONTENT_ENCODING};
+        use crate::test::Client;
+        async fn no_transform(ctx: &mut Context) -> crate::Result {
+            ctx.resp
+                .headers
+                .insert(CACHE_CONTROL, "no-cache, No-Transform".parse()?);
+            ctx.resp.write("Hello, World");
+            Ok(())
+        }
+        let client = Client::new(
+            App::new()
+                .gate(Compress::new().level(Level::Fastest))
+                .end(no_transform),
+        );
+        let resp = client.get("/").header(ACCEPT_ENCODING, "gzip").send().await;
+        assert!(resp.headers.get(CONTENT_ENCODING).is_none());
+        assert_eq!("Hello, World", resp.text().await?);
+
+        async fn encoded(ctx: &mut Context) -> crate::Result {
+            ctx.resp.headers.insert(CONTENT_ENCODING, "br".parse()?);
+            ctx.resp.write("Hello, World");
+            Ok(())
+        }
+        let client = Client::new(
+            App::new()
+                .gate(Compress::new().level(Level::Fastest))
+                .end(encoded),
+        );
+        let resp = client.get("/").header(ACCEPT_ENCODING, "gzip").send().await;
+        assert_eq!("br", resp.headers[CONTENT_ENCODING]);
+        assert_eq!("Hello, World", resp.text().await?);
+
+        let client = Client::new(
+            App::new()
+                .gate(Compress::new().level(Level::Fastest))
+                .end(end),
+        );
+        let resp = client
+            .get("/")
+            .header(ACCEPT_ENCODING, "gzip")
+            .header(crate::http::header::RANGE, "bytes=0-9")
+            .send()
+            .await;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status);
+        assert!(resp.headers.get(CONTENT_ENCODING).is_none());
+        assert_eq!(10, resp.bytes().await?.len());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn with_etag() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::etag::Etag;
+        use crate::http::header::{CONTENT_ENCODING, ETAG, IF_NONE_MATCH, VARY};
+        use crate::test::Client;
+        let client = Client::new(
+            App::new()
+                .gate(Compress::new().level(Level::Fastest))
+                .gate(Etag::new())
+                .end(end),
+        );
+        let resp = client.get("/").header(ACCEPT_ENCODING, "gzip").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("gzip", resp.headers[CONTENT_ENCODING]);
+        assert_eq!("accept-encoding", resp.headers[VARY]);
+        let etag = resp.headers[ETAG].clone();
+        assert!(etag.to_str()?.starts_with("W/\""));
+
+        let resp = client
+            .get("/")
+            .header(ACCEPT_ENCODING, "gzip")
+            .header(IF_NONE_MATCH, etag.clone())
+            .send()
+            .await;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status);
+        assert_eq!(etag, resp.headers[ETAG]);
+        assert_eq!("accept-encoding", resp.headers[VARY]);
+        assert!(resp.headers.get(CONTENT_ENCODING).is_none());
+        assert!(resp.bytes().await?.is_empty());
+
+        let resp = client
+            .get("/")
+            .header(ACCEPT_ENCODING, "identity")
+            .header(IF_NONE_MATCH, etag)
+            .send()
+            .await;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status);
+        assert!(resp.headers[ETAG].to_str()?.starts_with('"'));
+        Ok(())
+    }
 }
@@ -113,21 +113,25 @@ pub trait CookieSetter {
 /// A middleware to parse cookie.
 #[inline]
 pub async fn cookie_parser<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
-    if let Some(cookies) = ctx.get(header::COOKIE) {
-        for cookie in cookies
+    for cookie in parse_cookies(ctx) {
+        let name = cookie.name().to_string();
+        ctx.store_scoped(CookieScope, name, cookie);
+    }
+    next.await
+}
+
+/// Parse all cookies in request.
+pub(crate) fn parse_cookies<S>(ctx: &Context<S>) -> Vec<Cookie<'static>> {
+    match ctx.get(header::COOKIE) {
+        Some(cookies) => cookies
             .split(';')
             .map(|cookie| cookie.trim())
             .map(Cookie::parse_encoded)
             .filter_map(|cookie| cookie.ok())
             .map(|cookie| cookie.into_owned())
-            .collect::<Vec<_>>()
-            .into_iter()
-        {
-            let name = cookie.name().to_string();
-            ctx.store_scoped(CookieScope, name, cookie);
-        }
+            .collect(),
+        None => Vec::new(),
     }
-    next.await
 }
 
 impl<S> CookieGetter for Context<S> {
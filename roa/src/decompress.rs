@@ -0,0 +1,339 @@
+//! This module provides a middleware `Decompress`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::decompress::Decompress;
+//! use roa::{App, Context, Request};
+//! use futures::AsyncReadExt;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     let mut data = String::new();
+//!     // throw 413 PAYLOAD TOO LARGE if the decompressed body is too large,
+//!     // or 400 BAD REQUEST if the body is corrupt.
+//!     ctx.req
+//!         .reader()
+//!         .read_to_string(&mut data)
+//!         .await
+//!         .map_err(Request::body_error_status)?;
+//!     ctx.resp.write(data);
+//!     Ok(())
+//! }
+//!
+//! let app = App::new()
+//!     .gate(Decompress::new().max_size(1024 * 1024))
+//!     .end(end);
+//! ```
+
+use crate::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH};
+use crate::http::StatusCode;
+use crate::{
+    async_trait, status, BodyLimitError, Context, MalformedBody, Middleware, Next,
+    Result,
+};
+use async_compression::stream::{BrotliDecoder, GzipDecoder, ZlibDecoder, ZstdDecoder};
+use bytes::Bytes;
+use futures::stream::{BoxStream, Stream, StreamExt, TryStreamExt};
+use std::io;
+use std::pin::Pin;
+use std::task::{self, Poll};
+
+/// Supported content codings.
+const ENCODINGS: &str = "gzip, deflate, br, zstd";
+
+/// Default max size of decompressed body, 10 MiB.
+const DEFAULT_MAX_SIZE: usize = 10 * 1024 * 1024;
+
+/// A middleware to decompress request body by "Content-Encoding",
+/// supports gzip, deflate, brotli, zstd and identity.
+///
+/// The body read by `Request::stream` or `Request::reader` is decompressed,
+/// and "Content-Encoding" and "Content-Length" are removed.
+///
+/// It throws 415 UNSUPPORTED MEDIA TYPE with "Accept-Encoding"
+/// if any content coding is not supported.
+/// Reading a decompressed body larger than `max_size` returns an io error
+/// of `BodyLimitError::TooLarge`, and reading a corrupt body returns
+/// an io error of `MalformedBody`, which `Request::body_error_status`
+/// converts to 413 PAYLOAD TOO LARGE and 400 BAD REQUEST respectively.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Decompress {
+    max_size: usize,
+}
+
+/// A decoded content coding.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+/// A stream failing if the total size exceeds the limit.
+struct MaxSize<S> {
+    stream: S,
+    max_size: usize,
+    received: usize,
+}
+
+impl Default for Decompress {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+}
+
+impl Decompress {
+    /// Construct a middleware with default max size, 10 MiB.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set max size of decompressed body in bytes.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+impl Encoding {
+    /// Parse a content coding, None if it's identity.
+    fn parse(encoding: &str) -> Result<Option<Self>> {
+        let encoding = encoding.trim();
+        let encoding = if encoding.eq_ignore_ascii_case("gzip")
+            || encoding.eq_ignore_ascii_case("x-gzip")
+        {
+            Encoding::Gzip
+        } else if encoding.eq_ignore_ascii_case("deflate") {
+            Encoding::Deflate
+        } else if encoding.eq_ignore_ascii_case("br") {
+            Encoding::Brotli
+        } else if encoding.eq_ignore_ascii_case("zstd") {
+            Encoding::Zstd
+        } else if encoding.eq_ignore_ascii_case("identity") || encoding.is_empty() {
+            return Ok(None);
+        } else {
+            return Err(status!(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("unsupported content coding: {}", encoding)
+            )
+            .header(ACCEPT_ENCODING, ENCODINGS));
+        };
+        Ok(Some(encoding))
+    }
+
+    /// Decode a stream, errors of decoding are wrapped in `MalformedBody`.
+    fn decode(
+        self,
+        stream: BoxStream<'static, io::Result<Bytes>>,
+    ) -> BoxStream<'static, io::Result<Bytes>> {
+        let stream = match self {
+            Encoding::Gzip => GzipDecoder::new(stream).boxed(),
+            Encoding::Deflate => ZlibDecoder::new(stream).boxed(),
+            Encoding::Brotli => BrotliDecoder::new(stream).boxed(),
+            Encoding::Zstd => ZstdDecoder::new(stream).boxed(),
+        };
+        stream.map_err(malformed).boxed()
+    }
+}
+
+/// Wrap an error of decoding in `MalformedBody`,
+/// errors of receiving body or of inner decoders are passed through.
+fn malformed(err: io::Error) -> io::Error {
+    let passed = err.get_ref().map_or(false, |inner| {
+        inner.is::<hyper::Error>() || inner.is::<MalformedBody>()
+    });
+    if passed {
+        err
+    } else {
+        MalformedBody::new(err).into()
+    }
+}
+
+impl<S> Stream for MaxSize<S>
+where
+    S: Stream<Item = io::Result<Bytes>> + Unpin,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        match futures::ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+            Some(Ok(data)) => {
+                self.received += data.len();
+                if self.received > self.max_size {
+                    Poll::Ready(Some(
+                        Err(BodyLimitError::TooLarge(self.max_size).into()),
+                    ))
+                } else {
+                    Poll::Ready(Some(Ok(data)))
+                }
+            }
+            item => Poll::Ready(item),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for Decompress {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let mut encodings = Vec::new();
+        for value in ctx.req.headers.get_all(CONTENT_ENCODING) {
+            let value = value
+                .to_str()
+                .map_err(|err| status!(StatusCode::BAD_REQUEST, err))?;
+            for encoding in value.split(',') {
+                if let Some(encoding) = Encoding::parse(encoding)? {
+                    encodings.push(encoding);
+                }
+            }
+        }
+        ctx.req.headers.remove(CONTENT_ENCODING);
+        if encodings.is_empty() {
+            return next.await;
+        }
+        ctx.req.headers.remove(CONTENT_LENGTH);
+        let mut stream = ctx
+            .req
+            .raw_body()
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+            .boxed();
+        // codings are listed in the order they were applied
+        for encoding in encodings.into_iter().rev() {
+            stream = encoding.decode(stream);
+        }
+        ctx.req.set_raw_body(hyper::Body::wrap_stream(MaxSize {
+            stream,
+            max_size: self.max_size,
+            received: 0,
+        }));
+        next.await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decompress;
+    use crate::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+    use crate::http::StatusCode;
+    use crate::test::Client;
+    use crate::{App, Context, Request};
+    use async_compression::stream::{BrotliEncoder, GzipEncoder, ZstdEncoder};
+    use bytes::Bytes;
+    use futures::stream::{once, TryStreamExt};
+    use futures::AsyncReadExt;
+    use std::io;
+
+    async fn echo(ctx: &mut Context) -> crate::Result {
+        assert!(ctx.req.headers.get(CONTENT_ENCODING).is_none());
+        let mut data = Vec::new();
+        ctx.req
+            .reader()
+            .read_to_end(&mut data)
+            .await
+            .map_err(Request::body_error_status)?;
+        ctx.resp.write(data);
+        Ok(())
+    }
+
+    fn data(data: impl Into<Bytes>) -> impl futures::Stream<Item = io::Result<Bytes>> {
+        once(futures::future::ok(data.into()))
+    }
+
+    async fn concat(
+        stream: impl futures::Stream<Item = io::Result<Bytes>>,
+    ) -> io::Result<Vec<u8>> {
+        stream
+            .try_fold(Vec::new(), |mut body, chunk| {
+                body.extend_from_slice(&chunk);
+                futures::future::ok(body)
+            })
+            .await
+    }
+
+    #[async_std::test]
+    async fn decompress() -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().gate(Decompress::new()).end(echo));
+        let gzip = concat(GzipEncoder::new(data("Hello, World"))).await?;
+        let resp = client
+            .post("/")
+            .header(CONTENT_ENCODING, "gzip")
+            .body(gzip)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hello, World", resp.text().await?);
+
+        let zstd = concat(ZstdEncoder::new(data("Hello, World"))).await?;
+        let resp = client
+            .post("/")
+            .header(CONTENT_ENCODING, "zstd")
+            .body(zstd)
+            .send()
+            .await;
+        assert_eq!("Hello, World", resp.text().await?);
+
+        let chained =
+            concat(BrotliEncoder::new(GzipEncoder::new(data("Hello")))).await?;
+        let resp = client
+            .post("/")
+            .header(CONTENT_ENCODING, "gzip, br")
+            .body(chained)
+            .send()
+            .await;
+        assert_eq!("Hello", resp.text().await?);
+
+        let resp = client
+            .post("/")
+            .header(CONTENT_ENCODING, "identity")
+            .body("Hello")
+            .send()
+            .await;
+        assert_eq!("Hello", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn unsupported() -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().gate(Decompress::new()).end(echo));
+        let resp = client
+            .post("/")
+            .header(CONTENT_ENCODING, "compress")
+            .body("Hello")
+            .send()
+            .await;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status);
+        assert_eq!("gzip, deflate, br, zstd", resp.headers[ACCEPT_ENCODING]);
+        assert_eq!("unsupported content coding: compress", resp.text().await?);
+
+        let resp = client
+            .post("/")
+            .header(CONTENT_ENCODING, "gzip")
+            .body("Hello")
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn bomb() -> Result<(), Box<dyn std::error::Error>> {
+        let client =
+            Client::new(App::new().gate(Decompress::new().max_size(1024)).end(echo));
+        let bomb = concat(GzipEncoder::new(data(vec![0; 1024 * 1024]))).await?;
+        assert!(bomb.len() < 1024 * 1024);
+        let resp = client
+            .post("/")
+            .header(CONTENT_ENCODING, "gzip")
+            .body(bomb)
+            .send()
+            .await;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status);
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+//! This module provides a middleware `Etag`,
+//! which generates "ETag" of response and handles conditional requests.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::etag::Etag;
+//! use roa::{App, Context};
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     ctx.resp.write(r#"{"name":"Hexilee"}"#);
+//!     Ok(())
+//! }
+//!
+//! let app = App::new().gate(Etag::new()).end(end);
+//! ```
+
+use crate::http::header::CONTENT_LENGTH;
+use crate::http::{Method, StatusCode};
+use crate::{async_trait, throw, Body, Context, Middleware, Next, Result};
+use headers::{ETag, HeaderMapExt, IfMatch, IfNoneMatch};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+/// A middleware to generate "ETag" and handle conditional requests.
+///
+/// After the following middlewares and endpoints, it takes "ETag" set by them,
+/// or generates one by hashing the body if it's `Body::Once`;
+/// a successful response without "ETag" or with a streaming body is not affected.
+///
+/// Then it compares "ETag" with "If-Match" and "If-None-Match":
+///
+/// - "If-Match" mismatches: 412 PRECONDITION FAILED.
+/// - "If-None-Match" matches: 304 NOT MODIFIED with body stripped for GET and HEAD,
+/// 412 PRECONDITION FAILED for other methods.
+///
+/// As the conditions are checked after endpoints, an unsafe method is already executed
+/// when it responds 412, endpoints should check preconditions themselves
+/// if the effects must not be applied.
+///
+/// It should be mounted after `Compress`,
+/// so "ETag" is generated by the body before compression.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::compress::Compress;
+/// use roa::etag::Etag;
+/// use roa::{App, Context};
+///
+/// async fn end(ctx: &mut Context) -> roa::Result {
+///     ctx.resp.write("Hello, World");
+///     Ok(())
+/// }
+///
+/// let app = App::new()
+///     .gate(Compress::default())
+///     .gate(Etag::new().weak(true))
+///     .end(end);
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Etag {
+    weak: bool,
+}
+
+impl Etag {
+    /// Construct a middleware generating strong "ETag".
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generate weak "ETag" or not.
+    pub fn weak(mut self, weak: bool) -> Self {
+        self.weak = weak;
+        self
+    }
+
+    /// Generate "ETag" by length and hash of data.
+    fn generate(self, data: &[u8]) -> ETag {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(data);
+        let prefix = if self.weak { "W/" } else { "" };
+        format!("{}\"{:x}-{:x}\"", prefix, data.len(), hasher.finish())
+            .parse()
+            .expect("generated etag must be valid")
+    }
+}
+
+/// Strip body and respond 412 PRECONDITION FAILED.
+fn precondition_failed<S>(ctx: &mut Context<S>) -> Result {
+    ctx.resp.body = Body::empty();
+    ctx.resp.headers.remove(CONTENT_LENGTH);
+    throw!(StatusCode::PRECONDITION_FAILED)
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for Etag {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        next.await?;
+        if !ctx.resp.status.is_success() {
+            return Ok(());
+        }
+        let etag = match ctx.resp.headers.typed_get::<ETag>() {
+            Some(etag) => etag,
+            None => match &ctx.resp.body {
+                Body::Once(data) => {
+                    let etag = self.generate(data);
+                    ctx.resp.headers.typed_insert(etag.clone());
+                    etag
+                }
+                _ => return Ok(()),
+            },
+        };
+        if let Some(if_match) = ctx.req.headers.typed_get::<IfMatch>() {
+            if !if_match.precondition_passes(&etag) {
+                return precondition_failed(ctx);
+            }
+        }
+        if let Some(if_none_match) = ctx.req.headers.typed_get::<IfNoneMatch>() {
+            if !if_none_match.precondition_passes(&etag) {
+                if ctx.method() != Method::GET && ctx.method() != Method::HEAD {
+                    return precondition_failed(ctx);
+                }
+                ctx.resp.status = StatusCode::NOT_MODIFIED;
+                ctx.resp.body = Body::empty();
+                ctx.resp.headers.remove(CONTENT_LENGTH);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Etag;
+    use crate::http::header::{ETAG, IF_MATCH, IF_NONE_MATCH};
+    use crate::http::StatusCode;
+    use crate::test::Client;
+    use crate::{App, Context};
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        ctx.resp.write("Hello, World");
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn generate() -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().gate(Etag::new()).end(end));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let etag = resp.headers[ETAG].to_str()?.to_string();
+        assert!(etag.starts_with("\"c-"));
+        assert_eq!("Hello, World", resp.text().await?);
+
+        let client = Client::new(App::new().gate(Etag::new().weak(true)).end(end));
+        let resp = client.get("/").send().await;
+        assert_eq!(format!("W/{}", etag), resp.headers[ETAG]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn not_modified() -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().gate(Etag::new()).end(end));
+        let etag = client.get("/").send().await.headers[ETAG].clone();
+        let resp = client
+            .get("/")
+            .header(IF_NONE_MATCH, format!("\"other\", {}", etag.to_str()?))
+            .send()
+            .await;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status);
+        assert_eq!(etag, resp.headers[ETAG]);
+        assert_eq!("", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(IF_NONE_MATCH, "\"other\"")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+
+        let resp = client.post("/").header(IF_NONE_MATCH, "*").send().await;
+        assert_eq!(StatusCode::PRECONDITION_FAILED, resp.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn precondition() -> Result<(), Box<dyn std::error::Error>> {
+        async fn put(ctx: &mut Context) -> crate::Result {
+            ctx.resp.headers.insert(ETAG, "\"v2\"".parse()?);
+            ctx.resp.write("updated");
+            Ok(())
+        }
+        let client = Client::new(App::new().gate(Etag::new()).end(put));
+        let resp = client.put("/").header(IF_MATCH, "\"v1\"").send().await;
+        assert_eq!(StatusCode::PRECONDITION_FAILED, resp.status);
+        assert_eq!("", resp.text().await?);
+
+        let resp = client.put("/").header(IF_MATCH, "\"v2\"").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("updated", resp.text().await?);
+
+        let resp = client.put("/").header(IF_MATCH, "W/\"v2\"").send().await;
+        assert_eq!(StatusCode::PRECONDITION_FAILED, resp.status);
+        Ok(())
+    }
+}
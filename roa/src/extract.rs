@@ -0,0 +1,657 @@
+//! This module provides typed extractors and an endpoint wrapper `extract`.
+//!
+//! An endpoint function can declare typed arguments after the context,
+//! each argument is extracted from the context by `FromContext` before the function is called.
+//! Extraction failures are thrown as 400 BAD REQUEST with messages.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::extract::{extract, Header, Json, Path};
+//! use roa::router::{Router, post};
+//! use roa::{App, Context, Result};
+//! use roa::http::StatusCode;
+//! use roa::test::Client;
+//! use headers::UserAgent;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! async fn create(
+//!     ctx: &mut Context,
+//!     Path(group): Path<u64>,
+//!     Header(agent): Header<UserAgent>,
+//!     Json(user): Json<User>,
+//! ) -> Result {
+//!     ctx.resp.write(format!("{} joins group {} by {}", user.name, group, agent));
+//!     Ok(())
+//! }
+//!
+//! #[async_std::main]
+//! async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+//!     let router = Router::new().on("/:group/user", post(extract(create)));
+//!     let client = Client::new(App::new().end(router.routes("/")?));
+//!     let resp = client
+//!         .post("/0/user")
+//!         .header("user-agent", "roa")
+//!         .body(r#"{"name": "Hexilee"}"#)
+//!         .send()
+//!         .await;
+//!     assert_eq!(StatusCode::OK, resp.status);
+//!     assert_eq!("Hexilee joins group 0 by roa", resp.text().await?);
+//!
+//!     let resp = client.post("/x/user").send().await;
+//!     assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+//!     Ok(())
+//! }
+//! ```
+
+#[cfg(feature = "router")]
+mod params;
+
+use crate::http::header::HeaderName;
+use crate::http::StatusCode;
+use crate::{async_trait, Context, Endpoint, Responder, Result, Status};
+use headers::HeaderMapExt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+#[cfg(any(feature = "router", feature = "json", feature = "urlencoded"))]
+use serde::de::DeserializeOwned;
+
+#[cfg(any(feature = "json", feature = "urlencoded"))]
+use crate::{body::PowerBody, State};
+
+#[cfg(feature = "json")]
+use crate::http::header::{HeaderValue, CONTENT_TYPE};
+
+#[cfg(feature = "json")]
+use serde::Serialize;
+
+#[cfg(feature = "cookies")]
+use crate::cookie::{parse_cookies, Cookie};
+
+#[cfg(feature = "cookies")]
+use std::collections::HashMap;
+
+/// A type that can be extracted from context.
+///
+/// ### Example
+/// ```rust
+/// use roa::extract::FromContext;
+/// use roa::{async_trait, Context, Result, State};
+/// use roa::forward::Forward;
+///
+/// struct ClientIp(String);
+///
+/// #[async_trait(?Send)]
+/// impl<S: State> FromContext<S> for ClientIp {
+///     async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+///         Ok(ClientIp(ctx.client_ip().to_string()))
+///     }
+/// }
+/// ```
+#[async_trait(?Send)]
+pub trait FromContext<S = ()>: Sized {
+    /// Extract from context.
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self>;
+}
+
+/// A function with context and extracted arguments.
+///
+/// It's implemented for functions with signature like
+/// `async fn(&mut Context<S>, A1, A2, ...) -> impl Responder<S>`, up to six arguments.
+pub trait Handler<'a, S, Args>: 'static + Sync + Send {
+    /// Output of the future, will be written to response.
+    type Output;
+
+    /// Future returned by the handler.
+    type Future: 'a + Future<Output = Self::Output>;
+
+    /// Call this handler.
+    fn call(&self, ctx: &'a mut Context<S>, args: Args) -> Self::Future;
+}
+
+/// An endpoint wrapper of a handler, constructed by `extract`.
+pub struct Extract<F, Args> {
+    handler: F,
+    args: PhantomData<fn() -> Args>,
+}
+
+/// Wrap a handler with extracted arguments as an endpoint.
+#[inline]
+pub fn extract<F, Args>(handler: F) -> Extract<F, Args> {
+    Extract {
+        handler,
+        args: PhantomData,
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, F, Args, R> Endpoint<'a, S> for Extract<F, Args>
+where
+    S: 'a,
+    F: for<'b> Handler<'b, S, Args, Output = R>,
+    Args: 'static + FromContext<S>,
+    R: Responder<S>,
+{
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        let args = Args::from_context(ctx).await?;
+        self.handler.call(ctx, args).await.respond(ctx)
+    }
+}
+
+macro_rules! impl_handler {
+    ($($arg:ident),+) => {
+        impl<'a, S, F, Fut, $($arg),+> Handler<'a, S, ($($arg,)+)> for F
+        where
+            S: 'a,
+            F: 'static + Sync + Send + Fn(&'a mut Context<S>, $($arg),+) -> Fut,
+            Fut: 'a + Future,
+        {
+            type Output = Fut::Output;
+            type Future = Fut;
+            #[allow(non_snake_case)]
+            #[inline]
+            fn call(&self, ctx: &'a mut Context<S>, ($($arg,)+): ($($arg,)+)) -> Fut {
+                (self)(ctx, $($arg),+)
+            }
+        }
+
+        #[async_trait(?Send)]
+        impl<S, $($arg),+> FromContext<S> for ($($arg,)+)
+        where
+            $($arg: FromContext<S>),+
+        {
+            #[allow(non_snake_case)]
+            #[inline]
+            async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+                $(let $arg = $arg::from_context(ctx).await?;)+
+                Ok(($($arg,)+))
+            }
+        }
+    };
+}
+
+impl_handler!(A1);
+impl_handler!(A1, A2);
+impl_handler!(A1, A2, A3);
+impl_handler!(A1, A2, A3, A4);
+impl_handler!(A1, A2, A3, A4, A5);
+impl_handler!(A1, A2, A3, A4, A5, A6);
+
+/// Construct a 400 BAD REQUEST status.
+fn bad_request(message: impl ToString) -> Status {
+    Status::new(StatusCode::BAD_REQUEST, message, true)
+}
+
+macro_rules! impl_deref {
+    ($($extractor:ident),*) => {
+        $(
+            impl<T> Deref for $extractor<T> {
+                type Target = T;
+                #[inline]
+                fn deref(&self) -> &Self::Target {
+                    &self.0
+                }
+            }
+
+            impl<T> DerefMut for $extractor<T> {
+                #[inline]
+                fn deref_mut(&mut self) -> &mut Self::Target {
+                    &mut self.0
+                }
+            }
+        )*
+    };
+}
+
+/// An extractor of router parameters, must be used in `Router`.
+///
+/// - Structs and maps are deserialized by parameter names.
+/// - Tuples are deserialized by parameter order.
+/// - Other types are deserialized from the only parameter.
+///
+/// ### Example
+/// ```rust
+/// use roa::extract::Path;
+/// use roa::{Context, Result};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Article {
+///     year: u16,
+///     title: String,
+/// }
+///
+/// // "/:year/:title"
+/// async fn get(ctx: &mut Context, Path(article): Path<Article>) -> Result {
+///     Ok(())
+/// }
+///
+/// // "/:group/:id"
+/// async fn get_user(ctx: &mut Context, Path((group, id)): Path<(String, u64)>) -> Result {
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "router")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "router")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Path<T>(pub T);
+
+/// An extractor of query string, deserialized as "urlencoded form".
+///
+/// ### Example
+/// ```rust
+/// use roa::extract::Query;
+/// use roa::{Context, Result};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Pagination {
+///     page: u64,
+///     size: Option<u64>,
+/// }
+///
+/// async fn list(ctx: &mut Context, Query(pagination): Query<Pagination>) -> Result {
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "urlencoded")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "urlencoded")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Query<T>(pub T);
+
+/// An extractor of request body deserialized as json,
+/// or a responder to write body as "application/json".
+///
+/// ### Example
+/// ```rust
+/// use roa::extract::Json;
+/// use roa::{App, Context, Result};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn echo(ctx: &mut Context, Json(user): Json<User>) -> Result<Json<User>> {
+///     Ok(Json(user))
+/// }
+///
+/// async fn get(ctx: &mut Context) -> Json<User> {
+///     Json(User { name: "Hexilee".to_string() })
+/// }
+///
+/// let app = App::new().end(get);
+/// ```
+#[cfg(feature = "json")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Json<T>(pub T);
+
+/// An extractor of request body, deserialized as "urlencoded form".
+#[cfg(feature = "urlencoded")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "urlencoded")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Form<T>(pub T);
+
+/// An extractor of a typed header, throw 400 BAD REQUEST if it's missing or invalid.
+///
+/// Use `Option<Header<T>>` for an optional header.
+///
+/// ### Example
+/// ```rust
+/// use roa::extract::Header;
+/// use roa::{Context, Result};
+/// use headers::{ContentLength, UserAgent};
+///
+/// async fn get(
+///     ctx: &mut Context,
+///     Header(agent): Header<UserAgent>,
+///     length: Option<Header<ContentLength>>,
+/// ) -> Result {
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Header<T>(pub T);
+
+/// An extractor of request cookies.
+///
+/// Percent-encoded cookies will be decoded.
+///
+/// ### Example
+/// ```rust
+/// use roa::extract::Cookies;
+/// use roa::{Context, Result};
+///
+/// async fn get(ctx: &mut Context, cookies: Cookies) -> Result {
+///     let name = cookies.must_get("name")?.value();
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "cookies")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "cookies")))]
+#[derive(Debug, Clone, Default)]
+pub struct Cookies(HashMap<String, Cookie<'static>>);
+
+#[cfg(feature = "router")]
+impl_deref!(Path);
+
+#[cfg(feature = "urlencoded")]
+impl_deref!(Query, Form);
+
+#[cfg(feature = "json")]
+impl_deref!(Json);
+
+impl_deref!(Header);
+
+#[cfg(feature = "router")]
+#[async_trait(?Send)]
+impl<S, T> FromContext<S> for Path<T>
+where
+    T: DeserializeOwned,
+{
+    #[inline]
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+        let params = crate::router::params(ctx);
+        params::from_params(&params)
+            .map(Path)
+            .map_err(|err| bad_request(format!("{}\ninvalid router parameters", err)))
+    }
+}
+
+#[cfg(feature = "urlencoded")]
+#[async_trait(?Send)]
+impl<S, T> FromContext<S> for Query<T>
+where
+    T: DeserializeOwned,
+{
+    #[inline]
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+        let query = ctx.uri().query().unwrap_or("");
+        serde_urlencoded::from_str(query)
+            .map(Query)
+            .map_err(|err| bad_request(format!("{}\ninvalid query `{}`", err, query)))
+    }
+}
+
+#[cfg(feature = "json")]
+#[async_trait(?Send)]
+impl<S, T> FromContext<S> for Json<T>
+where
+    S: State,
+    T: DeserializeOwned,
+{
+    #[inline]
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+        ctx.read_json().await.map(Json)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<S, T> Responder<S> for Json<T>
+where
+    T: Serialize,
+{
+    #[inline]
+    fn respond(self, ctx: &mut Context<S>) -> Result {
+        ctx.resp.write(serde_json::to_vec(&self.0)?);
+        ctx.resp.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/json; charset=utf-8"),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(feature = "urlencoded")]
+#[async_trait(?Send)]
+impl<S, T> FromContext<S> for Form<T>
+where
+    S: State,
+    T: DeserializeOwned,
+{
+    #[inline]
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+        ctx.read_form().await.map(Form)
+    }
+}
+
+#[async_trait(?Send)]
+impl<S, T> FromContext<S> for Header<T>
+where
+    T: headers::Header,
+{
+    #[inline]
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+        let name: &HeaderName = T::name();
+        if !ctx.req.headers.contains_key(name) {
+            return Err(bad_request(format!("header `{}` is required", name)));
+        }
+        ctx.req
+            .headers
+            .typed_get()
+            .map(Header)
+            .ok_or_else(|| bad_request(format!("invalid header `{}`", name)))
+    }
+}
+
+#[async_trait(?Send)]
+impl<S, T> FromContext<S> for Option<Header<T>>
+where
+    T: headers::Header,
+{
+    #[inline]
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+        if ctx.req.headers.contains_key(T::name()) {
+            Header::from_context(ctx).await.map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(feature = "cookies")]
+impl Cookies {
+    /// Try to get a cookie, return `None` if it not exists.
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&Cookie<'static>> {
+        self.0.get(name)
+    }
+
+    /// Must get a cookie, throw 400 BAD REQUEST if it not exists.
+    #[inline]
+    pub fn must_get(&self, name: &str) -> Result<&Cookie<'static>> {
+        self.get(name)
+            .ok_or_else(|| bad_request(format!("cookie `{}` is required", name)))
+    }
+
+    /// Iterate all cookies.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie<'static>> {
+        self.0.values()
+    }
+}
+
+#[cfg(feature = "cookies")]
+#[async_trait(?Send)]
+impl<S> FromContext<S> for Cookies {
+    #[inline]
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+        Ok(Cookies(
+            parse_cookies(ctx)
+                .into_iter()
+                .map(|cookie| (cookie.name().to_string(), cookie))
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "router",
+    feature = "json",
+    feature = "urlencoded",
+    feature = "cookies"
+))]
+mod tests {
+    use super::{extract, Cookies, Form, Header, Json, Path, Query};
+    use crate::http::StatusCode;
+    use crate::router::{get, post, Router};
+    use crate::test::Client;
+    use crate::{App, Context, Result};
+    use headers::{ContentLength, UserAgent};
+    use serde::{Deserialize, Serialize};
+    use std::error::Error;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct User {
+        id: u64,
+        name: String,
+    }
+
+    #[async_std::test]
+    async fn path() -> std::result::Result<(), Box<dyn Error>> {
+        async fn test(
+            ctx: &mut Context,
+            Path((group, id)): Path<(String, u64)>,
+        ) -> Result {
+            ctx.resp.write(format!("{}:{}", group, id));
+            Ok(())
+        }
+        let router = Router::new().on("/:group/:id", get(extract(test)));
+        let client = Client::new(App::new().end(router.routes("/")?));
+        let resp = client.get("/admin/0").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("admin:0", resp.text().await?);
+
+        let resp = client.get("/admin/x").send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        assert!(resp.text().await?.ends_with("invalid router parameters"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn query() -> std::result::Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context, Query(user): Query<User>) -> Result {
+            ctx.resp.write(user.name);
+            Ok(())
+        }
+        let client = Client::new(App::new().end(extract(test)));
+        let resp = client.get("/?id=0&name=Hexilee").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexilee", resp.text().await?);
+
+        let resp = client.get("/?name=Hexilee").send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn body() -> std::result::Result<(), Box<dyn Error>> {
+        async fn json(_ctx: &mut Context, Json(user): Json<User>) -> Result {
+            assert_eq!("Hexilee", user.name);
+            Ok(())
+        }
+        async fn form(_ctx: &mut Context, Form(user): Form<User>) -> Result {
+            assert_eq!("Hexilee", user.name);
+            Ok(())
+        }
+        let router = Router::new()
+            .on("/json", post(extract(json)))
+            .on("/form", post(extract(form)));
+        let client = Client::new(App::new().end(router.routes("/")?));
+        let user = User {
+            id: 0,
+            name: "Hexilee".to_string(),
+        };
+        let resp = client.post("/json").json(&user).send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let resp = client.post("/form").form(&user).send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let resp = client.post("/json").body("Hexilee").send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn respond_json() -> std::result::Result<(), Box<dyn Error>> {
+        async fn echo(
+            _ctx: &mut Context,
+            Path(id): Path<u64>,
+            Json(user): Json<User>,
+        ) -> Result<(StatusCode, Json<User>)> {
+            Ok((StatusCode::CREATED, Json(User { id, ..user })))
+        }
+        let router = Router::new().on("/:id", post(extract(echo)));
+        let client = Client::new(App::new().end(router.routes("/")?));
+        let resp = client
+            .post("/1")
+            .json(&User {
+                id: 0,
+                name: "Hexilee".to_string(),
+            })
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, resp.status);
+        assert_eq!(
+            "application/json; charset=utf-8",
+            resp.headers["content-type"]
+        );
+        let user: User = resp.json().await?;
+        assert_eq!(1, user.id);
+        assert_eq!("Hexilee", user.name);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn header() -> std::result::Result<(), Box<dyn Error>> {
+        async fn test(
+            ctx: &mut Context,
+            Header(agent): Header<UserAgent>,
+            length: Option<Header<ContentLength>>,
+        ) -> Result {
+            assert!(length.is_none());
+            ctx.resp.write(agent.to_string());
+            Ok(())
+        }
+        let client = Client::new(App::new().end(extract(test)));
+        let resp = client.get("/").header("user-agent", "roa").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("roa", resp.text().await?);
+
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        assert_eq!("header `user-agent` is required", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn cookies() -> std::result::Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context, cookies: Cookies) -> Result {
+            ctx.resp
+                .write(cookies.must_get("name")?.value().to_string());
+            Ok(())
+        }
+        let client = Client::new(App::new().end(extract(test)));
+        let resp = client
+            .get("/")
+            .header("cookie", "id=0; name=Hexi%20Lee")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hexi Lee", resp.text().await?);
+
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        Ok(())
+    }
+}
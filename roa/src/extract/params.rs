@@ -0,0 +1,317 @@
+use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use serde::de::{self, Deserializer, Error as _, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+/// A deserializer of router parameters.
+///
+/// - Structs and maps are deserialized by parameter names.
+/// - Sequences and tuples are deserialized by parameter order.
+/// - Other types are deserialized from the only parameter.
+pub struct ParamsDeserializer<'de>(pub &'de [(String, String)]);
+
+/// A deserializer of a single router parameter,
+/// primitive types are parsed from string.
+struct ParamDeserializer<'de>(&'de str);
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                let value = self.0.parse().map_err(|err| {
+                    Error::custom(format!("`{}` is invalid: {}", self.0, err))
+                })?;
+                visitor.$visit(value)
+            }
+        )*
+    };
+}
+
+impl<'de> ParamsDeserializer<'de> {
+    fn single(self) -> Result<ParamDeserializer<'de>, Error> {
+        match self.0 {
+            [(_, value)] => Ok(ParamDeserializer(value)),
+            params => Err(Error::custom(format!(
+                "expect 1 router parameter, found {}",
+                params.len()
+            ))),
+        }
+    }
+
+    fn values(
+        self,
+    ) -> SeqDeserializer<impl Iterator<Item = ParamDeserializer<'de>>, Error> {
+        SeqDeserializer::new(self.0.iter().map(|(_, value)| ParamDeserializer(value)))
+    }
+}
+
+impl<'de> Deserializer<'de> for ParamsDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(MapDeserializer::new(
+            self.0
+                .iter()
+                .map(|(name, value)| (name.as_str(), ParamDeserializer(value))),
+        ))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(self.values())
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.0.len() != len {
+            return Err(Error::custom(format!(
+                "expect {} router parameters, found {}",
+                len,
+                self.0.len()
+            )));
+        }
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_single! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_option
+        deserialize_identifier deserialize_ignored_any
+    }
+}
+
+impl<'de> Deserializer<'de> for ParamDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ParamDeserializer<'de> {
+    type Deserializer = Self;
+    #[inline]
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+/// Deserialize router parameters.
+pub fn from_params<'de, T>(params: &'de [(String, String)]) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    T::deserialize(ParamsDeserializer(params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_params;
+    use serde::Deserialize;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Article {
+        year: u16,
+        title: String,
+        draft: Option<bool>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Kind {
+        User,
+        Group,
+    }
+
+    #[test]
+    fn single() {
+        let params = params(&[("id", "0")]);
+        assert_eq!(0u64, from_params::<u64>(&params).unwrap());
+        assert_eq!("0", from_params::<String>(&params).unwrap());
+        assert!(from_params::<u64>(&self::params(&[("id", "x")])).is_err());
+        assert!(from_params::<u64>(&self::params(&[])).is_err());
+    }
+
+    #[test]
+    fn tuple() {
+        let params = params(&[("kind", "group"), ("id", "1")]);
+        assert_eq!(
+            (Kind::Group, 1u64),
+            from_params::<(Kind, u64)>(&params).unwrap()
+        );
+        assert!(from_params::<(Kind, u64, u64)>(&params).is_err());
+    }
+
+    #[test]
+    fn structure() {
+        let params = params(&[("title", "router"), ("year", "2020")]);
+        let article: Article = from_params(&params).unwrap();
+        assert_eq!(
+            Article {
+                year: 2020,
+                title: "router".to_string(),
+                draft: None,
+            },
+            article
+        );
+    }
+}
@@ -32,18 +32,48 @@ pub mod jwt;
 #[cfg_attr(feature = "docs", doc(cfg(feature = "compress")))]
 pub mod compress;
 
+#[cfg(feature = "compress")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "compress")))]
+pub mod decompress;
+
+#[cfg(feature = "json")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+pub mod problem;
+
+#[cfg(feature = "sse")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "sse")))]
+pub mod sse;
+
+#[cfg(feature = "file")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "file")))]
+pub mod serve_dir;
+
+#[cfg(feature = "trace")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "trace")))]
+pub mod trace;
+
 pub mod body;
+pub mod cache;
 pub mod cors;
+pub mod etag;
+pub mod extract;
 pub mod forward;
 pub mod logger;
+pub mod negotiate;
 pub mod query;
+pub mod request_id;
 pub mod stream;
+pub mod test;
+
+mod util;
 
 /// Reexport all extension traits.
 pub mod preload {
     pub use crate::body::PowerBody;
     pub use crate::forward::Forward;
+    pub use crate::negotiate::Negotiate;
     pub use crate::query::Query;
+    pub use crate::request_id::RequestId;
 
     #[cfg(feature = "tcp")]
     #[doc(no_inline)]
@@ -61,4 +91,10 @@ pub mod preload {
 
     #[cfg(feature = "router")]
     pub use crate::router::RouterParam;
+
+    #[cfg(feature = "sse")]
+    pub use crate::sse::Sse;
+
+    #[cfg(feature = "trace")]
+    pub use crate::trace::Trace;
 }
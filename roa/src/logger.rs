@@ -1,4 +1,4 @@
-//! This module provides a middleware `logger`.
+//! This module provides a middleware `logger` and a configurable middleware `Logger`.
 //!
 //! ### Example
 //!
@@ -22,24 +22,57 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! An access logger in Apache Combined Log Format:
+//!
+//! ```rust
+//! use roa::logger::{Format, Logger};
+//! use roa::App;
+//!
+//! let app = App::new()
+//!     .gate(Logger::new().format(Format::Combined).skip_path("/health"))
+//!     .end("Hello, World");
+//! ```
 
-use crate::http::Uri;
-use crate::{Context, Executor, JoinHandle, Next, Result};
+use crate::forward::Forward;
+use crate::http::header::{REFERER, USER_AGENT};
+use crate::http::{Uri, Version};
+use crate::request_id::{RequestId, REQUEST_ID};
+use crate::{
+    async_trait, Context, Executor, JoinHandle, Middleware, Next, Result, State,
+};
 use bytes::Bytes;
 use bytesize::ByteSize;
 use futures::task::{self, Poll};
 use futures::{Future, Stream};
 use log::{error, info};
 use roa_core::http::{Method, StatusCode};
+use std::fmt::Write;
 use std::io;
 use std::mem;
+use std::net::IpAddr;
 use std::pin::Pin;
-use std::time::Instant;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Template of Apache Common Log Format.
+const COMMON: &str =
+    r#"{remote_addr} - - [{time}] "{method} {uri} {version}" {status} {bytes}"#;
+
+/// Template of Apache Combined Log Format.
+const COMBINED: &str = r#"{remote_addr} - - [{time}] "{method} {uri} {version}" {status} {bytes} "{referer}" "{user_agent}""#;
+
+/// A task to log when polling is complete.
+trait Log {
+    /// Log with the number of bytes sent.
+    fn log(&mut self, counter: u64) -> JoinHandle<()>;
+}
 
 /// A finite-state machine to log success information in each successful response.
-enum StreamLogger<S> {
+enum StreamLogger<S, T> {
     /// Polling state, as a body stream.
-    Polling { stream: S, task: LogTask },
+    Polling { stream: S, task: T, counter: u64 },
 
     /// Logging state, as a logger future.
     Logging(JoinHandle<()>),
@@ -51,7 +84,6 @@ enum StreamLogger<S> {
 /// A task structure to log when polling is complete.
 #[derive(Clone)]
 struct LogTask {
-    counter: u64,
     method: Method,
     status_code: StatusCode,
     uri: Uri,
@@ -59,11 +91,10 @@ struct LogTask {
     exec: Executor,
 }
 
-impl LogTask {
+impl Log for LogTask {
     #[inline]
-    fn log(&self) -> JoinHandle<()> {
+    fn log(&mut self, counter: u64) -> JoinHandle<()> {
         let LogTask {
-            counter,
             method,
             status_code,
             uri,
@@ -83,9 +114,10 @@ impl LogTask {
     }
 }
 
-impl<S> Stream for StreamLogger<S>
+impl<S, T> Stream for StreamLogger<S, T>
 where
     S: 'static + Send + Send + Unpin + Stream<Item = io::Result<Bytes>>,
+    T: Log + Unpin,
 {
     type Item = io::Result<Bytes>;
 
@@ -94,20 +126,22 @@ where
         cx: &mut task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
         match &mut *self {
-            StreamLogger::Polling { stream, task } => {
-                match futures::ready!(Pin::new(stream).poll_next(cx)) {
-                    Some(Ok(bytes)) => {
-                        task.counter += bytes.len() as u64;
-                        Poll::Ready(Some(Ok(bytes)))
-                    }
-                    None => {
-                        let handler = task.log();
-                        *self = StreamLogger::Logging(handler);
-                        self.poll_next(cx)
-                    }
-                    err => Poll::Ready(err),
+            StreamLogger::Polling {
+                stream,
+                task,
+                counter,
+            } => match futures::ready!(Pin::new(stream).poll_next(cx)) {
+                Some(Ok(bytes)) => {
+                    *counter += bytes.len() as u64;
+                    Poll::Ready(Some(Ok(bytes)))
                 }
-            }
+                None => {
+                    let handler = task.log(*counter);
+                    *self = StreamLogger::Logging(handler);
+                    self.poll_next(cx)
+                }
+                err => Poll::Ready(err),
+            },
 
             StreamLogger::Logging(handler) => {
                 futures::ready!(Pin::new(handler).poll(cx));
@@ -120,6 +154,551 @@ where
     }
 }
 
+/// Format of access log.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Format {
+    /// Apache Common Log Format,
+    /// `{remote_addr} - - [{time}] "{method} {uri} {version}" {status} {bytes}`.
+    Common,
+
+    /// Apache Combined Log Format, Common Log Format with
+    /// `"{referer}" "{user_agent}"`.
+    Combined,
+
+    /// A JSON object in a line, with all fields and "message" of an exposed error.
+    Json,
+
+    /// A custom format string with tokens:
+    ///
+    /// - `{remote_addr}`: ip of client, by `Forward::client_ip`.
+    /// - `{time}`: time the request is received, like `10/Oct/2000:13:55:36 +0000`.
+    /// - `{method}`, `{uri}`, `{path}` and `{version}` of the request.
+    /// - `{status}`: status code of the response, like `200`.
+    /// - `{bytes}`: bytes of the response body sent.
+    /// - `{latency}`: milliseconds from receiving the request to sending the whole body.
+    /// - `{user_agent}` and `{referer}` of the request.
+    /// - `{request_id}`: id by `RequestId::request_id`,
+    ///   or "X-Request-Id" of the response or the request.
+    ///
+    /// Missing values are written as `-`, and `{{` and `}}` are escaped braces.
+    ///
+    /// Like Apache formats, it renders one line per request,
+    /// so message of an exposed error is logged in a separate line.
+    Custom(String),
+}
+
+/// A field of log.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Field {
+    RemoteAddr,
+    Time,
+    Method,
+    Uri,
+    Path,
+    Version,
+    Status,
+    Bytes,
+    Latency,
+    UserAgent,
+    Referer,
+    RequestId,
+}
+
+/// A segment of template.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field(Field),
+}
+
+/// A parsed format.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Template {
+    Segments(Vec<Segment>),
+    Json,
+}
+
+/// Information of a request and its response, to be logged.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Ip of client, by `Forward::client_ip`.
+    pub remote_addr: IpAddr,
+
+    /// Time the request is received.
+    pub time: SystemTime,
+
+    /// Method of the request.
+    pub method: Method,
+
+    /// Uri of the request.
+    pub uri: Uri,
+
+    /// Version of the request.
+    pub version: Version,
+
+    /// Status code of the response.
+    pub status: StatusCode,
+
+    /// Bytes of the response body sent.
+    pub bytes: u64,
+
+    /// Duration from receiving the request to sending the whole body.
+    pub latency: Duration,
+
+    /// "User-Agent" of the request.
+    pub user_agent: Option<String>,
+
+    /// "Referer" of the request.
+    pub referer: Option<String>,
+
+    /// Id by `RequestId::request_id`, or "X-Request-Id" of the response or the request.
+    pub request_id: Option<String>,
+}
+
+/// Filter of records.
+type Filter = dyn 'static + Fn(&Record) -> bool + Sync + Send;
+
+/// Configuration of `Logger`, shared with log tasks.
+struct Config {
+    template: Template,
+    filter: Option<Box<Filter>>,
+    skipped_paths: Vec<String>,
+    sample: usize,
+    counter: AtomicUsize,
+}
+
+/// A configurable middleware to log a line for each request
+/// when the response body is sent.
+///
+/// Successful responses are logged by `log::info!`,
+/// and errors thrown by following middlewares and endpoints are always logged
+/// by `log::error!`, regardless of paths, filter and sampling.
+/// Messages of exposed errors are logged with them, and errors are not modified,
+/// so messages not exposed are left to the error handler.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::logger::{Format, Logger};
+/// use roa::App;
+///
+/// let logger = Logger::new()
+///     .format(Format::Custom(
+///         "{request_id} {method} {path} {status} {bytes} {latency}ms".to_string(),
+///     ))
+///     .skip_path("/static")
+///     .filter(|record| record.status.as_u16() != 404)
+///     .sample(10);
+/// let app = App::new().gate(logger).end("Hello, World");
+/// ```
+pub struct Logger {
+    config: Arc<Config>,
+}
+
+/// A task structure of `Logger` to log when polling is complete.
+struct AccessTask {
+    config: Arc<Config>,
+    record: Record,
+    start: Instant,
+    exec: Executor,
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self {
+            config: Arc::new(Config {
+                template: Template::parse(COMMON).expect("common format must be valid"),
+                filter: None,
+                skipped_paths: Vec::new(),
+                sample: 1,
+                counter: AtomicUsize::new(0),
+            }),
+        }
+    }
+}
+
+impl Logger {
+    /// Construct a logger in Apache Common Log Format.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get mutable configuration, which is shared only by log tasks.
+    fn config(&mut self) -> &mut Config {
+        Arc::get_mut(&mut self.config).expect("logger must not be shared in building")
+    }
+
+    /// Set format of log.
+    ///
+    /// ### Panic
+    ///
+    /// It panics if a custom format string contains an unknown token
+    /// or an unclosed brace.
+    pub fn format(mut self, format: Format) -> Self {
+        self.config().template = match format {
+            Format::Common => Template::parse(COMMON),
+            Format::Combined => Template::parse(COMBINED),
+            Format::Json => Ok(Template::Json),
+            Format::Custom(format) => Template::parse(&format),
+        }
+        .unwrap_or_else(|err| panic!("invalid log format: {}", err));
+        self
+    }
+
+    /// Log a record of successful response only if the filter returns true.
+    pub fn filter(
+        mut self,
+        filter: impl 'static + Fn(&Record) -> bool + Sync + Send,
+    ) -> Self {
+        self.config().filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Don't log successful responses to requests whose path starts with the prefix.
+    pub fn skip_path(mut self, prefix: &str) -> Self {
+        self.config().skipped_paths.push(prefix.to_string());
+        self
+    }
+
+    /// Log only one of every `n` records of successful responses,
+    /// responses with status code greater than or equal to 400 are always sampled.
+    ///
+    /// ### Panic
+    ///
+    /// It panics if `n` is zero.
+    pub fn sample(mut self, n: usize) -> Self {
+        assert!(n > 0, "sample rate must be positive");
+        self.config().sample = n;
+        self
+    }
+}
+
+impl Config {
+    /// Should the record be logged?
+    fn accepts(&self, record: &Record) -> bool {
+        let path = record.uri.path();
+        if self
+            .skipped_paths
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+        {
+            return false;
+        }
+        if let Some(filter) = &self.filter {
+            if !filter(record) {
+                return false;
+            }
+        }
+        record.status.as_u16() >= 400
+            || self.sample == 1
+            || self.counter.fetch_add(1, Ordering::Relaxed) % self.sample == 0
+    }
+
+    /// Render a record in a line, with message of an error only in JSON format.
+    fn render(&self, record: &Record, message: Option<&str>) -> String {
+        match &self.template {
+            Template::Json => json(record, message),
+            Template::Segments(segments) => {
+                let mut line = String::new();
+                for segment in segments {
+                    match segment {
+                        Segment::Literal(literal) => line.push_str(literal),
+                        Segment::Field(field) => write_field(&mut line, *field, record),
+                    }
+                }
+                line
+            }
+        }
+    }
+
+    /// Log a record of error, with message of the error in a separate line
+    /// unless in JSON format.
+    fn log_error(&self, record: &Record, message: Option<&str>) {
+        error!("{}", self.render(record, message));
+        if let (Template::Segments(_), Some(message)) = (&self.template, message) {
+            error!("{} {}: {}", record.method, record.uri, message);
+        }
+    }
+}
+
+impl Template {
+    /// Parse a format string.
+    fn parse(format: &str) -> std::result::Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unclosed token `{{{}`", name)),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(Field::parse(&name)?));
+                }
+                '}' => return Err("unmatched `}`".to_string()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Template::Segments(segments))
+    }
+}
+
+impl Field {
+    /// Parse name of a token.
+    fn parse(name: &str) -> std::result::Result<Self, String> {
+        let field = match name.trim() {
+            "remote_addr" => Field::RemoteAddr,
+            "time" => Field::Time,
+            "method" => Field::Method,
+            "uri" => Field::Uri,
+            "path" => Field::Path,
+            "version" => Field::Version,
+            "status" => Field::Status,
+            "bytes" => Field::Bytes,
+            "latency" => Field::Latency,
+            "user_agent" => Field::UserAgent,
+            "referer" => Field::Referer,
+            "request_id" => Field::RequestId,
+            name => return Err(format!("unknown token `{{{}}}`", name)),
+        };
+        Ok(field)
+    }
+}
+
+/// Write a field of record, missing values are written as `-`.
+fn write_field(line: &mut String, field: Field, record: &Record) {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".into());
+    let _ = match field {
+        Field::RemoteAddr => write!(line, "{}", record.remote_addr),
+        Field::Time => write!(line, "{}", DateTime::from(record.time).clf()),
+        Field::Method => write!(line, "{}", record.method),
+        Field::Uri => write!(line, "{}", record.uri),
+        Field::Path => write!(line, "{}", record.uri.path()),
+        Field::Version => write!(line, "{:?}", record.version),
+        Field::Status => write!(line, "{}", record.status.as_u16()),
+        Field::Bytes if record.bytes == 0 => write!(line, "-"),
+        Field::Bytes => write!(line, "{}", record.bytes),
+        Field::Latency => write!(line, "{}", record.latency.as_millis()),
+        Field::UserAgent => write!(line, "{}", optional(&record.user_agent)),
+        Field::Referer => write!(line, "{}", optional(&record.referer)),
+        Field::RequestId => write!(line, "{}", optional(&record.request_id)),
+    };
+}
+
+/// Render a record as a JSON object.
+fn json(record: &Record, message: Option<&str>) -> String {
+    let string = |value: &str| format!("\"{}\"", escape(value));
+    let optional =
+        |value: Option<&str>| value.map(string).unwrap_or_else(|| "null".into());
+    let mut line = format!(
+        r#"{{"remote_addr":"{}","time":"{}","method":{},"uri":{},"version":"{:?}","status":{},"bytes":{},"latency":{},"user_agent":{},"referer":{},"request_id":{}"#,
+        record.remote_addr,
+        DateTime::from(record.time).rfc3339(),
+        string(record.method.as_str()),
+        string(&record.uri.to_string()),
+        record.version,
+        record.status.as_u16(),
+        record.bytes,
+        record.latency.as_millis(),
+        optional(record.user_agent.as_deref()),
+        optional(record.referer.as_deref()),
+        optional(record.request_id.as_deref()),
+    );
+    if let Some(message) = message {
+        let _ = write!(line, r#","message":{}"#, string(message));
+    }
+    line.push('}');
+    line
+}
+
+/// Escape a JSON string.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A UTC date time.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct DateTime {
+    year: u64,
+    month: u64,
+    day: u64,
+    hour: u64,
+    minute: u64,
+    second: u64,
+}
+
+impl From<SystemTime> for DateTime {
+    fn from(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        // civil from days, http://howardhinnant.github.io/date_algorithms.html
+        let days = secs / 86400 + 719_468;
+        let era = days / 146_097;
+        let doe = days % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        Self {
+            year,
+            month,
+            day,
+            hour: secs % 86400 / 3600,
+            minute: secs % 3600 / 60,
+            second: secs % 60,
+        }
+    }
+}
+
+impl DateTime {
+    /// Format as Common Log Format, like `10/Oct/2000:13:55:36 +0000`.
+    fn clf(self) -> String {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov",
+            "Dec",
+        ];
+        format!(
+            "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+            self.day,
+            MONTHS[self.month as usize - 1],
+            self.year,
+            self.hour,
+            self.minute,
+            self.second
+        )
+    }
+
+    /// Format as RFC 3339, like `2000-10-10T13:55:36Z`.
+    fn rfc3339(self) -> String {
+        format!(
+            "{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+impl Log for AccessTask {
+    fn log(&mut self, counter: u64) -> JoinHandle<()> {
+        let config = self.config.clone();
+        let mut record = self.record.clone();
+        record.bytes = counter;
+        record.latency = self.start.elapsed();
+        self.exec.spawn_blocking(move || {
+            if config.accepts(&record) {
+                info!("{}", config.render(&record, None))
+            }
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S: State> Middleware<'a, S> for Logger {
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let time = SystemTime::now();
+        let start = Instant::now();
+        let result = next.await;
+
+        let header = |value: Option<&crate::http::HeaderValue>| {
+            value
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string)
+        };
+        let mut record = Record {
+            remote_addr: ctx.client_ip(),
+            time,
+            method: ctx.method().clone(),
+            uri: ctx.uri().clone(),
+            version: ctx.version(),
+            status: ctx.status(),
+            bytes: 0,
+            latency: Duration::from_secs(0),
+            user_agent: header(ctx.req.headers.get(USER_AGENT)),
+            referer: header(ctx.req.headers.get(REFERER)),
+            request_id: ctx.request_id().map(|id| id.as_str().to_string()).or_else(
+                || {
+                    header(
+                        ctx.resp
+                            .headers
+                            .get(REQUEST_ID)
+                            .or_else(|| ctx.req.headers.get(REQUEST_ID)),
+                    )
+                },
+            ),
+        };
+        let config = self.config.clone();
+        let exec = ctx.exec.clone();
+
+        match &result {
+            Err(status) => {
+                record.status = status.status_code;
+                record.latency = start.elapsed();
+                // unexposed message is left to the error handler.
+                let message = if status.expose {
+                    Some(status.message.clone())
+                } else {
+                    None
+                };
+                exec.spawn_blocking(move || {
+                    config.log_error(&record, message.as_deref())
+                })
+                .await
+            }
+            Ok(_) => {
+                // logging when body polling complete.
+                let logger = StreamLogger::Polling {
+                    stream: mem::take(&mut ctx.resp.body),
+                    task: AccessTask {
+                        config,
+                        record,
+                        start,
+                        exec,
+                    },
+                    counter: 0,
+                };
+                ctx.resp.write_stream(logger);
+            }
+        }
+        result
+    }
+}
+
 /// A middleware to log information about request and response.
 ///
 /// Based on crate `log`, the log level must be greater than `INFO` to log all information,
@@ -127,27 +706,24 @@ where
 pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
     info!("--> {} {}", ctx.method(), ctx.uri().path());
     let start = Instant::now();
-    let mut result = next.await;
+    let result = next.await;
 
     let method = ctx.method().clone();
     let uri = ctx.uri().clone();
     let exec = ctx.exec.clone();
 
-    match &mut result {
+    match &result {
         Err(status) => {
             let status_code = status.status_code;
+            // unexposed message is left to the error handler.
             let message = if status.expose {
-                status.message.clone()
+                format!("\n{}", status.message)
             } else {
-                // set expose to true; then root status_handler won't log this status.
-                status.expose = true;
-
-                // take unexposed message
-                mem::take(&mut status.message)
+                String::new()
             };
             ctx.exec
                 .spawn_blocking(move || {
-                    error!("<-- {} {} {}\n{}", method, uri, status_code, message,);
+                    error!("<-- {} {} {}{}", method, uri, status_code, message);
                 })
                 .await
         }
@@ -157,16 +733,252 @@ pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
             let logger = StreamLogger::Polling {
                 stream: mem::take(&mut ctx.resp.body),
                 task: LogTask {
-                    counter: 0,
                     method,
                     uri,
                     status_code,
                     start,
                     exec,
                 },
+                counter: 0,
             };
             ctx.resp.write_stream(logger);
         }
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DateTime, Format, Logger, Record};
+    use crate::http::{Method, StatusCode, Version};
+    use crate::request_id::{RequestIdPropagator, REQUEST_ID};
+    use crate::test::Client;
+    use crate::{throw, App, Context, Status};
+    use bytes::Bytes;
+    use futures::stream;
+    use lazy_static::lazy_static;
+    use log::{LevelFilter, Log, Metadata};
+    use std::sync::{Mutex, Once};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    lazy_static! {
+        static ref LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    }
+
+    /// A logger capturing lines.
+    struct Capture;
+
+    impl Log for Capture {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let line = format!("{} {}", record.level(), record.args());
+            LINES.lock().unwrap().push(line);
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Set the capturing logger.
+    fn capture() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&Capture).expect("logger must be set only once");
+            log::set_max_level(LevelFilter::Info);
+        });
+    }
+
+    /// Get captured lines containing the pattern.
+    fn captured(pattern: &str) -> Vec<String> {
+        LINES
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|line| line.contains(pattern))
+            .cloned()
+            .collect()
+    }
+
+    fn access(logger: Logger) -> Logger {
+        logger.format(Format::Custom(
+            "{request_id} {method} {path} {status} {bytes}".to_string(),
+        ))
+    }
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        match ctx.uri().path() {
+            "/stream" => {
+                ctx.resp.write_stream(stream::iter(vec![
+                    Ok(Bytes::from_static(b"Hello")),
+                    Ok(Bytes::from_static(b", World")),
+                ]));
+                Ok(())
+            }
+            "/invalid" => throw!(StatusCode::BAD_REQUEST, "invalid"),
+            _ => Err(
+                Status::new(StatusCode::INTERNAL_SERVER_ERROR, "secret", false)
+                    .code("SECRET"),
+            ),
+        }
+    }
+
+    fn record() -> Record {
+        Record {
+            remote_addr: [127, 0, 0, 1].into(),
+            time: UNIX_EPOCH + Duration::from_secs(971_186_136),
+            method: Method::GET,
+            uri: "/apache_pb.gif?id=1".parse().unwrap(),
+            version: Version::HTTP_11,
+            status: StatusCode::OK,
+            bytes: 2326,
+            latency: Duration::from_millis(12),
+            user_agent: Some("Mozilla/4.08".to_string()),
+            referer: None,
+            request_id: Some("a\"b".to_string()),
+        }
+    }
+
+    fn render(logger: &Logger, message: Option<&str>) -> String {
+        logger.config.render(&record(), message)
+    }
+
+    #[test]
+    fn date_time() {
+        let time = DateTime::from(UNIX_EPOCH + Duration::from_secs(971_186_136));
+        assert_eq!("10/Oct/2000:13:55:36 +0000", time.clf());
+        assert_eq!("2000-10-10T13:55:36Z", time.rfc3339());
+        assert_eq!("1970-01-01T00:00:00Z", DateTime::from(UNIX_EPOCH).rfc3339());
+        let leap = DateTime::from(UNIX_EPOCH + Duration::from_secs(951_782_400));
+        assert_eq!("2000-02-29T00:00:00Z", leap.rfc3339());
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /apache_pb.gif?id=1 HTTP/1.1" 200 2326"#,
+            render(&Logger::new(), None)
+        );
+        assert_eq!(
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /apache_pb.gif?id=1 HTTP/1.1" 200 2326 "-" "Mozilla/4.08""#,
+            render(&Logger::new().format(Format::Combined), None)
+        );
+        let custom = Logger::new().format(Format::Custom(
+            "{{{request_id}}} {method} {path} {status} {latency}ms".to_string(),
+        ));
+        assert_eq!(
+            "{a\"b} GET /apache_pb.gif 200 12ms",
+            render(&custom, Some("bad request"))
+        );
+        assert_eq!(
+            r#"{"remote_addr":"127.0.0.1","time":"2000-10-10T13:55:36Z","method":"GET","uri":"/apache_pb.gif?id=1","version":"HTTP/1.1","status":200,"bytes":2326,"latency":12,"user_agent":"Mozilla/4.08","referer":null,"request_id":"a\"b","message":"bad\nrequest"}"#,
+            render(&Logger::new().format(Format::Json), Some("bad\nrequest"))
+        );
+    }
+
+    #[async_std::test]
+    async fn streamed() -> Result<(), Box<dyn std::error::Error>> {
+        capture();
+        let client = Client::new(
+            App::new()
+                .gate(RequestIdPropagator::new())
+                .gate(access(Logger::new()))
+                .end(end),
+        );
+        let resp = client
+            .get("/stream")
+            .header(REQUEST_ID, "logger-streamed")
+            .send()
+            .await;
+        assert_eq!("Hello, World", resp.text().await?);
+        assert_eq!(
+            vec!["INFO logger-streamed GET /stream 200 12"],
+            captured("logger-streamed")
+        );
+
+        let client = Client::new(
+            App::new()
+                .gate(RequestIdPropagator::new())
+                .gate(access(Logger::new().skip_path("/stream")))
+                .end(end),
+        );
+        let resp = client
+            .get("/stream")
+            .header(REQUEST_ID, "logger-skipped")
+            .send()
+            .await;
+        assert_eq!("Hello, World", resp.text().await?);
+        assert!(captured("logger-skipped").is_empty());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn error() -> Result<(), Box<dyn std::error::Error>> {
+        capture();
+        let logger = Logger::new().skip_path("/").filter(|_| false).sample(100);
+        let app = App::new()
+            .error_handler(|ctx: &mut Context, status: Status| {
+                ctx.resp
+                    .write(format!("{} {}", status.expose, status.message));
+            })
+            .gate(RequestIdPropagator::new())
+            .gate(access(logger))
+            .end(end);
+        let client = Client::new(app);
+        let resp = client
+            .get("/crash")
+            .header(REQUEST_ID, "logger-crash")
+            .send()
+            .await;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status);
+        assert!(resp.text().await?.starts_with("false "));
+        let lines = captured("logger-crash");
+        assert_eq!(vec!["ERROR logger-crash GET /crash 500 -"], lines);
+
+        let resp = client
+            .get("/invalid")
+            .header(REQUEST_ID, "logger-invalid")
+            .send()
+            .await;
+        assert_eq!("true invalid", resp.text().await?);
+        assert_eq!(
+            vec!["ERROR logger-invalid GET /invalid 400 -"],
+            captured("logger-invalid")
+        );
+        assert_eq!(vec!["ERROR GET /invalid: invalid"], captured("/invalid: "));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown token `{host}`")]
+    fn unknown_token() {
+        Logger::new().format(Format::Custom("{host}".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "unclosed token")]
+    fn unclosed_token() {
+        Logger::new().format(Format::Custom("{method".to_string()));
+    }
+
+    #[test]
+    fn accepts() {
+        let logger = Logger::new()
+            .skip_path("/apache")
+            .filter(|record| record.status != StatusCode::NOT_FOUND);
+        let mut filtered = record();
+        assert!(!logger.config.accepts(&filtered));
+        filtered.uri = "/index.html".parse().unwrap();
+        assert!(logger.config.accepts(&filtered));
+        filtered.status = StatusCode::NOT_FOUND;
+        assert!(!logger.config.accepts(&filtered));
+
+        let logger = Logger::new().sample(3);
+        let mut sampled = record();
+        let logged = (0..6).filter(|_| logger.config.accepts(&sampled)).count();
+        assert_eq!(2, logged);
+        sampled.status = StatusCode::INTERNAL_SERVER_ERROR;
+        assert!((0..3).all(|_| logger.config.accepts(&sampled)));
+    }
+}
@@ -0,0 +1,345 @@
+//! This module provides a context extension `Negotiate`,
+//! which is used to negotiate with client by `Accept-*` headers.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::negotiate::Negotiate;
+//! use roa::http::StatusCode;
+//! use roa::{throw, Context, Result};
+//!
+//! async fn get(ctx: &mut Context) -> Result {
+//!     match ctx.accepts(&["json", "html"]) {
+//!         Some("json") => ctx.resp.write(r#"{"name":"Hexilee"}"#),
+//!         Some("html") => ctx.resp.write("<p>Hexilee</p>"),
+//!         _ => throw!(StatusCode::NOT_ACCEPTABLE),
+//!     };
+//!     let language = ctx.accepts_language(&["en", "zh-CN"]).unwrap_or("en");
+//!     println!("language: {}", language);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::http::header::{ACCEPT, ACCEPT_CHARSET, ACCEPT_ENCODING, ACCEPT_LANGUAGE};
+use crate::{Context, State};
+
+/// A context extension to negotiate with client.
+///
+/// Each method returns the most preferred one of offers by q-values of the header,
+/// the earlier offer takes precedence if q-values are equal.
+/// It returns the first offer if the header is not set,
+/// or None if no offer is acceptable.
+pub trait Negotiate {
+    /// Negotiate media type by `Accept`.
+    ///
+    /// An offer can be a full media type like "application/json",
+    /// or a short name like "json", "html" and "text".
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::negotiate::Negotiate;
+    /// use roa::{Context, Result};
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     // Accept: text/*, application/json;q=0.5
+    ///     assert_eq!(Some("html"), ctx.accepts(&["json", "html"]));
+    ///     assert_eq!(Some("json"), ctx.accepts(&["json", "image/png"]));
+    ///     Ok(())
+    /// }
+    /// ```
+    fn accepts<'a>(&self, types: &[&'a str]) -> Option<&'a str>;
+
+    /// Negotiate language by `Accept-Language`.
+    ///
+    /// A language range like "en" matches "en-US".
+    fn accepts_language<'a>(&self, languages: &[&'a str]) -> Option<&'a str>;
+
+    /// Negotiate charset by `Accept-Charset`.
+    fn accepts_charset<'a>(&self, charsets: &[&'a str]) -> Option<&'a str>;
+
+    /// Negotiate content coding by `Accept-Encoding`.
+    ///
+    /// "identity" is always acceptable unless it's excluded explicitly.
+    fn accepts_encoding<'a>(&self, encodings: &[&'a str]) -> Option<&'a str>;
+}
+
+/// Map short name to media type.
+fn media_type(name: &str) -> &str {
+    match name {
+        "json" => "application/json",
+        "html" => "text/html",
+        "text" | "txt" => "text/plain",
+        "xml" => "application/xml",
+        "form" | "urlencoded" => "application/x-www-form-urlencoded",
+        "msgpack" => "application/msgpack",
+        "cbor" => "application/cbor",
+        "yaml" => "application/yaml",
+        "protobuf" => "application/protobuf",
+        name => name,
+    }
+}
+
+/// Parse ranges and q-values of an `Accept-*` header.
+fn preferences(value: &str) -> Vec<(&str, f32)> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let range = params.next().unwrap_or("").trim();
+            if range.is_empty() {
+                return None;
+            }
+            let q = params
+                .filter_map(|param| {
+                    let mut pair = param.splitn(2, '=');
+                    match (pair.next(), pair.next()) {
+                        (Some(name), Some(value)) if name.trim() == "q" => {
+                            value.trim().parse().ok()
+                        }
+                        _ => None,
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+            Some((range, q))
+        })
+        .collect()
+}
+
+/// Get q-value of an offer by the most specific matching range,
+/// None if no range matches.
+fn quality(
+    preferences: &[(&str, f32)],
+    offer: &str,
+    specificity: fn(&str, &str) -> Option<u8>,
+) -> Option<f32> {
+    let mut best: Option<(u8, f32)> = None;
+    for (range, q) in preferences {
+        if let Some(specificity) = specificity(range, offer) {
+            match best {
+                Some((best_specificity, _)) if best_specificity >= specificity => (),
+                _ => best = Some((specificity, *q)),
+            }
+        }
+    }
+    best.map(|(_, q)| q)
+}
+
+/// Choose the most preferred offer.
+fn negotiate<'a>(
+    header: Option<&str>,
+    offers: &[&'a str],
+    specificity: fn(&str, &str) -> Option<u8>,
+    default: fn(&str) -> f32,
+) -> Option<&'a str> {
+    let header = match header {
+        None => return offers.first().cloned(),
+        Some(header) => header,
+    };
+    let preferences = preferences(header);
+    let mut best: Option<(&'a str, f32)> = None;
+    for offer in offers {
+        let q =
+            quality(&preferences, offer, specificity).unwrap_or_else(|| default(offer));
+        match best {
+            _ if q <= 0.0 => (),
+            Some((_, best_q)) if best_q >= q => (),
+            _ => best = Some((offer, q)),
+        }
+    }
+    best.map(|(offer, _)| offer)
+}
+
+/// Specificity of a media range matching a media type.
+fn media_specificity(range: &str, offer: &str) -> Option<u8> {
+    let offer = media_type(offer);
+    let main_type = offer.split('/').next().unwrap_or("");
+    if range.eq_ignore_ascii_case(offer) {
+        Some(2)
+    } else if range.ends_with("/*")
+        && range[..range.len() - 2].eq_ignore_ascii_case(main_type)
+    {
+        Some(1)
+    } else if range == "*/*" {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+/// Specificity of a language range matching a language tag.
+fn language_specificity(range: &str, offer: &str) -> Option<u8> {
+    if range.eq_ignore_ascii_case(offer) {
+        Some(2)
+    } else if offer.len() > range.len()
+        && offer.as_bytes()[range.len()] == b'-'
+        && offer[..range.len()].eq_ignore_ascii_case(range)
+    {
+        Some(1)
+    } else if range == "*" {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+/// Specificity of a token range, like charset or content coding.
+fn token_specificity(range: &str, offer: &str) -> Option<u8> {
+    if range.eq_ignore_ascii_case(offer) {
+        Some(1)
+    } else if range == "*" {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+/// Offers not matching any range are not acceptable.
+fn unacceptable(_offer: &str) -> f32 {
+    0.0
+}
+
+/// "identity" is acceptable by default.
+fn identity(offer: &str) -> f32 {
+    if offer.eq_ignore_ascii_case("identity") {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+impl<S: State> Negotiate for Context<S> {
+    #[inline]
+    fn accepts<'a>(&self, types: &[&'a str]) -> Option<&'a str> {
+        negotiate(self.get(ACCEPT), types, media_specificity, unacceptable)
+    }
+
+    #[inline]
+    fn accepts_language<'a>(&self, languages: &[&'a str]) -> Option<&'a str> {
+        negotiate(
+            self.get(ACCEPT_LANGUAGE),
+            languages,
+            language_specificity,
+            unacceptable,
+        )
+    }
+
+    #[inline]
+    fn accepts_charset<'a>(&self, charsets: &[&'a str]) -> Option<&'a str> {
+        negotiate(
+            self.get(ACCEPT_CHARSET),
+            charsets,
+            token_specificity,
+            unacceptable,
+        )
+    }
+
+    #[inline]
+    fn accepts_encoding<'a>(&self, encodings: &[&'a str]) -> Option<&'a str> {
+        negotiate(
+            self.get(ACCEPT_ENCODING),
+            encodings,
+            token_specificity,
+            identity,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        identity, language_specificity, media_specificity, negotiate, token_specificity,
+        unacceptable, Negotiate,
+    };
+    use crate::http::header::{ACCEPT, ACCEPT_LANGUAGE};
+    use crate::http::StatusCode;
+    use crate::test::Client;
+    use crate::{App, Context};
+
+    fn accepts<'a>(header: &str, offers: &[&'a str]) -> Option<&'a str> {
+        negotiate(Some(header), offers, media_specificity, unacceptable)
+    }
+
+    #[test]
+    fn media_type() {
+        assert_eq!(Some("json"), accepts("application/json", &["html", "json"]));
+        assert_eq!(
+            Some("html"),
+            accepts("text/*, */*;q=0.5", &["json", "html"])
+        );
+        assert_eq!(Some("json"), accepts("*/*", &["json", "html"]));
+        assert_eq!(
+            Some("html"),
+            accepts("application/json;q=0.8, text/html", &["json", "html"])
+        );
+        assert_eq!(
+            Some("text/plain"),
+            accepts("text/*, text/html;q=0", &["text/html", "text/plain"])
+        );
+        assert_eq!(None, accepts("image/png", &["json", "html"]));
+        assert_eq!(None, accepts("application/json;q=0", &["json"]));
+        assert_eq!(
+            Some("json"),
+            negotiate(None, &["json", "html"], media_specificity, unacceptable)
+        );
+    }
+
+    #[test]
+    fn language() {
+        let accepts = |header, offers: &[&'static str]| {
+            negotiate(Some(header), offers, language_specificity, unacceptable)
+        };
+        assert_eq!(Some("en-US"), accepts("en", &["zh-CN", "en-US"]));
+        assert_eq!(
+            Some("zh-CN"),
+            accepts("zh-CN, en;q=0.8", &["en-US", "zh-CN"])
+        );
+        assert_eq!(Some("en"), accepts("fr, *;q=0.1", &["en"]));
+        assert_eq!(None, accepts("en-US", &["en"]));
+        assert_eq!(None, accepts("english", &["en"]));
+    }
+
+    #[test]
+    fn encoding() {
+        let accepts = |header, offers: &[&'static str]| {
+            negotiate(Some(header), offers, token_specificity, identity)
+        };
+        assert_eq!(Some("br"), accepts("gzip;q=0.8, br", &["gzip", "br"]));
+        assert_eq!(Some("gzip"), accepts("*", &["gzip", "br"]));
+        assert_eq!(Some("identity"), accepts("", &["gzip", "identity"]));
+        assert_eq!(Some("identity"), accepts("br", &["gzip", "identity"]));
+        assert_eq!(None, accepts("identity;q=0", &["gzip", "identity"]));
+        assert_eq!(None, accepts("*;q=0", &["gzip", "identity"]));
+    }
+
+    #[async_std::test]
+    async fn negotiate_context() -> Result<(), Box<dyn std::error::Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let media_type = ctx.accepts(&["json", "html"]).unwrap_or("none");
+            let language = ctx.accepts_language(&["en", "zh"]).unwrap_or("none");
+            ctx.resp.write(format!("{} {}", media_type, language));
+            Ok(())
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("json en", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(ACCEPT, "text/html, application/json;q=0.9")
+            .header(ACCEPT_LANGUAGE, "zh-CN, zh;q=0.9, en;q=0.8")
+            .send()
+            .await;
+        assert_eq!("html zh", resp.text().await?);
+
+        let resp = client
+            .get("/")
+            .header(ACCEPT, "image/*")
+            .header(ACCEPT_LANGUAGE, "fr")
+            .send()
+            .await;
+        assert_eq!("none none", resp.text().await?);
+        Ok(())
+    }
+}
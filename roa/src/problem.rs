@@ -0,0 +1,221 @@
+//! This module provides an error handler `render`,
+//! which renders status as RFC 7807 `application/problem+json` or HTML according to `Accept`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::{App, Context, Result, throw};
+//! use roa::problem::render;
+//! use roa::http::StatusCode;
+//! use roa::test::Client;
+//!
+//! async fn end(_ctx: &mut Context) -> Result {
+//!     throw!(StatusCode::BAD_REQUEST, "invalid user")
+//! }
+//!
+//! #[async_std::main]
+//! async fn main() -> std::io::Result<()> {
+//!     let client = Client::new(App::new().error_handler(render).end(end));
+//!     let resp = client.get("/").send().await;
+//!     assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+//!     assert_eq!("application/problem+json", resp.headers["content-type"]);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::http::header::{HeaderValue, CONTENT_TYPE};
+use crate::http::StatusCode;
+use crate::negotiate::Negotiate;
+use crate::util::escape_html;
+use crate::{Context, State, Status};
+use log::error;
+use serde_json::{json, Map, Value};
+
+const PROBLEM_JSON: &str = "application/problem+json";
+const TEXT_HTML: &str = "text/html; charset=utf-8";
+
+/// An error handler rendering status as problem details or HTML.
+///
+/// - If `Accept` prefers "text/html", negotiated by `Negotiate::accepts`,
+///   render a HTML page.
+/// - Else render RFC 7807 problem details in json.
+///
+/// Message, code and details are only rendered if the status is exposed,
+/// otherwise, the status will be logged.
+pub fn render<S: State>(ctx: &mut Context<S>, status: Status) {
+    if !status.expose {
+        let status = status.clone();
+        ctx.exec.spawn_blocking(move || {
+            if status.details.is_empty() {
+                error!("Uncaught status: {}", status)
+            } else {
+                error!("Uncaught status: {}, details: {:?}", status, status.details)
+            }
+        });
+    }
+
+    if status.status_code == StatusCode::NO_CONTENT
+        || status.status_code == StatusCode::NOT_MODIFIED
+    {
+        return;
+    }
+
+    if let Some("html") = ctx.accepts(&[PROBLEM_JSON, "json", "html"]) {
+        ctx.resp
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static(TEXT_HTML));
+        ctx.resp.write(html(&status));
+    } else {
+        let instance = ctx.uri().path().to_string();
+        ctx.resp
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        ctx.resp.write(problem(&status, instance).to_string());
+    }
+}
+
+/// Get the canonical reason of a status code.
+fn title(status_code: StatusCode) -> &'static str {
+    status_code.canonical_reason().unwrap_or("Unknown Status")
+}
+
+/// Construct problem details.
+fn problem(status: &Status, instance: String) -> Value {
+    let mut problem = Map::new();
+    problem.insert("type".into(), json!("about:blank"));
+    problem.insert("title".into(), json!(title(status.status_code)));
+    problem.insert("status".into(), json!(status.status_code.as_u16()));
+    problem.insert("instance".into(), json!(instance));
+    if status.expose {
+        if !status.message.is_empty() {
+            problem.insert("detail".into(), json!(status.message));
+        }
+        if let Some(code) = &status.code {
+            problem.insert("code".into(), json!(code));
+        }
+        if !status.details.is_empty() {
+            problem.insert("details".into(), json!(status.details));
+        }
+    }
+    Value::Object(problem)
+}
+
+/// Construct a HTML page.
+fn html(status: &Status) -> String {
+    let title = format!(
+        "{} {}",
+        status.status_code.as_u16(),
+        title(status.status_code)
+    );
+    let mut body = format!("<h1>{}</h1>", escape_html(&title));
+    if status.expose {
+        if !status.message.is_empty() {
+            body.push_str(&format!("<p>{}</p>", escape_html(&status.message)));
+        }
+        if let Some(code) = &status.code {
+            body.push_str(&format!("<p>Code: {}</p>", escape_html(code)));
+        }
+        if !status.details.is_empty() {
+            body.push_str("<ul>");
+            for (key, value) in status.details.iter() {
+                body.push_str(&format!(
+                    "<li>{}: {}</li>",
+                    escape_html(key),
+                    escape_html(value)
+                ));
+            }
+            body.push_str("</ul>");
+        }
+    }
+    format!(
+        "<!DOCTYPE html><html><head><title>{}</title></head><body>{}</body></html>",
+        escape_html(&title),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::http::header::{ACCEPT, RETRY_AFTER};
+    use crate::http::StatusCode;
+    use crate::test::Client;
+    use crate::{App, Context, Result, Status};
+    use serde_json::Value;
+
+    async fn end(_ctx: &mut Context) -> Result {
+        Err(Status::new(StatusCode::BAD_REQUEST, "invalid <user>", true)
+            .code("INVALID_USER")
+            .detail("name", "name is too long")
+            .header(RETRY_AFTER, "120"))
+    }
+
+    #[async_std::test]
+    async fn problem_json() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().error_handler(render).end(end));
+        let resp = client.get("/user").send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        assert_eq!("application/problem+json", resp.headers["content-type"]);
+        assert_eq!("120", resp.headers[RETRY_AFTER]);
+        let problem: Value = resp.json().await?;
+        assert_eq!("about:blank", problem["type"]);
+        assert_eq!("Bad Request", problem["title"]);
+        assert_eq!(400, problem["status"]);
+        assert_eq!("/user", problem["instance"]);
+        assert_eq!("invalid <user>", problem["detail"]);
+        assert_eq!("INVALID_USER", problem["code"]);
+        assert_eq!("name is too long", problem["details"]["name"]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn html() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().error_handler(render).end(end));
+        let resp = client
+            .get("/user")
+            .header(ACCEPT, "text/html,application/xhtml+xml,*/*;q=0.8")
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        assert_eq!("text/html; charset=utf-8", resp.headers["content-type"]);
+        let text = resp.text().await?;
+        assert!(text.contains("<h1>400 Bad Request</h1>"));
+        assert!(text.contains("<p>invalid &lt;user&gt;</p>"));
+        assert!(text.contains("<li>name: name is too long</li>"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn negotiate() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().error_handler(render).end(end));
+        for (accept, content_type) in &[
+            ("text/*;q=0.5, application/json", "application/problem+json"),
+            ("application/json;q=0.5, text/*", "text/html; charset=utf-8"),
+            ("text/html, application/*", "application/problem+json"),
+            ("text/*, text/html;q=0", "application/problem+json"),
+            ("image/png", "application/problem+json"),
+        ] {
+            let resp = client.get("/").header(ACCEPT, *accept).send().await;
+            assert_eq!(*content_type, resp.headers["content-type"], "{}", accept);
+        }
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn not_exposed() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        async fn end(_ctx: &mut Context) -> Result {
+            Err(Status::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "secret",
+                false,
+            ))
+        }
+        let client = Client::new(App::new().error_handler(render).end(end));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status);
+        let problem: Value = resp.json().await?;
+        assert_eq!("Internal Server Error", problem["title"]);
+        assert!(problem.get("detail").is_none());
+        Ok(())
+    }
+}
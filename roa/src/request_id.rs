@@ -0,0 +1,314 @@
+//! This module provides a middleware `RequestIdPropagator` and a context extension `RequestId`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::request_id::RequestIdPropagator;
+//! use roa::preload::*;
+//! use roa::{App, Context};
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     let id = ctx.request_id().expect("request id must be set");
+//!     ctx.resp.write(format!("request {}", id.as_str()));
+//!     Ok(())
+//! }
+//!
+//! let app = App::new().gate(RequestIdPropagator::new()).end(end);
+//! ```
+
+use crate::http::header::HeaderName;
+use crate::http::HeaderValue;
+use crate::util::random;
+use crate::{async_trait, Context, Middleware, Next, Result, State, Variable};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default header of request id.
+pub const REQUEST_ID: &str = "x-request-id";
+
+/// Max length of a trusted request id from client.
+const MAX_LENGTH: usize = 128;
+
+/// Alphabet of Crockford's base32.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// A private scope.
+struct RequestIdScope;
+
+/// A context extension to get id of the request.
+pub trait RequestId {
+    /// Get id of the request, None if `RequestIdPropagator` is not mounted.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::request_id::RequestIdPropagator;
+    /// use roa::preload::*;
+    /// use roa::{App, Context};
+    /// use log::info;
+    ///
+    /// async fn end(ctx: &mut Context) -> roa::Result {
+    ///     if let Some(id) = ctx.request_id() {
+    ///         info!("query users, request id: {}", id.as_str());
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().gate(RequestIdPropagator::new()).end(end);
+    /// ```
+    fn request_id(&self) -> Option<Variable<'static, String>>;
+}
+
+/// A middleware to read or generate id of each request.
+///
+/// It reads id from "X-Request-Id" (configurable),
+/// or generates a ULID if the header is missing, untrusted or invalid;
+/// a valid id is non-empty visible ASCII of at most 128 bytes.
+///
+/// Then the id is stored in context, which can be got by `RequestId::request_id`,
+/// and is echoed on the response, even if following middlewares or endpoints throw.
+/// A status not exposed carries the id as its "request_id" detail, leaving the message
+/// unchanged, so it's logged with the id by `HttpService::serve` when uncaught.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::request_id::RequestIdPropagator;
+/// use roa::App;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static COUNTER: AtomicUsize = AtomicUsize::new(0);
+///
+/// let propagator = RequestIdPropagator::new()
+///     .header("x-correlation-id")
+///     .trust_incoming(false)
+///     .generator(|| COUNTER.fetch_add(1, Ordering::SeqCst).to_string());
+/// let app = App::new().gate(propagator).end("Hello, World");
+/// ```
+#[derive(Clone)]
+pub struct RequestIdPropagator {
+    header: HeaderName,
+    trust_incoming: bool,
+    generator: Arc<dyn 'static + Fn() -> String + Sync + Send>,
+}
+
+impl Default for RequestIdPropagator {
+    fn default() -> Self {
+        Self {
+            header: HeaderName::from_static(REQUEST_ID),
+            trust_incoming: true,
+            generator: Arc::new(ulid),
+        }
+    }
+}
+
+impl RequestIdPropagator {
+    /// Construct a middleware reading "X-Request-Id" and generating ULIDs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set header of request id.
+    ///
+    /// ### Panic
+    ///
+    /// It panics if the header name is invalid.
+    pub fn header(mut self, name: &str) -> Self {
+        self.header = name.parse().expect("invalid header name");
+        self
+    }
+
+    /// Use id from client or not, true by default.
+    pub fn trust_incoming(mut self, trust: bool) -> Self {
+        self.trust_incoming = trust;
+        self
+    }
+
+    /// Set generator of request id.
+    /// Invalid ids from the generator are replaced by ULIDs.
+    pub fn generator(
+        mut self,
+        generator: impl 'static + Fn() -> String + Sync + Send,
+    ) -> Self {
+        self.generator = Arc::new(generator);
+        self
+    }
+
+    /// Get a trusted id from request, or generate one.
+    fn id<S>(&self, ctx: &Context<S>) -> (String, HeaderValue) {
+        if self.trust_incoming {
+            let incoming = ctx
+                .req
+                .headers
+                .get(&self.header)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| valid(value));
+            if let Some(id) = incoming {
+                return (
+                    id.to_string(),
+                    HeaderValue::from_str(id).expect("id is valid"),
+                );
+            }
+        }
+        let mut id = (self.generator)();
+        if !valid(&id) {
+            id = ulid();
+        }
+        let value = HeaderValue::from_str(&id).expect("id is valid");
+        (id, value)
+    }
+}
+
+/// Is the id non-empty visible ASCII, and not too long?
+fn valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_LENGTH
+        && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Generate a ULID, 48 bits of milliseconds and 80 bits of randomness
+/// encoded by Crockford's base32.
+fn ulid() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0)
+        & ((1 << 48) - 1);
+    let randomness =
+        (u128::from(random()) << 16 | u128::from(random() >> 48)) & ((1 << 80) - 1);
+    let mut value = millis << 80 | randomness;
+    let mut id = [0; 26];
+    for byte in id.iter_mut().rev() {
+        *byte = ALPHABET[(value & 31) as usize];
+        value >>= 5;
+    }
+    id.iter().map(|byte| *byte as char).collect()
+}
+
+impl<S> RequestId for Context<S> {
+    #[inline]
+    fn request_id(&self) -> Option<Variable<'static, String>> {
+        self.load_scoped::<RequestIdScope, String>("id")
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S: State> Middleware<'a, S> for RequestIdPropagator {
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let (id, value) = self.id(ctx);
+        ctx.store_scoped(RequestIdScope, "id", id.clone());
+        ctx.resp.headers.insert(self.header.clone(), value.clone());
+        let mut result = next.await;
+        if let Err(status) = &mut result {
+            status.headers.insert(self.header.clone(), value);
+            if !status.expose {
+                status.details.insert("request_id".to_string(), id);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ulid, RequestIdPropagator, REQUEST_ID};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::test::Client;
+    use crate::{throw, App, Context, Status};
+    use std::collections::HashSet;
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        let id = ctx.request_id().expect("request id must be set");
+        ctx.resp.write(id.as_str().to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn generate() {
+        let ids: HashSet<_> = (0..1000).map(|_| ulid()).collect();
+        assert_eq!(1000, ids.len());
+        for id in ids {
+            assert_eq!(26, id.len());
+            assert!(id.bytes().all(|byte| super::ALPHABET.contains(&byte)));
+        }
+    }
+
+    #[async_std::test]
+    async fn propagate() -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().gate(RequestIdPropagator::new()).end(end));
+        let resp = client.get("/").send().await;
+        let id = resp.headers[REQUEST_ID].to_str()?.to_string();
+        assert_eq!(26, id.len());
+        assert_eq!(id, resp.text().await?);
+
+        let resp = client.get("/").header(REQUEST_ID, "abc-123").send().await;
+        assert_eq!("abc-123", resp.headers[REQUEST_ID]);
+        assert_eq!("abc-123", resp.text().await?);
+
+        let resp = client.get("/").header(REQUEST_ID, "a b").send().await;
+        assert_eq!(26, resp.headers[REQUEST_ID].len());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn config() -> Result<(), Box<dyn std::error::Error>> {
+        let propagator = RequestIdPropagator::new()
+            .header("x-correlation-id")
+            .trust_incoming(false)
+            .generator(|| "generated".to_string());
+        let client = Client::new(App::new().gate(propagator).end(end));
+        let resp = client
+            .get("/")
+            .header("x-correlation-id", "incoming")
+            .send()
+            .await;
+        assert_eq!("generated", resp.headers["x-correlation-id"]);
+        assert!(resp.headers.get(REQUEST_ID).is_none());
+        assert_eq!("generated", resp.text().await?);
+
+        let propagator = RequestIdPropagator::new().generator(String::new);
+        let client = Client::new(App::new().gate(propagator).end(end));
+        let resp = client.get("/").send().await;
+        assert_eq!(26, resp.text().await?.len());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn error() -> Result<(), Box<dyn std::error::Error>> {
+        async fn fail(ctx: &mut Context) -> crate::Result {
+            ctx.resp.headers.clear();
+            throw!(StatusCode::BAD_REQUEST, "invalid")
+        }
+        let client = Client::new(App::new().gate(RequestIdPropagator::new()).end(fail));
+        let resp = client.get("/").header(REQUEST_ID, "abc").send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status);
+        assert_eq!("abc", resp.headers[REQUEST_ID]);
+        assert_eq!("invalid", resp.text().await?);
+
+        async fn crash(_ctx: &mut Context) -> crate::Result {
+            Err(Status::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "crash",
+                false,
+            ))
+        }
+        let app = App::new()
+            .error_handler(|ctx: &mut Context, status: Status| {
+                ctx.resp.write(format!(
+                    "{} {}",
+                    status.details["request_id"], status.message
+                ));
+            })
+            .gate(RequestIdPropagator::new())
+            .end(crash);
+        let resp = Client::new(app)
+            .get("/")
+            .header(REQUEST_ID, "abc")
+            .send()
+            .await;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status);
+        assert_eq!("abc crash", resp.text().await?);
+        Ok(())
+    }
+}
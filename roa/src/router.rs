@@ -1,6 +1,34 @@
 //! This module provides a context extension `RouterParam` and
 //! many endpoint wrappers like `Router`, `Dispatcher` and `Guard`.
 //!
+//! ### Path
+//!
+//! - `/user`: static path.
+//! - `/user/:id`: variable matching a segment.
+//! - `/user/:id<\d+>`: variable constrained by a regex.
+//! - `/user/:id:u64`: variable constrained by a type, integers, floats and `bool` are supported.
+//! - `/:file.:ext`: multiple variables in a segment.
+//! - `/posts/:page?`: optional segment.
+//! - `/static/*{path}`: wildcard matching any non-empty path.
+//!
+//! Static paths take precedence over dynamic paths.
+//! Dynamic paths are compiled into a segment tree and matched segment by segment,
+//! static segments take precedence, then mixed segments like `:file.:ext`,
+//! constrained variables, plain variables and wildcards.
+//! If the rest of path doesn't match, the next candidate segment is tried.
+//! Each node of the tree is tried at most once per request,
+//! so the worst case is linear in the size of the tree instead of the length of path.
+//! Candidates with the same rank are tried in order of registration.
+//!
+//! Dynamic paths only differing in variable names are ambiguous,
+//! `Router::routes` will return an error.
+//!
+//! ### Introspection
+//!
+//! Routes can be listed by `RouteTable::routes`.
+//! Operations can be documented by `Router::doc`,
+//! and `Router::openapi` serves an OpenAPI 3 document of all routes.
+//!
 //! ### Example
 //!
 //! ```rust
@@ -49,7 +77,9 @@
 
 mod endpoints;
 mod err;
+mod openapi;
 mod path;
+mod tree;
 
 #[doc(inline)]
 pub use endpoints::*;
@@ -57,21 +87,42 @@ pub use endpoints::*;
 #[doc(inline)]
 pub use err::RouterError;
 
-use crate::http::StatusCode;
+#[doc(inline)]
+pub use openapi::Operation;
+
+use crate::http::{Method, StatusCode};
 use crate::{
     async_trait, throw, Boxed, Context, Endpoint, EndpointExt, Middleware,
     MiddlewareExt, Result, Shared, Status, Variable,
 };
 use err::Conflict;
-use path::{join_path, standardize_path, Path, RegexPath};
+use openapi::Document;
+use path::{join_path, standardize_path, Path, Segment};
 use percent_encoding::percent_decode_str;
 use radix_trie::Trie;
-use std::convert::AsRef;
+use std::collections::HashMap;
 use std::result::Result as StdResult;
+use std::sync::Arc;
+use tree::Node;
 
 /// A private scope to store and load variables in Context::storage.
 struct RouterScope;
 
+/// A private scope to store and load all router parameters in order.
+struct ParamsScope;
+
+/// A private scope to store and load paths of named routes.
+struct NamesScope;
+
+/// A private scope to store and load path pattern of the matched route.
+struct RouteScope;
+
+/// Paths of named routes.
+type Names = Arc<HashMap<String, Path>>;
+
+/// Router parameters in order of the path.
+type Params = Vec<(String, String)>;
+
 /// A context extension.
 /// This extension must be used in `Router`,
 /// otherwise you cannot get expected router parameters.
@@ -137,18 +188,98 @@ pub trait RouterParam {
     ///
     /// ```
     fn param<'a>(&self, name: &'a str) -> Option<Variable<'a, String>>;
+
+    /// Build url of a named route, with prefixes of `Router::include` and `Router::routes`.
+    ///
+    /// Values of parameters are percent-encoded,
+    /// throw 500 INTERNAL SERVER ERROR if route not exists or parameters mismatch.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::router::{get, Router, RouterParam};
+    /// use roa::http::header::LOCATION;
+    /// use roa::http::StatusCode;
+    /// use roa::{App, Context, Result};
+    ///
+    /// async fn create(ctx: &mut Context) -> Result {
+    ///     let url = ctx.url_for("user", &[("id", "5")])?;
+    ///     assert_eq!("/api/user/5", url);
+    ///     ctx.resp.status = StatusCode::CREATED;
+    ///     ctx.resp.headers.insert(LOCATION, url.parse()?);
+    ///     Ok(())
+    /// }
+    ///
+    /// async fn query(ctx: &mut Context) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// # fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    /// let user = Router::new()
+    ///     .on("/", get(create))
+    ///     .on_named("user", "/:id", get(query));
+    /// let router = Router::new().include("/user", user);
+    /// let app = App::new().end(router.routes("/api")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String>;
+
+    /// Get path pattern of the matched route with prefixes, like `/api/user/:id`,
+    /// return `None` if no route matches.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::router::{Router, RouterParam};
+    /// use roa::{App, Context, Result};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     assert_eq!("/api/user/:id", ctx.route().unwrap().as_str());
+    ///     Ok(())
+    /// }
+    ///
+    /// # fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    /// let router = Router::new().on("/user/:id", end);
+    /// let app = App::new().end(router.routes("/api")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn route(&self) -> Option<Variable<'static, String>>;
 }
 
 /// A builder of `RouteTable`.
 pub struct Router<S> {
     middleware: Shared<S>,
-    endpoints: Vec<(String, Boxed<S>)>,
+    endpoints: Vec<Entry<S>>,
+    openapi: Option<(&'static str, &'static str, &'static str)>,
 }
 
 /// An endpoint to route request by uri path.
 pub struct RouteTable<S> {
-    static_route: Trie<String, Boxed<S>>,
-    dynamic_route: Vec<(RegexPath, Boxed<S>)>,
+    static_route: Trie<String, usize>,
+    dynamic_route: Node<usize>,
+    endpoints: Vec<(String, Boxed<S>)>,
+    shapes: HashMap<Vec<Segment>, String>,
+    names: Names,
+    routes: Vec<Route>,
+}
+
+/// A registered endpoint.
+struct Entry<S> {
+    path: String,
+    name: Option<String>,
+    endpoint: Boxed<S>,
+    operations: Vec<(Method, Operation)>,
+}
+
+/// Information of a route in `RouteTable`.
+#[derive(Clone)]
+pub struct Route {
+    path: Path,
+    methods: Option<Vec<Method>>,
+    name: Option<String>,
+    operations: Vec<(Method, Operation)>,
 }
 
 impl<S> Router<S>
@@ -160,17 +291,68 @@ where
         Self {
             middleware: ().shared(),
             endpoints: Vec::new(),
+            openapi: None,
         }
     }
 
     /// Register a new endpoint.
-    pub fn on(
+    pub fn on(self, path: &'static str, endpoint: impl for<'a> Endpoint<'a, S>) -> Self {
+        self.push(path, None, endpoint)
+    }
+
+    /// Register a new endpoint with a name, to build url by `RouterParam::url_for`.
+    pub fn on_named(
+        self,
+        name: &'static str,
+        path: &'static str,
+        endpoint: impl for<'a> Endpoint<'a, S>,
+    ) -> Self {
+        self.push(path, Some(name.to_string()), endpoint)
+    }
+
+    /// Document an operation of the last registered endpoint.
+    ///
+    /// ### Panics
+    ///
+    /// Panics if no endpoint is registered.
+    pub fn doc(mut self, method: Method, operation: Operation) -> Self {
+        let entry = self
+            .endpoints
+            .last_mut()
+            .expect("Router::doc must be called after an endpoint is registered");
+        entry
+            .operations
+            .retain(|(documented, _)| *documented != method);
+        entry.operations.push((method, operation));
+        self
+    }
+
+    /// Serve OpenAPI document of all routes on a path.
+    ///
+    /// Operations are documented by `Router::doc`.
+    pub fn openapi(
+        mut self,
+        path: &'static str,
+        title: &'static str,
+        version: &'static str,
+    ) -> Self {
+        self.openapi = Some((path, title, version));
+        self
+    }
+
+    fn push(
         mut self,
         path: &'static str,
+        name: Option<String>,
         endpoint: impl for<'a> Endpoint<'a, S>,
     ) -> Self {
-        self.endpoints
-            .push((path.to_string(), self.register(endpoint)));
+        let endpoint = self.register(endpoint);
+        self.endpoints.push(Entry {
+            path: path.to_string(),
+            name,
+            endpoint,
+            operations: Vec::new(),
+        });
         self
     }
 
@@ -181,9 +363,13 @@ where
 
     /// Include another router with prefix.
     pub fn include(mut self, prefix: &'static str, router: Router<S>) -> Self {
-        for (path, endpoint) in router.endpoints {
-            self.endpoints
-                .push((join_path([prefix, path.as_str()]), self.register(endpoint)))
+        for entry in router.endpoints {
+            let endpoint = self.register(entry.endpoint);
+            self.endpoints.push(Entry {
+                path: join_path([prefix, entry.path.as_str()]),
+                endpoint,
+                ..entry
+            })
         }
         self
     }
@@ -193,23 +379,85 @@ where
         let Self {
             middleware,
             endpoints,
+            openapi,
         } = self;
         Self {
             middleware: middleware.chain(next).shared(),
             endpoints,
+            openapi,
         }
     }
 
     /// Build RouteTable with path prefix.
     pub fn routes(self, prefix: &'static str) -> StdResult<RouteTable<S>, RouterError> {
         let mut route_table = RouteTable::default();
-        for (raw_path, endpoint) in self.endpoints {
-            route_table.insert(join_path([prefix, raw_path.as_str()]), endpoint)?;
+        let mut names = HashMap::new();
+        for entry in self.endpoints {
+            let path: Path = join_path([prefix, entry.path.as_str()]).parse()?;
+            if let Some(name) = entry.name.as_ref() {
+                if names.insert(name.clone(), path.clone()).is_some() {
+                    return Err(Conflict::Name(name.clone()).into());
+                }
+            }
+            route_table.routes.push(Route {
+                path: path.clone(),
+                methods: entry.endpoint.allowed_methods(),
+                name: entry.name,
+                operations: entry.operations,
+            });
+            route_table.insert(path, entry.endpoint)?;
+        }
+        route_table.names = Arc::new(names);
+
+        if let Some((path, title, version)) = self.openapi {
+            let document = openapi::document(title, version, &route_table.routes);
+            let endpoint = self
+                .middleware
+                .end(get(Document(document.to_string().into())))
+                .boxed();
+            let path: Path = join_path([prefix, path]).parse()?;
+            route_table.routes.push(Route {
+                path: path.clone(),
+                methods: endpoint.allowed_methods(),
+                name: None,
+                operations: Vec::new(),
+            });
+            route_table.insert(path, endpoint)?;
         }
         Ok(route_table)
     }
 }
 
+impl Route {
+    /// Path pattern, like `/user/:id`.
+    pub fn path(&self) -> String {
+        format!("/{}", self.path.raw().trim_matches('/'))
+    }
+
+    /// Allowed methods, `None` if the endpoint doesn't restrict methods.
+    pub fn methods(&self) -> Option<&[Method]> {
+        self.methods.as_deref()
+    }
+
+    /// Variables in order of the path.
+    pub fn vars(&self) -> Vec<String> {
+        self.path.vars()
+    }
+
+    /// Name registered by `Router::on_named`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Documentation of an operation registered by `Router::doc`.
+    pub fn operation(&self, method: &Method) -> Option<&Operation> {
+        self.operations
+            .iter()
+            .find(|(documented, _)| documented == method)
+            .map(|(_, operation)| operation)
+    }
+}
+
 impl<S> RouteTable<S>
 where
     S: 'static,
@@ -217,28 +465,135 @@ where
     fn new() -> Self {
         Self {
             static_route: Trie::new(),
-            dynamic_route: Vec::new(),
+            dynamic_route: Node::new(),
+            endpoints: Vec::new(),
+            shapes: HashMap::new(),
+            names: Arc::new(HashMap::new()),
+            routes: Vec::new(),
         }
     }
 
     /// Insert endpoint to table.
-    fn insert(
-        &mut self,
-        raw_path: impl AsRef<str>,
-        endpoint: Boxed<S>,
-    ) -> StdResult<(), RouterError> {
-        match raw_path.as_ref().parse()? {
-            Path::Static(path) => {
-                if self.static_route.insert(path.clone(), endpoint).is_some() {
-                    return Err(Conflict::Path(path).into());
+    fn insert(&mut self, path: Path, endpoint: Boxed<S>) -> StdResult<(), RouterError> {
+        // detect ambiguous paths
+        let shapes = path.shapes();
+        for shape in shapes.iter() {
+            if let Some(other) = self.shapes.get(shape) {
+                return Err(if other == path.raw() {
+                    Conflict::Path(other.clone())
+                } else {
+                    Conflict::Ambiguous {
+                        paths: (other.clone(), path.raw().to_string()),
+                    }
                 }
+                .into());
+            }
+        }
+        for shape in shapes {
+            self.shapes.insert(shape, path.raw().to_string());
+        }
+
+        let index = self.endpoints.len();
+        let pattern = format!("/{}", path.raw().trim_matches('/'));
+        match path {
+            Path::Static(path) => {
+                self.static_route.insert(path, index);
+            }
+            Path::Dynamic(dynamic_path) => {
+                self.dynamic_route
+                    .insert(dynamic_path.inner_segments(), index);
             }
-            Path::Dynamic(regex_path) => self.dynamic_route.push((regex_path, endpoint)),
         }
+        self.endpoints.push((pattern, endpoint));
         Ok(())
     }
 }
 
+impl<S> RouteTable<S> {
+    /// Get methods allowed on a path, `None` if no route matches.
+    ///
+    /// All methods are listed if the endpoint doesn't restrict methods.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::router::{get, Router};
+    /// use roa::http::Method;
+    /// use roa::{Context, Result};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// # fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    /// let table = Router::new().on("/user/:id", get(end).delete(end)).routes("/")?;
+    /// assert_eq!(
+    ///     Some(vec![Method::GET, Method::OPTIONS, Method::DELETE, Method::HEAD]),
+    ///     table.allowed_methods("/user/1"),
+    /// );
+    /// assert_eq!(None, table.allowed_methods("/group/1"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn allowed_methods(&self, path: &str) -> Option<Vec<Method>>
+    where
+        S: 'static,
+    {
+        let path = standardize_path(&percent_decode_str(path).decode_utf8().ok()?);
+        let ((_, end), _) = self.find(&path)?;
+        Some(
+            end.allowed_methods()
+                .unwrap_or_else(|| ALL_METHODS.to_vec()),
+        )
+    }
+
+    /// List all routes in order of registration.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::router::{get, Router};
+    /// use roa::http::Method;
+    /// use roa::{Context, Result};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// # fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    /// let table = Router::new()
+    ///     .on_named("user", "/user/:id", get(end).delete(end))
+    ///     .on("/", end)
+    ///     .routes("/api")?;
+    /// let routes = table.routes();
+    /// assert_eq!("/api/user/:id", routes[0].path());
+    /// assert_eq!(Some("user"), routes[0].name());
+    /// assert_eq!(vec!["id"], routes[0].vars());
+    /// assert_eq!(
+    ///     Some(&[Method::GET, Method::OPTIONS, Method::DELETE, Method::HEAD][..]),
+    ///     routes[0].methods(),
+    /// );
+    /// assert_eq!("/api", routes[1].path());
+    /// assert_eq!(None, routes[1].methods());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// Find the endpoint and its path pattern matching a standardized path,
+    /// with router parameters.
+    fn find(&self, path: &str) -> Option<(&(String, Boxed<S>), Params)> {
+        // search static routes
+        if let Some(index) = self.static_route.get(path) {
+            return Some((&self.endpoints[*index], Vec::new()));
+        }
+
+        // search dynamic routes
+        let (index, params) = self.dynamic_route.find(path)?;
+        Some((&self.endpoints[*index], params))
+    }
+}
+
 impl<S> Default for Router<S>
 where
     S: 'static,
@@ -281,30 +636,34 @@ where
                 },
             )?);
 
-        // search static routes
-        if let Some(end) = self.static_route.get(&path) {
-            return end.call(ctx).await;
+        if !self.names.is_empty() {
+            ctx.store_scoped(NamesScope, "names", self.names.clone());
         }
 
-        // search dynamic routes
-        for (regexp_path, end) in self.dynamic_route.iter() {
-            if let Some(cap) = regexp_path.re.captures(&path) {
-                for var in regexp_path.vars.iter() {
-                    ctx.store_scoped(
-                        RouterScope,
-                        var.to_string(),
-                        cap[var.as_str()].to_string(),
-                    );
+        match self.find(&path) {
+            Some(((pattern, end), params)) => {
+                ctx.store_scoped(RouteScope, "route", pattern.clone());
+                if !params.is_empty() {
+                    for (name, value) in params.iter() {
+                        ctx.store_scoped(RouterScope, name.clone(), value.clone());
+                    }
+                    ctx.store_scoped(ParamsScope, "params", params);
                 }
-                return end.call(ctx).await;
+                end.call(ctx).await
             }
+            // 404 NOT FOUND
+            None => throw!(StatusCode::NOT_FOUND),
         }
-
-        // 404 NOT FOUND
-        throw!(StatusCode::NOT_FOUND)
     }
 }
 
+/// Get all router parameters in order of the path.
+pub(crate) fn params<S>(ctx: &Context<S>) -> Params {
+    ctx.load_scoped::<ParamsScope, Params>("params")
+        .map(|params| (*params).clone())
+        .unwrap_or_default()
+}
+
 impl<S> RouterParam for Context<S> {
     #[inline]
     fn must_param<'a>(&self, name: &'a str) -> Result<Variable<'a, String>> {
@@ -320,13 +679,41 @@ impl<S> RouterParam for Context<S> {
     fn param<'a>(&self, name: &'a str) -> Option<Variable<'a, String>> {
         self.load_scoped::<RouterScope, String>(name)
     }
+
+    #[inline]
+    fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String> {
+        let names = self.load_scoped::<NamesScope, Names>("names");
+        let path = names
+            .as_ref()
+            .and_then(|names| names.get(name))
+            .ok_or_else(|| {
+                Status::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("route `{}` not found", name),
+                    false,
+                )
+            })?;
+        path.url(params).map_err(|err| {
+            Status::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("{}\nfail to build url of route `{}`", err, name),
+                false,
+            )
+        })
+    }
+
+    #[inline]
+    fn route(&self) -> Option<Variable<'static, String>> {
+        self.load_scoped::<RouteScope, String>("route")
+    }
 }
 
 #[cfg(all(test, feature = "tcp"))]
 mod tests {
-    use super::Router;
+    use super::{Router, RouterParam};
     use crate::http::StatusCode;
     use crate::tcp::Listener;
+    use crate::test::Client;
     use crate::{App, Context, Next, Status};
     use async_std::task::spawn;
     use encoding::EncoderTrap;
@@ -377,6 +764,90 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn ambiguous_path() -> Result<(), Box<dyn std::error::Error>> {
+        let router = Router::new().on("/user/:id", test).on("/user/:name", test);
+        assert!(router.routes("/").is_err());
+        let router = Router::new().on("/posts", test).on("/posts/:page?", test);
+        assert!(router.routes("/").is_err());
+        let router = Router::new()
+            .on("/user/:id:u64", test)
+            .on("/user/:name", test);
+        router.routes("/")?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn route_priority() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> Result<(), Status> {
+            let id = ctx.param("id").map(|id| id.to_string());
+            ctx.resp.write(id.unwrap_or_else(|| "-".to_string()));
+            Ok(())
+        }
+        let router = Router::new()
+            .on("/user/*{path}", end)
+            .on("/user/:name", end)
+            .on("/user/:id:u8", end)
+            .on("/user/:id<[a-z]+>.json", end);
+        let client = Client::new(App::new().end(router.routes("/")?));
+        assert_eq!("1", client.get("/user/1").send().await.text().await?);
+        assert_eq!("-", client.get("/user/256").send().await.text().await?);
+        assert_eq!(
+            "abc",
+            client.get("/user/abc.json").send().await.text().await?
+        );
+        assert_eq!("-", client.get("/user/1/2").send().await.text().await?);
+        Ok(())
+    }
+
+    #[test]
+    fn conflict_name() {
+        let router = Router::new()
+            .on_named("user", "/user/:id", test)
+            .include("/api", Router::new().on_named("user", "/user/:id", test));
+        assert!(router.routes("/").is_err());
+    }
+
+    #[async_std::test]
+    async fn url_for() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> Result<(), Status> {
+            assert_eq!("/api/user/a%2Fb", ctx.url_for("user", &[("id", "a/b")])?);
+            assert_eq!("/api/user", ctx.url_for("users", &[])?);
+            assert!(ctx.url_for("user", &[]).is_err());
+            assert!(ctx.url_for("users", &[("id", "1")]).is_err());
+            assert!(ctx.url_for("group", &[]).is_err());
+            Ok(())
+        }
+        let user_router = Router::new()
+            .on_named("users", "/", end)
+            .on_named("user", "/:id", end);
+        let router = Router::new().include("/user", user_router);
+        let client = Client::new(App::new().end(router.routes("/api")?));
+        assert_eq!(
+            StatusCode::OK,
+            client.get("/api/user/1").send().await.status
+        );
+        assert_eq!(StatusCode::OK, client.get("/api/user").send().await.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn matched_route() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> Result<(), Status> {
+            let route = ctx.route().expect("route must be matched");
+            ctx.resp.write(route.to_string());
+            Ok(())
+        }
+        let user_router = Router::new().on("/", end).on("/:id", end);
+        let router = Router::new().include("/user", user_router);
+        let client = Client::new(App::new().end(router.routes("/api")?));
+        let resp = client.get("/api/user/1").send().await;
+        assert_eq!("/api/user/:id", resp.text().await?);
+        let resp = client.get("/api/user/").send().await;
+        assert_eq!("/api/user", resp.text().await?);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn route_not_found() -> Result<(), Box<dyn std::error::Error>> {
         let app = App::new().end(Router::default().routes("/")?);
@@ -1,15 +1,82 @@
 mod dispatcher;
 mod guard;
 
+use crate::http::header::{ALLOW, CONTENT_LENGTH};
 use crate::http::{Method, StatusCode};
-use crate::{throw, Result};
+use crate::{Body, Context, Endpoint, Result, Status};
+
+/// All http methods, in order of the `Allow` header.
+pub(crate) const ALL_METHODS: [Method; 9] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::OPTIONS,
+    Method::DELETE,
+    Method::HEAD,
+    Method::TRACE,
+    Method::CONNECT,
+];
+
+/// Join methods as value of the `Allow` header.
+fn allow_header(methods: &[Method]) -> String {
+    methods
+        .iter()
+        .map(Method::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// List allowed methods, HEAD is implicitly allowed if GET is allowed,
+/// and OPTIONS is always allowed.
+fn allowed(contains: impl Fn(&Method) -> bool) -> Vec<Method> {
+    ALL_METHODS
+        .iter()
+        .filter(|method| {
+            contains(method)
+                || (**method == Method::HEAD && contains(&Method::GET))
+                || **method == Method::OPTIONS
+        })
+        .cloned()
+        .collect()
+}
 
 #[inline]
-fn method_not_allowed(method: &Method) -> Result {
-    throw!(
+fn method_not_allowed(method: &Method, allowed: &[Method]) -> Result {
+    Err(Status::new(
         StatusCode::METHOD_NOT_ALLOWED,
-        format!("Method {} not allowed", method)
+        format!("Method {} not allowed", method),
+        true,
     )
+    .header(ALLOW, allow_header(allowed)))
+}
+
+/// Answer an OPTIONS request with allowed methods.
+#[inline]
+fn answer_options<S>(ctx: &mut Context<S>, allowed: &[Method]) -> Result {
+    ctx.resp.status = StatusCode::NO_CONTENT;
+    ctx.resp
+        .headers
+        .insert(ALLOW, allow_header(allowed).parse()?);
+    Ok(())
+}
+
+/// Answer a HEAD request by the GET endpoint, with response body stripped.
+///
+/// "Content-Length" of a sized body is kept, so it's the same as the GET response.
+#[inline]
+async fn answer_head<S, E>(endpoint: &E, ctx: &mut Context<S>) -> Result
+where
+    E: ?Sized + for<'a> Endpoint<'a, S>,
+{
+    let result = endpoint.call(ctx).await;
+    if !ctx.resp.headers.contains_key(CONTENT_LENGTH) {
+        if let Body::Once(bytes) = &ctx.resp.body {
+            ctx.resp.headers.insert(CONTENT_LENGTH, bytes.len().into());
+        }
+    }
+    ctx.resp.body = Body::empty();
+    result
 }
 
 pub use dispatcher::{
@@ -17,3 +84,92 @@ pub use dispatcher::{
 };
 
 pub use guard::{allow, deny, Guard};
+
+#[cfg(test)]
+mod tests {
+    use super::{allow, deny, get};
+    use crate::http::header::{ALLOW, CONTENT_LENGTH};
+    use crate::http::{Method, StatusCode};
+    use crate::test::Client;
+    use crate::{App, Context, Result};
+
+    async fn hello(ctx: &mut Context) -> Result {
+        ctx.resp.write("Hello, world");
+        Ok(())
+    }
+
+    async fn create(_ctx: &mut Context) -> Result {
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn dispatcher() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().end(get(hello).post(create)));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hello, world", resp.text().await?);
+
+        let resp = client.head("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("", resp.text().await?);
+
+        let resp = client.options("/").send().await;
+        assert_eq!(StatusCode::NO_CONTENT, resp.status);
+        assert_eq!("GET, POST, OPTIONS, HEAD", resp.headers[ALLOW]);
+
+        let resp = client.delete("/").send().await;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status);
+        assert_eq!("GET, POST, OPTIONS, HEAD", resp.headers[ALLOW]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn dispatcher_options() -> std::result::Result<(), Box<dyn std::error::Error>>
+    {
+        async fn options(ctx: &mut Context) -> Result {
+            ctx.resp.write("options");
+            Ok(())
+        }
+        let client = Client::new(App::new().end(get(hello).options(options)));
+        let resp = client.options("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("options", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn guard() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().end(allow([Method::GET], hello)));
+        let resp = client.head("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("", resp.text().await?);
+
+        let resp = client.options("/").send().await;
+        assert_eq!(StatusCode::NO_CONTENT, resp.status);
+        assert_eq!("GET, OPTIONS, HEAD", resp.headers[ALLOW]);
+
+        let resp = client.post("/").send().await;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status);
+        assert_eq!("GET, OPTIONS, HEAD", resp.headers[ALLOW]);
+
+        let client = Client::new(App::new().end(deny([Method::GET], hello)));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status);
+        assert_eq!(
+            "POST, PUT, PATCH, OPTIONS, DELETE, HEAD, TRACE, CONNECT",
+            resp.headers[ALLOW]
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn head_content_length() -> std::result::Result<(), Box<dyn std::error::Error>>
+    {
+        let client = Client::new(App::new().end(get(hello)));
+        let resp = client.head("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("12", resp.headers[CONTENT_LENGTH]);
+        assert_eq!("", resp.text().await?);
+        Ok(())
+    }
+}
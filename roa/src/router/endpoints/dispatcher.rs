@@ -1,4 +1,4 @@
-use super::method_not_allowed;
+use super::{allowed, answer_head, answer_options, method_not_allowed};
 use crate::http::Method;
 use crate::{async_trait, Context, Endpoint, Result};
 use doc_comment::doc_comment;
@@ -58,6 +58,10 @@ let app = App::new().end(", stringify!($end), "(end));
 }
 
 /// An endpoint wrapper to dispatch requests by http method.
+///
+/// - HEAD falls back to the endpoint on GET, with response body stripped.
+/// - OPTIONS is answered with the `Allow` header if no endpoint is set on it.
+/// - Other methods without endpoint get a 405 METHOD NOT ALLOWED with the `Allow` header.
 pub struct Dispatcher<S>(HashMap<Method, Box<dyn for<'a> Endpoint<'a, S>>>);
 
 impl_http_functions!(get, Method::GET);
@@ -96,9 +100,25 @@ where
 {
     #[inline]
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result<()> {
-        match self.0.get(ctx.method()) {
-            Some(endpoint) => endpoint.call(ctx).await,
-            None => method_not_allowed(ctx.method()),
+        if let Some(endpoint) = self.0.get(ctx.method()) {
+            return endpoint.call(ctx).await;
         }
+        let method = ctx.method().clone();
+        match self.0.get(&Method::GET) {
+            Some(endpoint) if method == Method::HEAD => {
+                answer_head(endpoint.as_ref(), ctx).await
+            }
+            _ if method == Method::OPTIONS => {
+                answer_options(ctx, &self.allowed_methods().unwrap_or_default())
+            }
+            _ => {
+                method_not_allowed(&method, &self.allowed_methods().unwrap_or_default())
+            }
+        }
+    }
+
+    #[inline]
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        Some(allowed(|method| self.0.contains_key(method)))
     }
 }
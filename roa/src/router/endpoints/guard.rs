@@ -1,23 +1,14 @@
-use super::method_not_allowed;
+use super::{allowed, answer_head, answer_options, method_not_allowed, ALL_METHODS};
 use crate::http::Method;
 use crate::{async_trait, Context, Endpoint, Result};
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
-/// Methods allowed in `Guard`.
-const ALL_METHODS: [Method; 9] = [
-    Method::GET,
-    Method::POST,
-    Method::PUT,
-    Method::PATCH,
-    Method::OPTIONS,
-    Method::DELETE,
-    Method::HEAD,
-    Method::TRACE,
-    Method::CONNECT,
-];
-
 /// An endpoint wrapper to guard endpoint by http method.
+///
+/// - HEAD is allowed if GET is allowed, with response body stripped.
+/// - OPTIONS is answered with the `Allow` header if it's not allowed.
+/// - Other methods not allowed get a 405 METHOD NOT ALLOWED with the `Allow` header.
 pub struct Guard<E> {
     white_list: HashSet<Method>,
     endpoint: E,
@@ -77,14 +68,24 @@ pub fn deny<E>(methods: impl AsRef<[Method]>, endpoint: E) -> Guard<E> {
 #[async_trait(?Send)]
 impl<'a, S, E> Endpoint<'a, S> for Guard<E>
 where
-    E: Endpoint<'a, S>,
+    E: for<'b> Endpoint<'b, S>,
 {
     #[inline]
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
-        if self.white_list.contains(ctx.method()) {
+        let method = ctx.method().clone();
+        if self.white_list.contains(&method) {
             self.endpoint.call(ctx).await
+        } else if method == Method::HEAD && self.white_list.contains(&Method::GET) {
+            answer_head(&self.endpoint, ctx).await
+        } else if method == Method::OPTIONS {
+            answer_options(ctx, &self.allowed_methods().unwrap_or_default())
         } else {
-            method_not_allowed(ctx.method())
+            method_not_allowed(&method, &self.allowed_methods().unwrap_or_default())
         }
     }
+
+    #[inline]
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        Some(allowed(|method| self.white_list.contains(method)))
+    }
 }
@@ -7,6 +7,9 @@ pub enum RouterError {
     /// Dynamic paths miss variable.
     MissingVariable(String),
 
+    /// Invalid variable constraint on path.
+    InvalidConstraint(String, String),
+
     /// Variables, methods or paths conflict.
     Conflict(Conflict),
 }
@@ -20,6 +23,10 @@ pub enum Conflict {
         paths: (String, String),
         var_name: String,
     },
+    Ambiguous {
+        paths: (String, String),
+    },
+    Name(String),
 }
 
 impl Display for Conflict {
@@ -34,6 +41,10 @@ impl Display for Conflict {
                 "conflict variable `{}`: between `{}` and `{}`",
                 var_name, paths.0, paths.1
             )),
+            Conflict::Ambiguous { paths } => {
+                f.write_str(&format!("ambiguous paths: `{}` and `{}`", paths.0, paths.1))
+            }
+            Conflict::Name(name) => f.write_str(&format!("conflict name: `{}`", name)),
         }
     }
 }
@@ -47,6 +58,10 @@ impl Display for RouterError {
             RouterError::MissingVariable(path) => {
                 f.write_str(&format!("missing variable on path {}", path))
             }
+            RouterError::InvalidConstraint(constraint, path) => f.write_str(&format!(
+                "invalid constraint `{}` on path {}",
+                constraint, path
+            )),
         }
     }
 }
@@ -83,6 +98,17 @@ mod tests {
             }
             .to_string()
         );
+        assert_eq!(
+            "ambiguous paths: `/user/:id` and `/user/:name`",
+            Conflict::Ambiguous {
+                paths: ("/user/:id".to_string(), "/user/:name".to_string()),
+            }
+            .to_string()
+        );
+        assert_eq!(
+            "conflict name: `user`",
+            Conflict::Name("user".to_string()).to_string()
+        );
     }
 
     #[test]
@@ -95,5 +121,10 @@ mod tests {
             "missing variable on path /:",
             RouterError::MissingVariable("/:".to_string()).to_string()
         );
+        assert_eq!(
+            "invalid constraint `uuid` on path /:id:uuid",
+            RouterError::InvalidConstraint("uuid".to_string(), "/:id:uuid".to_string())
+                .to_string()
+        );
     }
 }
@@ -0,0 +1,322 @@
+use super::path::{Constraint, Piece, Segment};
+use super::Route;
+use crate::http::header::CONTENT_TYPE;
+use crate::http::{Method, StatusCode};
+use crate::{async_trait, Context, Endpoint, Result};
+use bytes::Bytes;
+use serde_json::{json, Map, Value};
+
+/// Version of OpenAPI specification.
+const OPENAPI_VERSION: &str = "3.0.3";
+
+/// Documentation of an operation, which is a method on a route.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::router::{get, Operation, Router};
+/// use roa::http::{Method, StatusCode};
+/// use roa::{Context, Result};
+/// use serde_json::json;
+///
+/// async fn query(ctx: &mut Context) -> Result {
+///     Ok(())
+/// }
+///
+/// let router = Router::new()
+///     .on("/user/:id", get(query))
+///     .doc(
+///         Method::GET,
+///         Operation::new()
+///             .summary("Query user")
+///             .response_schema(
+///                 StatusCode::OK,
+///                 "The user",
+///                 json!({"type": "object", "properties": {"name": {"type": "string"}}}),
+///             )
+///             .response(StatusCode::NOT_FOUND, "User not found"),
+///     )
+///     .openapi("/openapi.json", "User API", "1.0.0");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Operation {
+    summary: Option<String>,
+    description: Option<String>,
+    request: Option<Value>,
+    responses: Vec<(StatusCode, String, Option<Value>)>,
+}
+
+impl Operation {
+    /// Construct an empty operation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set summary.
+    pub fn summary(mut self, summary: impl ToString) -> Self {
+        self.summary = Some(summary.to_string());
+        self
+    }
+
+    /// Set description.
+    pub fn description(mut self, description: impl ToString) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Set JSON schema of request body.
+    pub fn request(mut self, schema: Value) -> Self {
+        self.request = Some(schema);
+        self
+    }
+
+    /// Add a response without body.
+    pub fn response(mut self, status: StatusCode, description: impl ToString) -> Self {
+        self.responses.push((status, description.to_string(), None));
+        self
+    }
+
+    /// Add a response with JSON schema of body.
+    pub fn response_schema(
+        mut self,
+        status: StatusCode,
+        description: impl ToString,
+        schema: Value,
+    ) -> Self {
+        self.responses
+            .push((status, description.to_string(), Some(schema)));
+        self
+    }
+
+    /// Generate operation object.
+    fn to_json(&self, parameters: &[Value], operation_id: Option<String>) -> Value {
+        let mut object = Map::new();
+        if let Some(operation_id) = operation_id {
+            object.insert("operationId".to_string(), operation_id.into());
+        }
+        if let Some(summary) = &self.summary {
+            object.insert("summary".to_string(), summary.as_str().into());
+        }
+        if let Some(description) = &self.description {
+            object.insert("description".to_string(), description.as_str().into());
+        }
+        if !parameters.is_empty() {
+            object.insert("parameters".to_string(), parameters.into());
+        }
+        if let Some(schema) = &self.request {
+            object.insert(
+                "requestBody".to_string(),
+                json!({ "content": json_content(schema) }),
+            );
+        }
+        let mut responses = Map::new();
+        for (status, description, schema) in self.responses.iter() {
+            let mut response = Map::new();
+            response.insert("description".to_string(), description.as_str().into());
+            if let Some(schema) = schema {
+                response.insert("content".to_string(), json_content(schema));
+            }
+            responses.insert(status.as_str().to_string(), response.into());
+        }
+        if responses.is_empty() {
+            responses.insert(
+                "default".to_string(),
+                json!({ "description": "default response" }),
+            );
+        }
+        object.insert("responses".to_string(), responses.into());
+        object.into()
+    }
+}
+
+/// Content object of JSON.
+fn json_content(schema: &Value) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+/// Path template and parameters of segments.
+fn path_item(segments: &[Segment]) -> (String, Vec<Value>) {
+    let mut parameters = Vec::new();
+    let mut texts = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let mut text = String::new();
+        for piece in segment.pieces.iter() {
+            let (name, schema) = match piece {
+                Piece::Static(static_text) => {
+                    text.push_str(static_text);
+                    continue;
+                }
+                Piece::Variable(name, Constraint::Any) | Piece::Wildcard(name) => {
+                    (name, json!({ "type": "string" }))
+                }
+                Piece::Variable(name, Constraint::Regex(pattern)) => (
+                    name,
+                    json!({ "type": "string", "pattern": format!("^(?:{})$", pattern) }),
+                ),
+                Piece::Variable(name, Constraint::Type(param_type)) => {
+                    (name, json!({ "type": param_type.schema_type() }))
+                }
+            };
+            text.push_str(&format!("{{{}}}", name));
+            parameters.push(json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": schema,
+            }));
+        }
+        texts.push(text);
+    }
+    (
+        format!("/{}", texts.join("/").trim_matches('/')),
+        parameters,
+    )
+}
+
+/// Methods to document for a route.
+///
+/// Implicit HEAD and OPTIONS are omitted unless they are documented.
+/// If the endpoint allows any method, only documented methods are listed, or GET if none.
+fn documented_methods(route: &Route) -> Vec<Method> {
+    match route.methods() {
+        Some(methods) => methods
+            .iter()
+            .filter(|method| {
+                (**method != Method::HEAD && **method != Method::OPTIONS)
+                    || route.operation(method).is_some()
+            })
+            .cloned()
+            .collect(),
+        None if route.operations.is_empty() => vec![Method::GET],
+        None => route
+            .operations
+            .iter()
+            .map(|(method, _)| method.clone())
+            .collect(),
+    }
+}
+
+/// Generate OpenAPI document of routes.
+pub fn document(title: &str, version: &str, routes: &[Route]) -> Value {
+    let default_operation = Operation::default();
+    let mut paths = Map::new();
+    for route in routes {
+        let methods = documented_methods(route);
+        let variants = route.path.variants();
+        let last = variants.len() - 1;
+        for (index, variant) in variants.iter().enumerate() {
+            let (template, parameters) = path_item(variant);
+            let item = paths
+                .entry(template)
+                .or_insert_with(|| Value::Object(Map::new()));
+            for method in methods.iter() {
+                // operation id is unique, only the full variant has it
+                let operation_id = route.name().filter(|_| index == last).map(|name| {
+                    if methods.len() == 1 {
+                        name.to_string()
+                    } else {
+                        format!("{}_{}", name, method.as_str().to_lowercase())
+                    }
+                });
+                let operation = route.operation(method).unwrap_or(&default_operation);
+                item[method.as_str().to_lowercase()] =
+                    operation.to_json(&parameters, operation_id);
+            }
+        }
+    }
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": { "title": title, "version": version },
+        "paths": paths,
+    })
+}
+
+/// An endpoint serving a JSON document.
+pub struct Document(pub Bytes);
+
+#[async_trait(?Send)]
+impl<'a, S> Endpoint<'a, S> for Document {
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        ctx.resp.write(self.0.clone());
+        ctx.resp
+            .headers
+            .insert(CONTENT_TYPE, "application/json; charset=utf-8".parse()?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Operation;
+    use crate::http::{Method, StatusCode};
+    use crate::router::{get, Router};
+    use crate::test::Client;
+    use crate::{App, Context, Result};
+    use serde_json::{json, Value};
+
+    async fn end(_ctx: &mut Context) -> Result {
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn document() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let router = Router::new()
+            .on_named("user", "/user/:id:u64", get(end).delete(end))
+            .doc(
+                Method::GET,
+                Operation::new()
+                    .summary("Query user")
+                    .response_schema(
+                        StatusCode::OK,
+                        "The user",
+                        json!({ "type": "object" }),
+                    )
+                    .response(StatusCode::NOT_FOUND, "User not found"),
+            )
+            .on("/posts/:page?", end)
+            .on("/file/:name<[a-z]+>.:ext", get(end))
+            .openapi("/openapi.json", "Roa", "1.0.0");
+        let client = Client::new(App::new().end(router.routes("/api")?));
+        let resp = client.get("/api/openapi.json").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let doc: Value = resp.json().await?;
+        assert_eq!("3.0.3", doc["openapi"]);
+        assert_eq!("Roa", doc["info"]["title"]);
+        assert_eq!("1.0.0", doc["info"]["version"]);
+
+        let paths = doc["paths"].as_object().unwrap();
+        assert_eq!(4, paths.len());
+        assert!(paths.get("/api/openapi.json").is_none());
+
+        let user = &paths["/api/user/{id}"];
+        assert_eq!(2, user.as_object().unwrap().len());
+        assert_eq!("Query user", user["get"]["summary"]);
+        assert_eq!("user_get", user["get"]["operationId"]);
+        assert_eq!("integer", user["get"]["parameters"][0]["schema"]["type"]);
+        assert_eq!(
+            "object",
+            user["get"]["responses"]["200"]["content"]["application/json"]["schema"]
+                ["type"]
+        );
+        assert_eq!(
+            "User not found",
+            user["get"]["responses"]["404"]["description"]
+        );
+        assert_eq!(
+            "default response",
+            user["delete"]["responses"]["default"]["description"]
+        );
+
+        assert!(paths["/api/posts"]["get"]["parameters"].is_null());
+        assert_eq!(
+            "page",
+            paths["/api/posts/{page}"]["get"]["parameters"][0]["name"]
+        );
+
+        let file = &paths["/api/file/{name}.{ext}"]["get"]["parameters"];
+        assert_eq!("^(?:[a-z]+)$", file[0]["schema"]["pattern"]);
+        assert_eq!("ext", file[1]["name"]);
+        Ok(())
+    }
+}
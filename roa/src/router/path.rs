@@ -1,14 +1,41 @@
 use super::{Conflict, RouterError};
-use regex::{escape, Captures, Regex};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use regex::{escape, Regex};
+use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::convert::AsRef;
 use std::str::FromStr;
 
-/// Match pattern *{variable}
-const WILDCARD: &str = r"\*\{(?P<var>\w*)\}";
+/// Default pattern of segment variables.
+const VARIABLE: &str = r"[^\s/]+";
 
-/// Match pattern /:variable/
-const VARIABLE: &str = r"/:(?P<var>\w*)/";
+/// Pattern of wildcards.
+const WILDCARD: &str = r"\S+";
+
+/// Pattern of unsigned integers.
+const UNSIGNED: &str = r"\d+";
+
+/// Pattern of signed integers.
+const SIGNED: &str = r"[+-]?\d+";
+
+/// Pattern of floats.
+const FLOAT: &str = r"[+-]?(?:\d+\.?\d*|\.\d+)(?:[eE][+-]?\d+)?";
+
+/// Characters to be encoded in values of wildcards.
+const PATH_VALUE: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Characters to be encoded in values of segment variables.
+const SEGMENT_VALUE: &AsciiSet = &PATH_VALUE.add(b'/');
 
 /// {/path path/ /path/} => /path/
 pub fn standardize_path(raw_path: &str) -> String {
@@ -38,146 +65,644 @@ fn must_build(pattern: &str) -> Regex {
     })
 }
 
+macro_rules! param_types {
+    ($($ty:ident => $pattern:expr,)*) => {
+        /// Type constraint of variables, like `:id:u64`.
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+        pub enum ParamType {
+            $($ty,)*
+        }
+
+        impl ParamType {
+            /// Get type by name.
+            fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($ty) => Some(ParamType::$ty),)*
+                    _ => None,
+                }
+            }
+
+            /// Pattern to match values of this type.
+            fn pattern(self) -> &'static str {
+                match self {
+                    $(ParamType::$ty => $pattern,)*
+                }
+            }
+
+            /// Type of values in JSON schema.
+            pub fn schema_type(self) -> &'static str {
+                match self.pattern() {
+                    UNSIGNED | SIGNED => "integer",
+                    FLOAT => "number",
+                    _ => "boolean",
+                }
+            }
+
+            /// Check if value can be parsed as this type.
+            fn validate(self, value: &str) -> bool {
+                match self {
+                    $(ParamType::$ty => value.parse::<$ty>().is_ok(),)*
+                }
+            }
+        }
+    };
+}
+
+param_types! {
+    u8 => UNSIGNED,
+    u16 => UNSIGNED,
+    u32 => UNSIGNED,
+    u64 => UNSIGNED,
+    u128 => UNSIGNED,
+    usize => UNSIGNED,
+    i8 => SIGNED,
+    i16 => SIGNED,
+    i32 => SIGNED,
+    i64 => SIGNED,
+    i128 => SIGNED,
+    isize => SIGNED,
+    f32 => FLOAT,
+    f64 => FLOAT,
+    bool => "true|false",
+}
+
+/// Constraint of variables.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum Constraint {
+    /// `:var`
+    Any,
+
+    /// `:var<regex>`
+    Regex(String),
+
+    /// `:var:type`
+    Type(ParamType),
+}
+
+/// A piece of segment.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum Piece {
+    /// Static text.
+    Static(String),
+
+    /// Variable matching part of a segment.
+    Variable(String, Constraint),
+
+    /// Wildcard matching any non-empty path, `*{var}`.
+    Wildcard(String),
+}
+
+/// A segment between slashes.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Segment {
+    pub pieces: Vec<Piece>,
+    pub optional: bool,
+}
+
 /// Parsed path.
 #[derive(Clone)]
 pub enum Path {
     Static(String),
-    Dynamic(RegexPath),
+    Dynamic(DynamicPath),
 }
 
 /// Dynamic path.
 #[derive(Clone)]
-pub struct RegexPath {
+pub struct DynamicPath {
     pub raw: String,
-    pub vars: HashSet<String>,
-    pub re: Regex,
+    pub segments: Vec<Segment>,
+}
+
+/// Compiled pattern of dynamic segments.
+pub struct Pattern {
+    re: Regex,
+    segments: Vec<Segment>,
 }
 
 impl FromStr for Path {
     type Err = RouterError;
     fn from_str(raw_path: &str) -> Result<Self, Self::Err> {
         let path = standardize_path(raw_path);
-        Ok(match path_to_regexp(&path)? {
+        let segments = parse(&path)?;
+        Ok(match path_to_regexp(&segments) {
             None => Path::Static(path),
-            Some((pattern, vars)) => Path::Dynamic(RegexPath {
+            Some(_) => Path::Dynamic(DynamicPath {
                 raw: path,
-                vars,
-                re: must_build(&format!(r"^{}$", pattern)),
+                segments,
             }),
         })
     }
 }
 
-fn path_to_regexp(path: &str) -> Result<Option<(String, HashSet<String>)>, RouterError> {
-    let mut pattern = escape(path);
-    let mut vars = HashSet::new();
-    let wildcard_re = must_build(WILDCARD);
-    let variable_re = must_build(VARIABLE);
-    let wildcards: Vec<Captures> = wildcard_re.captures_iter(path).collect();
-    let variable_template = path.replace('/', "//"); // to match continuous variables like /:year/:month/:day/
-    let variables: Vec<Captures> =
-        variable_re.captures_iter(&variable_template).collect();
-    if wildcards.is_empty() && variables.is_empty() {
-        Ok(None)
-    } else {
-        // detect variable conflicts.
-        let try_add_variable = |set: &mut HashSet<String>, variable: String| {
-            if set.insert(variable.clone()) {
-                Ok(())
-            } else {
-                Err(Conflict::Variable {
-                    paths: (path.to_string(), path.to_string()),
-                    var_name: variable,
+impl Path {
+    /// Raw path.
+    pub fn raw(&self) -> &str {
+        match self {
+            Path::Static(path) => path,
+            Path::Dynamic(dynamic_path) => &dynamic_path.raw,
+        }
+    }
+
+    /// Variables of this path, in order of the path.
+    pub fn vars(&self) -> Vec<String> {
+        match self {
+            Path::Static(_) => Vec::new(),
+            Path::Dynamic(dynamic_path) => dynamic_path
+                .segments
+                .iter()
+                .flat_map(|segment| segment.pieces.iter())
+                .filter_map(|piece| match piece {
+                    Piece::Static(_) => None,
+                    Piece::Variable(name, _) | Piece::Wildcard(name) => {
+                        Some(name.clone())
+                    }
                 })
+                .collect(),
+        }
+    }
+
+    /// Variants of this path, optional segments are expanded.
+    pub fn variants(&self) -> Vec<Vec<Segment>> {
+        let segments = match self {
+            Path::Static(path) => return vec![static_segments(path)],
+            Path::Dynamic(dynamic_path) => &dynamic_path.segments,
+        };
+        let mut variants = vec![Vec::new()];
+        for segment in segments {
+            let required = Segment {
+                pieces: segment.pieces.clone(),
+                optional: false,
+            };
+            if segment.optional {
+                let mut expanded = variants.clone();
+                for variant in expanded.iter_mut() {
+                    variant.push(required.clone());
+                }
+                variants.extend(expanded);
+            } else {
+                for variant in variants.iter_mut() {
+                    variant.push(required.clone());
+                }
             }
+        }
+        variants
+    }
+
+    /// Shapes of this path, which are variants without variable names.
+    ///
+    /// Paths with a same shape are ambiguous.
+    pub fn shapes(&self) -> HashSet<Vec<Segment>> {
+        self.variants()
+            .into_iter()
+            .map(|variant| {
+                variant
+                    .into_iter()
+                    .map(|segment| Segment {
+                        pieces: segment.pieces.iter().map(Piece::shape).collect(),
+                        optional: false,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Build url by router parameters, values are percent-encoded.
+    ///
+    /// Optional segments are omitted if none of their variables are given.
+    pub fn url(&self, params: &[(&str, &str)]) -> Result<String, String> {
+        let segments = match self {
+            Path::Static(path) => match params.first() {
+                Some((name, _)) => {
+                    return Err(format!("unexpected router parameter `{}`", name))
+                }
+                None => return Ok(format!("/{}", path.trim_matches('/'))),
+            },
+            Path::Dynamic(dynamic_path) => &dynamic_path.segments,
+        };
+        let get = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
         };
+        let mut used = HashSet::new();
+        let mut texts = Vec::with_capacity(segments.len());
+        for segment in segments {
+            if segment.optional
+                && segment.pieces.iter().all(|piece| match piece {
+                    Piece::Static(_) => true,
+                    Piece::Variable(name, _) | Piece::Wildcard(name) => {
+                        get(name).is_none()
+                    }
+                })
+            {
+                continue;
+            }
+            let mut text = String::new();
+            for piece in segment.pieces.iter() {
+                let (name, ascii_set) = match piece {
+                    Piece::Static(static_text) => {
+                        text.push_str(static_text);
+                        continue;
+                    }
+                    Piece::Variable(name, _) => (name, SEGMENT_VALUE),
+                    Piece::Wildcard(name) => (name, PATH_VALUE),
+                };
+                let value = get(name)
+                    .ok_or_else(|| format!("missing router parameter `{}`", name))?;
+                used.insert(name.as_str());
+                text.extend(utf8_percent_encode(value, ascii_set));
+            }
+            texts.push(text);
+        }
+        if let Some((name, _)) = params.iter().find(|(name, _)| !used.contains(name)) {
+            return Err(format!("unexpected router parameter `{}`", name));
+        }
+        Ok(format!("/{}", texts.join("/").trim_matches('/')))
+    }
+}
+
+impl DynamicPath {
+    /// Segments between the leading and the trailing slash.
+    pub fn inner_segments(&self) -> &[Segment] {
+        &self.segments[1..self.segments.len() - 1]
+    }
+}
+
+impl Pattern {
+    /// Compile segments, which must contain variables.
+    pub fn new(segments: Vec<Segment>) -> Self {
+        let (pattern, _) = path_to_regexp(&segments).unwrap_or_default();
+        Self {
+            re: must_build(&format!(r"^{}$", pattern)),
+            segments,
+        }
+    }
+
+    /// Match a part of path, return router parameters in order of the path.
+    pub fn captures(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let cap = self.re.captures(path)?;
+        let mut params = Vec::new();
+        for piece in self
+            .segments
+            .iter()
+            .flat_map(|segment| segment.pieces.iter())
+        {
+            let (name, constraint) = match piece {
+                Piece::Static(_) => continue,
+                Piece::Variable(name, constraint) => (name, Some(constraint)),
+                Piece::Wildcard(name) => (name, None),
+            };
+            if let Some(value) = cap.name(name) {
+                if let Some(Constraint::Type(param_type)) = constraint {
+                    if !param_type.validate(value.as_str()) {
+                        return None;
+                    }
+                }
+                params.push((name.clone(), value.as_str().to_string()));
+            }
+        }
+        Some(params)
+    }
+}
+
+impl Piece {
+    /// Pattern of this piece.
+    fn pattern(&self) -> String {
+        match self {
+            Piece::Static(text) => escape(text),
+            Piece::Variable(name, constraint) => {
+                let pattern = match constraint {
+                    Constraint::Any => VARIABLE,
+                    Constraint::Regex(pattern) => pattern,
+                    Constraint::Type(param_type) => param_type.pattern(),
+                };
+                format!(r"(?P<{}>{})", name, pattern)
+            }
+            Piece::Wildcard(name) => format!(r"(?P<{}>{})", name, WILDCARD),
+        }
+    }
+
+    /// Check if this piece is a wildcard.
+    fn is_wildcard(&self) -> bool {
+        match self {
+            Piece::Wildcard(_) => true,
+            _ => false,
+        }
+    }
 
+    /// Erase variable name.
+    fn shape(&self) -> Piece {
+        match self {
+            Piece::Static(text) => Piece::Static(text.clone()),
+            Piece::Variable(_, constraint) => {
+                Piece::Variable(String::new(), constraint.clone())
+            }
+            Piece::Wildcard(_) => Piece::Wildcard(String::new()),
+        }
+    }
+}
+
+impl Segment {
+    /// Text of this segment, `None` if it contains variables.
+    pub fn static_text(&self) -> Option<String> {
+        let mut text = String::new();
+        for piece in self.pieces.iter() {
+            match piece {
+                Piece::Static(piece) => text.push_str(piece),
+                _ => return None,
+            }
+        }
+        Some(text)
+    }
+
+    /// Check if this segment contains a wildcard.
+    pub fn is_wildcard(&self) -> bool {
+        self.pieces.iter().any(Piece::is_wildcard)
+    }
+
+    /// Rank of this segment, lower is more specific:
+    /// static > mixed > constrained variable > variable > wildcard.
+    pub fn rank(&self) -> u8 {
+        match self.pieces.as_slice() {
+            _ if self.is_wildcard() => 4,
+            [] | [Piece::Static(_)] => 0,
+            [Piece::Variable(_, Constraint::Any)] => 3,
+            [Piece::Variable(_, _)] => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Compare priority of segments, the more specific one is less.
+///
+/// Segments are compared by rank one by one, the longer one is more specific if all ranks are equal.
+pub fn cmp_priority(segments: &[Segment], others: &[Segment]) -> Ordering {
+    let ranks = segments.iter().map(Segment::rank);
+    let other_ranks = others.iter().map(Segment::rank);
+    for (rank, other_rank) in ranks.zip(other_ranks) {
+        match rank.cmp(&other_rank) {
+            Ordering::Equal => continue,
+            ordering => return ordering,
+        }
+    }
+    others.len().cmp(&segments.len())
+}
+
+/// Segments of a static path.
+fn static_segments(path: &str) -> Vec<Segment> {
+    path.split('/')
+        .map(|segment| Segment {
+            pieces: if segment.is_empty() {
+                Vec::new()
+            } else {
+                vec![Piece::Static(segment.to_string())]
+            },
+            optional: false,
+        })
+        .collect()
+}
+
+/// Check if a char can be in variable name.
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Length of the leading word.
+fn word_len(text: &str) -> usize {
+    text.find(|c| !is_word(c)).unwrap_or(text.len())
+}
+
+/// Length of regex constraint starting with `<`, including the angle brackets.
+fn regex_len(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index + 1);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Parse path into segments.
+fn parse(path: &str) -> Result<Vec<Segment>, RouterError> {
+    let mut vars = HashSet::new();
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        let segment = parse_segment(path, segment)?;
+        for piece in segment.pieces.iter() {
+            match piece {
+                Piece::Variable(name, _) | Piece::Wildcard(name) => {
+                    // detect variable conflicts.
+                    if !vars.insert(name.clone()) {
+                        return Err(Conflict::Variable {
+                            paths: (path.to_string(), path.to_string()),
+                            var_name: name.clone(),
+                        }
+                        .into());
+                    }
+                }
+                Piece::Static(_) => (),
+            }
+        }
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+/// Parse a segment into pieces.
+fn parse_segment(path: &str, mut rest: &str) -> Result<Segment, RouterError> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut optional = false;
+    while let Some(c) = rest.chars().next() {
         // match wildcard patterns
-        for cap in wildcards {
-            let variable = &cap["var"];
-            if variable == r"" {
-                return Err(RouterError::MissingVariable(path.to_string()));
+        if rest.starts_with("*{") {
+            let len = word_len(&rest[2..]);
+            if rest[2 + len..].starts_with('}') {
+                if len == 0 {
+                    return Err(RouterError::MissingVariable(path.to_string()));
+                }
+                push_text(&mut pieces, &mut text);
+                pieces.push(Piece::Wildcard(rest[2..2 + len].to_string()));
+                rest = &rest[3 + len..];
+                continue;
             }
-            let var = escape(variable);
-            pattern = pattern.replace(
-                &escape(&format!(r"*{{{}}}", variable)),
-                &format!(r"(?P<{}>\S+)", &var),
-            );
-            try_add_variable(&mut vars, var)?;
         }
 
-        // match segment variable patterns
-        for cap in variables {
-            let variable = &cap["var"];
-            if variable == "" {
+        // match variable patterns
+        if c == ':' {
+            let len = word_len(&rest[1..]);
+            if len == 0 {
                 return Err(RouterError::MissingVariable(path.to_string()));
             }
-            let var = escape(variable);
-            pattern = pattern.replace(
-                &escape(&format!(r":{}", variable)),
-                &format!(r"(?P<{}>[^\s/]+)", &var),
-            );
-            try_add_variable(&mut vars, var)?;
+            let name = rest[1..1 + len].to_string();
+            rest = &rest[1 + len..];
+            let constraint = if rest.starts_with('<') {
+                let len = regex_len(rest).ok_or_else(|| {
+                    RouterError::InvalidConstraint(rest.to_string(), path.to_string())
+                })?;
+                let pattern = &rest[1..len - 1];
+                if Regex::new(&format!("^(?:{})$", pattern)).is_err() {
+                    return Err(RouterError::InvalidConstraint(
+                        pattern.to_string(),
+                        path.to_string(),
+                    ));
+                }
+                rest = &rest[len..];
+                Constraint::Regex(pattern.to_string())
+            } else if rest.starts_with(':') {
+                let len = word_len(&rest[1..]);
+                let name = &rest[1..1 + len];
+                let param_type = ParamType::from_name(name).ok_or_else(|| {
+                    RouterError::InvalidConstraint(name.to_string(), path.to_string())
+                })?;
+                rest = &rest[1 + len..];
+                Constraint::Type(param_type)
+            } else {
+                Constraint::Any
+            };
+            push_text(&mut pieces, &mut text);
+            pieces.push(Piece::Variable(name, constraint));
+            continue;
+        }
+
+        // a segment with variables is optional if it ends with `?`
+        if rest == "?" && !pieces.is_empty() {
+            optional = true;
+            break;
         }
-        Ok(Some((pattern, vars)))
+
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    push_text(&mut pieces, &mut text);
+    Ok(Segment { pieces, optional })
+}
+
+/// Push static text as a piece.
+fn push_text(pieces: &mut Vec<Piece>, text: &mut String) {
+    if !text.is_empty() {
+        pieces.push(Piece::Static(std::mem::take(text)));
+    }
+}
+
+/// Build regex pattern from segments, return `None` if the path is static.
+fn path_to_regexp(segments: &[Segment]) -> Option<(String, HashSet<String>)> {
+    let mut pattern = String::new();
+    let mut vars = HashSet::new();
+    for (index, segment) in segments.iter().enumerate() {
+        let mut segment_pattern = String::new();
+        for piece in segment.pieces.iter() {
+            match piece {
+                Piece::Variable(name, _) | Piece::Wildcard(name) => {
+                    vars.insert(name.clone());
+                }
+                Piece::Static(_) => (),
+            }
+            segment_pattern.push_str(&piece.pattern());
+        }
+        let separator = if index == 0 { "" } else { "/" };
+        if segment.optional {
+            pattern.push_str(&format!("(?:{}{})?", separator, segment_pattern));
+        } else {
+            pattern.push_str(separator);
+            pattern.push_str(&segment_pattern);
+        }
+    }
+    if vars.is_empty() {
+        None
+    } else {
+        Some((pattern, vars))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Path;
-    use super::{must_build, path_to_regexp, VARIABLE, WILDCARD};
+    use super::{must_build, parse, path_to_regexp};
+    use super::{Constraint, ParamType, Path, Piece};
     use test_case::test_case;
 
+    fn variables(path: &str) -> Vec<Piece> {
+        parse(path)
+            .unwrap()
+            .into_iter()
+            .flat_map(|segment| segment.pieces)
+            .filter(|piece| match piece {
+                Piece::Static(_) => false,
+                _ => true,
+            })
+            .collect()
+    }
+
     #[test_case("/:id/"; "pure dynamic")]
     #[test_case("/user/:id/"; "static prefix")]
     #[test_case("/user/:id/name"; "static prefix and suffix")]
-    fn var_regex_match(path: &str) {
-        let re = must_build(VARIABLE);
-        let cap = re.captures(path);
-        assert!(cap.is_some());
-        assert_eq!("id", &cap.unwrap()["var"]);
+    #[test_case("/-:id/"; "prefix in segment")]
+    #[test_case("/:id-/"; "suffix in segment")]
+    fn var_match(path: &str) {
+        assert_eq!(
+            vec![Piece::Variable("id".to_string(), Constraint::Any)],
+            variables(path)
+        );
     }
 
-    #[test_case("/-:id/"; "invalid prefix")]
-    #[test_case("/:i-d/"; "invalid variable name")]
-    #[test_case("/:id-/"; "invalid suffix")]
-    fn var_regex_mismatch(path: &str) {
-        let re = must_build(VARIABLE);
-        let cap = re.captures(path);
-        assert!(cap.is_none());
+    #[test_case("/:id<\\d+>/" => Constraint::Regex(r"\d+".to_string()); "regex")]
+    #[test_case("/:id<(a|b)<c>>/" => Constraint::Regex(r"(a|b)<c>".to_string()); "nested regex")]
+    #[test_case("/:id:u64/" => Constraint::Type(ParamType::u64); "type")]
+    fn var_constraint(path: &str) -> Constraint {
+        match variables(path).pop() {
+            Some(Piece::Variable(_, constraint)) => constraint,
+            _ => panic!("`{}` should have a variable", path),
+        }
+    }
+
+    #[test_case("/:id<\\d+/"; "unclosed regex")]
+    #[test_case("/:id<(>/"; "invalid regex")]
+    #[test_case("/:id:uuid/"; "unknown type")]
+    fn var_constraint_err(path: &str) {
+        assert!(parse(path).is_err())
     }
 
     #[test_case("*{id}"; "pure dynamic")]
     #[test_case("user-*{id}"; "static prefix")]
     #[test_case("user-*{id}-name"; "static prefix and suffix")]
-    fn wildcard_regex_match(path: &str) {
-        let re = must_build(WILDCARD);
-        let cap = re.captures(path);
-        assert!(cap.is_some());
-        assert_eq!("id", &cap.unwrap()["var"]);
+    fn wildcard_match(path: &str) {
+        assert_eq!(vec![Piece::Wildcard("id".to_string())], variables(path));
     }
 
     #[test_case("*"; "no variable")]
     #[test_case("*{-id}"; "invalid variable name")]
-    fn wildcard_regex_mismatch(path: &str) {
-        let re = must_build(WILDCARD);
-        let cap = re.captures(path);
-        assert!(cap.is_none());
+    fn wildcard_mismatch(path: &str) {
+        assert!(variables(path).is_empty());
     }
 
     #[test_case(r"/:id/" => r"/(?P<id>[^\s/]+)/"; "single variable")]
     #[test_case(r"/:year/:month/:day/" => r"/(?P<year>[^\s/]+)/(?P<month>[^\s/]+)/(?P<day>[^\s/]+)/"; "multiple variable")]
     #[test_case(r"*{id}" => r"(?P<id>\S+)"; "single wildcard")]
     #[test_case(r"*{year}_*{month}_*{day}" => r"(?P<year>\S+)_(?P<month>\S+)_(?P<day>\S+)"; "multiple wildcard")]
+    #[test_case(r"/:id?/" => r"(?:/(?P<id>[^\s/]+))?/"; "optional variable")]
+    #[test_case(r"/:file.:ext/" => r"/(?P<file>[^\s/]+)\.(?P<ext>[^\s/]+)/"; "multiple variable in segment")]
+    #[test_case(r"/:id:u8/" => r"/(?P<id>\d+)/"; "typed variable")]
     fn path_to_regexp_dynamic_pattern(path: &str) -> String {
-        path_to_regexp(path).unwrap().unwrap().0
+        path_to_regexp(&parse(path).unwrap()).unwrap().0
     }
 
     #[test_case(r"/id/")]
     #[test_case(r"/user/post/")]
     fn path_to_regexp_static(path: &str) {
-        assert!(path_to_regexp(path).unwrap().is_none())
+        assert!(path_to_regexp(&parse(path).unwrap()).is_none())
     }
 
     #[test_case(r"/:/"; "missing variable name")]
@@ -186,56 +711,63 @@ mod tests {
     #[test_case(r"*{id}-*{id}"; "wildcard conflict variable")]
     #[test_case(r"/:id/*{id}"; "mix conflict variable")]
     fn path_to_regexp_err(path: &str) {
-        assert!(path_to_regexp(path).is_err())
-    }
-
-    fn path_match(pattern: &str, path: &str) {
-        let pattern: Path = pattern.parse().unwrap();
-        match pattern {
-            Path::Static(pattern) => panic!(format!("`{}` should be dynamic", pattern)),
-            Path::Dynamic(re) => assert!(re.re.is_match(path)),
-        }
-    }
-
-    fn path_not_match(pattern: &str, path: &str) {
-        let pattern: Path = pattern.parse().unwrap();
-        match pattern {
-            Path::Static(pattern) => panic!(format!("`{}` should be dynamic", pattern)),
-            Path::Dynamic(re) => {
-                println!("regex: {}", re.re.to_string());
-                assert!(!re.re.is_match(path))
-            }
-        }
+        assert!(parse(path).is_err())
     }
 
-    #[test_case(r"/user/1/")]
-    #[test_case(r"/user/65535/")]
-    fn single_variable_path_match(path: &str) {
-        path_match(r"/user/:id", path)
-    }
-
-    #[test_case(r"/2000/01/01/")]
-    #[test_case(r"/2020/02/20/")]
-    fn multiple_variable_path_match(path: &str) {
-        path_match(r"/:year/:month/:day", path)
+    #[test]
+    fn url() {
+        let url = |path: &str, params: &[(&str, &str)]| {
+            path.parse::<Path>().unwrap().url(params)
+        };
+        assert_eq!(Ok("/".to_string()), url("/", &[]));
+        assert_eq!(Ok("/user".to_string()), url("/user/", &[]));
+        assert_eq!(Ok("/user/5".to_string()), url("/user/:id", &[("id", "5")]));
+        assert_eq!(
+            Ok("/user/a%2Fb%20c".to_string()),
+            url("/user/:name", &[("name", "a/b c")])
+        );
+        assert_eq!(
+            Ok("/static/css/app%20.css".to_string()),
+            url("/static/*{path}", &[("path", "css/app .css")])
+        );
+        assert_eq!(
+            Ok("/roa.tar.gz".to_string()),
+            url("/:file.:ext", &[("file", "roa.tar"), ("ext", "gz")])
+        );
+        assert_eq!(Ok("/posts".to_string()), url("/posts/:page?", &[]));
+        assert_eq!(
+            Ok("/posts/2".to_string()),
+            url("/posts/:page?", &[("page", "2")])
+        );
+        assert!(url("/user/:id", &[]).is_err());
+        assert!(url("/user/:id", &[("id", "5"), ("name", "Hexilee")]).is_err());
+        assert!(url("/user", &[("id", "5")]).is_err());
     }
 
-    #[test_case(r"/usr/include/boost/boost.h/")]
-    #[test_case(r"/usr/include/uv/uv.h/")]
-    fn segment_wildcard_path_match(path: &str) {
-        path_match(r"/usr/include/*{dir}/*{file}.h", path)
+    #[test]
+    fn shapes() {
+        let shapes = |path: &str| path.parse::<Path>().unwrap().shapes();
+        assert_eq!(shapes("/user/:id"), shapes("/user/:name"));
+        assert_ne!(shapes("/user/:id"), shapes("/user/:id:u64"));
+        assert!(shapes("/posts/:page?").is_superset(&shapes("/posts")));
+        assert!(shapes("/posts/:page?").is_superset(&shapes("/posts/:id")));
     }
 
-    #[test_case(r"/srv/static/app/index.html/")]
-    #[test_case(r"/srv/static/../../index.html/")]
-    fn full_wildcard_path_match(path: &str) {
-        path_match(r"/srv/static/*{path}/", path)
+    #[test]
+    fn vars() {
+        let vars = |path: &str| path.parse::<Path>().unwrap().vars();
+        assert!(vars("/user").is_empty());
+        assert_eq!(vec!["id"], vars("/user/:id:u64"));
+        assert_eq!(vec!["user", "file", "ext"], vars("/:user/*{file}.:ext"));
     }
 
-    #[test_case(r"/srv/app/index.html/")]
-    #[test_case(r"/srv/../../index.html/")]
-    fn variable_path_not_match(path: &str) {
-        path_not_match(r"/srv/:path/", path)
+    #[test]
+    fn variants() {
+        let variants = |path: &str| path.parse::<Path>().unwrap().variants().len();
+        assert_eq!(1, variants("/user"));
+        assert_eq!(1, variants("/user/:id"));
+        assert_eq!(2, variants("/posts/:page?"));
+        assert_eq!(4, variants("/:year?/:month?"));
     }
 
     #[should_panic]
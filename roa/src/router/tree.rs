@@ -0,0 +1,326 @@
+use super::path::{cmp_priority, Pattern, Segment};
+use std::collections::HashMap;
+
+/// Router parameters in order of the path.
+type Params = Vec<(String, String)>;
+
+/// A segment tree of dynamic paths.
+///
+/// Each node tries its children in a deterministic order:
+/// static segments, then dynamic segments sorted by rank
+/// (mixed, constrained variable, variable), and wildcards at last.
+/// It backtracks to the next child only if the deeper match fails,
+/// so the cost of matching is about the length of path in most cases.
+///
+/// In the worst case, every node is visited once per lookup,
+/// as each node consumes a fixed number of segments;
+/// so the cost is linear in the size of the tree rather than the length of path,
+/// plus matching the rest of path against each wildcard tried.
+pub struct Node<T> {
+    /// Children of static segments.
+    statics: HashMap<String, Node<T>>,
+
+    /// Children of dynamic segments, in order of rank.
+    dynamics: Vec<(Segment, Pattern, Node<T>)>,
+
+    /// Wildcards matching the rest of path, in order of priority.
+    wildcards: Vec<(Vec<Segment>, Pattern, T)>,
+
+    /// Value of the path ending at this node.
+    value: Option<T>,
+}
+
+impl<T> Node<T> {
+    /// Construct an empty node.
+    pub fn new() -> Self {
+        Self {
+            statics: HashMap::new(),
+            dynamics: Vec::new(),
+            wildcards: Vec::new(),
+            value: None,
+        }
+    }
+
+    /// Insert segments, optional segments are expanded.
+    ///
+    /// Segments after a wildcard are matched as a whole.
+    pub fn insert(&mut self, segments: &[Segment], value: T)
+    where
+        T: Clone,
+    {
+        let (segment, rest) = match segments.split_first() {
+            Some(pair) => pair,
+            None => {
+                self.value = Some(value);
+                return;
+            }
+        };
+
+        if segment.is_wildcard() {
+            self.wildcards.push((
+                segments.to_vec(),
+                Pattern::new(segments.to_vec()),
+                value,
+            ));
+            // stable sort, the more specific wildcard takes precedence
+            self.wildcards
+                .sort_by(|(a, _, _), (b, _, _)| cmp_priority(a, b));
+            return;
+        }
+
+        if segment.optional {
+            self.insert(rest, value.clone());
+        }
+
+        let child = match segment.static_text() {
+            Some(text) => self.statics.entry(text).or_default(),
+            None => self.dynamic_child(segment),
+        };
+        child.insert(rest, value);
+    }
+
+    /// Get or insert a child of dynamic segment.
+    fn dynamic_child(&mut self, segment: &Segment) -> &mut Node<T> {
+        let segment = Segment {
+            pieces: segment.pieces.clone(),
+            optional: false,
+        };
+        let index = match self.dynamics.iter().position(|(s, _, _)| *s == segment) {
+            Some(index) => index,
+            None => {
+                let rank = segment.rank();
+                // keep stable, the earlier inserted child takes precedence
+                let index = self
+                    .dynamics
+                    .iter()
+                    .position(|(s, _, _)| s.rank() > rank)
+                    .unwrap_or(self.dynamics.len());
+                let pattern = Pattern::new(vec![segment.clone()]);
+                self.dynamics.insert(index, (segment, pattern, Node::new()));
+                index
+            }
+        };
+        &mut self.dynamics[index].2
+    }
+
+    /// Find value by a standardized path, with router parameters.
+    pub fn find(&self, path: &str) -> Option<(&T, Params)> {
+        let path = path.trim_matches('/');
+        // segments with their byte offsets in path
+        let mut segments = Vec::new();
+        if !path.is_empty() {
+            let mut offset = 0;
+            for segment in path.split('/') {
+                segments.push((offset, segment));
+                offset += segment.len() + 1;
+            }
+        }
+        let mut params = Vec::new();
+        let value = self.find_with(path, &segments, &mut params)?;
+        Some((value, params))
+    }
+
+    fn find_with(
+        &self,
+        path: &str,
+        segments: &[(usize, &str)],
+        params: &mut Params,
+    ) -> Option<&T> {
+        if let Some(((_, segment), rest)) = segments.split_first() {
+            if let Some(child) = self.statics.get(*segment) {
+                if let Some(value) = child.find_with(path, rest, params) {
+                    return Some(value);
+                }
+            }
+
+            for (_, pattern, child) in self.dynamics.iter() {
+                if let Some(captures) = pattern.captures(segment) {
+                    let len = params.len();
+                    params.extend(captures);
+                    if let Some(value) = child.find_with(path, rest, params) {
+                        return Some(value);
+                    }
+                    params.truncate(len);
+                }
+            }
+        } else if let Some(value) = self.value.as_ref() {
+            return Some(value);
+        }
+
+        if self.wildcards.is_empty() {
+            return None;
+        }
+        let rest = segments.first().map_or("", |(offset, _)| &path[*offset..]);
+        for (_, pattern, value) in self.wildcards.iter() {
+            if let Some(captures) = pattern.captures(rest) {
+                params.extend(captures);
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Node;
+    use crate::router::path::{standardize_path, Path};
+    use test_case::test_case;
+
+    fn tree(patterns: &[&'static str]) -> Node<&'static str> {
+        let mut tree = Node::new();
+        for pattern in patterns {
+            match pattern.parse().unwrap() {
+                Path::Static(pattern) => panic!("`{}` should be dynamic", pattern),
+                Path::Dynamic(path) => tree.insert(path.inner_segments(), *pattern),
+            }
+        }
+        tree
+    }
+
+    fn find(
+        tree: &Node<&'static str>,
+        path: &str,
+    ) -> Option<(&'static str, Vec<(String, String)>)> {
+        tree.find(&standardize_path(path))
+            .map(|(pattern, params)| (*pattern, params))
+    }
+
+    fn captures(pattern: &'static str, path: &str) -> Option<Vec<(String, String)>> {
+        find(&tree(&[pattern]), path).map(|(_, params)| params)
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test_case(r"/user/1/")]
+    #[test_case(r"/user/65535/")]
+    fn single_variable_path_match(path: &str) {
+        assert!(captures(r"/user/:id", path).is_some())
+    }
+
+    #[test_case(r"/2000/01/01/")]
+    #[test_case(r"/2020/02/20/")]
+    fn multiple_variable_path_match(path: &str) {
+        assert!(captures(r"/:year/:month/:day", path).is_some())
+    }
+
+    #[test_case(r"/usr/include/boost/boost.h/")]
+    #[test_case(r"/usr/include/uv/uv.h/")]
+    fn segment_wildcard_path_match(path: &str) {
+        assert!(captures(r"/usr/include/*{dir}/*{file}.h", path).is_some())
+    }
+
+    #[test_case(r"/srv/static/app/index.html/")]
+    #[test_case(r"/srv/static/../../index.html/")]
+    fn full_wildcard_path_match(path: &str) {
+        assert!(captures(r"/srv/static/*{path}/", path).is_some())
+    }
+
+    #[test_case(r"/srv/app/index.html/")]
+    #[test_case(r"/srv/../../index.html/")]
+    fn variable_path_not_match(path: &str) {
+        assert!(captures(r"/srv/:path/", path).is_none())
+    }
+
+    #[test]
+    fn constrained_path_match() {
+        assert!(captures(r"/user/:id<\d+>", "/user/1/").is_some());
+        assert!(captures(r"/user/:id<\d+>", "/user/x/").is_none());
+        assert!(captures(r"/user/:id:u8", "/user/255/").is_some());
+        assert!(captures(r"/user/:id:u8", "/user/256/").is_none());
+        assert!(captures(r"/user/:slug<[a-z-]+>", "/user/hexi-lee/").is_some());
+        assert!(captures(r"/user/:slug<[a-z-]+>", "/user/Hexilee/").is_none());
+    }
+
+    #[test]
+    fn optional_path_match() {
+        assert_eq!(params(&[]), captures(r"/posts/:page?", "/posts/"));
+        assert_eq!(
+            params(&[("page", "2")]),
+            captures(r"/posts/:page?", "/posts/2/")
+        );
+        assert_eq!(params(&[]), captures(r"/:page?", "/"));
+        assert_eq!(
+            params(&[("path", "a/b")]),
+            captures(r"/static/*{path}?", "/static/a/b")
+        );
+        assert_eq!(params(&[]), captures(r"/static/*{path}?", "/static"));
+    }
+
+    #[test]
+    fn multiple_variable_segment_match() {
+        assert_eq!(
+            params(&[("file", "roa.tar"), ("ext", "gz")]),
+            captures(r"/:file.:ext", "/roa.tar.gz/")
+        );
+    }
+
+    #[test]
+    fn wildcard_params() {
+        assert_eq!(
+            params(&[("dir", "boost/detail"), ("file", "config")]),
+            captures(
+                r"/usr/include/*{dir}/*{file}.h",
+                "/usr/include/boost/detail/config.h"
+            )
+        );
+        assert_eq!(
+            params(&[("user", "hexilee"), ("path", "roa/src")]),
+            captures(r"/:user/*{path}/tree", "/hexilee/roa/src/tree")
+        );
+    }
+
+    #[test]
+    fn priority() {
+        let routes = tree(&[
+            "/*{path}",
+            "/:name",
+            "/:id:u64",
+            "/:file.:ext",
+            "/user/:id",
+            "/:name/*{path}",
+            "/:name/posts",
+        ]);
+        let matched = |path| find(&routes, path).map(|(pattern, _)| pattern);
+        assert_eq!(Some("/user/:id"), matched("/user/1"));
+        assert_eq!(Some("/:id:u64"), matched("/1"));
+        assert_eq!(Some("/:file.:ext"), matched("/1.json"));
+        assert_eq!(Some("/:name"), matched("/user"));
+        assert_eq!(Some("/user/:id"), matched("/user/posts"));
+        assert_eq!(Some("/:name/posts"), matched("/group/posts"));
+        assert_eq!(Some("/:name/*{path}"), matched("/user/1/posts"));
+        assert_eq!(Some("/:name/*{path}"), matched("/user.json/1"));
+        assert_eq!(None, matched("/"));
+
+        let routes = tree(&["/*{path}", "/:name/:id:u64"]);
+        assert_eq!(
+            Some("/*{path}"),
+            find(&routes, "/user/posts").map(|(pattern, _)| pattern)
+        );
+    }
+
+    #[test]
+    fn backtrack() {
+        let tree = tree(&["/user/:id/posts", "/:group/:name/comments"]);
+        assert_eq!(
+            Some((
+                "/:group/:name/comments",
+                params(&[("group", "user"), ("name", "1")]).unwrap()
+            )),
+            find(&tree, "/user/1/comments")
+        );
+    }
+}
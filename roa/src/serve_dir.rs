@@ -0,0 +1,556 @@
+//! This module provides an endpoint `ServeDir` to serve static files in a directory.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::router::{get, Router, RouterError};
+//! use roa::serve_dir::ServeDir;
+//! use roa::App;
+//!
+//! # fn main() -> Result<(), RouterError> {
+//! let assets = ServeDir::new("./assets")
+//!     .listing(true)
+//!     .precompressed(true)
+//!     .cache_control("public, max-age=3600");
+//! let router = Router::new().on("/assets/*{path}", get(assets));
+//! let app = App::new().end(router.routes("/")?);
+//! Ok(())
+//! # }
+//! ```
+
+use crate::body::file::{write_file_as, DispositionType};
+use crate::http::header::{
+    HeaderValue, ACCEPT_ENCODING, ALLOW, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE,
+    VARY,
+};
+use crate::http::{Method, StatusCode};
+use crate::negotiate::Negotiate;
+#[cfg(feature = "router")]
+use crate::router::RouterParam;
+use crate::util::escape_html;
+use crate::{async_trait, status, throw, Context, Endpoint, Result, State};
+use async_std::path::{Path, PathBuf};
+use async_std::prelude::*;
+use bytesize::ByteSize;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+
+/// Precompressed siblings, in order of precedence.
+const PRECOMPRESSED: &[(&str, &str)] = &[("br", ".br"), ("gzip", ".gz")];
+
+/// Characters to be encoded in a path segment of links.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// An endpoint to serve static files in a root directory.
+///
+/// The relative path is the router variable "path" if it exists,
+/// so it can be mounted under a router by a `*{path}` wildcard;
+/// otherwise it's the path of uri.
+///
+/// A path containing ".." segments is rejected by 400 BAD REQUEST,
+/// and a path containing hidden segments starting with "." like ".git" or ".env"
+/// is rejected by 404 NOT FOUND unless `ServeDir::hidden` is enabled.
+/// Only GET and HEAD are allowed.
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+    index: Vec<String>,
+    listing: bool,
+    hidden: bool,
+    precompressed: bool,
+    cache_control: Option<HeaderValue>,
+    cache_control_by_extension: Vec<(String, HeaderValue)>,
+    fallback: Option<String>,
+}
+
+/// An entry of directory listing.
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+}
+
+impl ServeDir {
+    /// Construct by a root directory.
+    ///
+    /// It serves "index.html" for directories, without listing, hidden files,
+    /// precompressed files, "Cache-Control" or fallback by default.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            index: vec!["index.html".to_string()],
+            listing: false,
+            hidden: false,
+            precompressed: false,
+            cache_control: None,
+            cache_control_by_extension: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Set index files of a directory, the first existing one is served.
+    pub fn index(mut self, names: &[&str]) -> Self {
+        self.index = names.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    /// Render an html listing for a directory without index file.
+    pub fn listing(mut self, enabled: bool) -> Self {
+        self.listing = enabled;
+        self
+    }
+
+    /// Serve and list hidden files and directories, whose names start with ".".
+    pub fn hidden(mut self, enabled: bool) -> Self {
+        self.hidden = enabled;
+        self
+    }
+
+    /// Serve ".br" or ".gz" sibling of a file if it exists and client accepts it.
+    pub fn precompressed(mut self, enabled: bool) -> Self {
+        self.precompressed = enabled;
+        self
+    }
+
+    /// Set "Cache-Control" of files.
+    ///
+    /// # Panics
+    ///
+    /// Panics if value is invalid.
+    pub fn cache_control(mut self, value: &str) -> Self {
+        self.cache_control = Some(value.parse().expect("invalid cache-control"));
+        self
+    }
+
+    /// Set "Cache-Control" of files with an extension,
+    /// which overrides the one set by `ServeDir::cache_control`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::serve_dir::ServeDir;
+    ///
+    /// let dist = ServeDir::new("./dist")
+    ///     .cache_control("public, max-age=31536000, immutable")
+    ///     .cache_control_for("html", "no-cache");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if value is invalid.
+    pub fn cache_control_for(mut self, extension: &str, value: &str) -> Self {
+        self.cache_control_by_extension.push((
+            extension.trim_start_matches('.').to_string(),
+            value.parse().expect("invalid cache-control"),
+        ));
+        self
+    }
+
+    /// Serve a file relative to root if the path is not found,
+    /// like "index.html" of a single page application.
+    pub fn fallback(mut self, path: impl Into<String>) -> Self {
+        self.fallback = Some(path.into());
+        self
+    }
+
+    /// Get the requested path relative to root.
+    fn relative_path<S: State>(&self, ctx: &Context<S>) -> Result<String> {
+        #[cfg(feature = "router")]
+        {
+            if let Some(path) = ctx.param("path") {
+                return Ok(path.to_string());
+            }
+        }
+        percent_decode_str(ctx.uri().path())
+            .decode_utf8()
+            .map(|path| path.into_owned())
+            .map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+    }
+
+    /// Join root with a relative path, reject path traversal and hidden files.
+    fn resolve(&self, relative: &str) -> Result<PathBuf> {
+        let mut path = self.root.clone();
+        for segment in relative.split('/') {
+            match segment {
+                "" | "." => (),
+                ".." => throw!(StatusCode::BAD_REQUEST, "invalid path"),
+                _ if segment.starts_with('.') && !self.hidden => {
+                    throw!(StatusCode::NOT_FOUND, "path not found")
+                }
+                _ if segment.contains('\\')
+                    || segment.contains('\0')
+                    || (cfg!(windows) && segment.contains(':')) =>
+                {
+                    throw!(StatusCode::BAD_REQUEST, "invalid path")
+                }
+                _ => path.push(segment),
+            }
+        }
+        Ok(path)
+    }
+
+    /// Find a precompressed sibling acceptable by client.
+    async fn find_precompressed<S: State>(
+        &self,
+        ctx: &Context<S>,
+        path: &Path,
+    ) -> Option<(PathBuf, &'static str)> {
+        ctx.get(ACCEPT_ENCODING)?;
+        let mut offers = Vec::new();
+        for (encoding, extension) in PRECOMPRESSED {
+            if sibling(path, extension).is_file().await {
+                offers.push(*encoding);
+            }
+        }
+        offers.push("identity");
+        let encoding = ctx.accepts_encoding(&offers)?;
+        PRECOMPRESSED
+            .iter()
+            .find(|(offer, _)| *offer == encoding)
+            .map(|(encoding, extension)| (sibling(path, extension), *encoding))
+    }
+
+    /// Serve a file.
+    async fn serve_file<S: State>(&self, ctx: &mut Context<S>, path: &Path) -> Result {
+        let filename = path
+            .file_name()
+            .map(|filename| filename.to_string_lossy().into_owned());
+        let mut encoding = None;
+        let mut file = path.to_path_buf();
+        if self.precompressed {
+            if let Some((precompressed, coding)) =
+                self.find_precompressed(ctx, path).await
+            {
+                file = precompressed;
+                encoding = Some(coding);
+            }
+            ctx.resp
+                .headers
+                .append(VARY, HeaderValue::from_static("accept-encoding"));
+        }
+        write_file_as(ctx, &file, filename.as_deref(), DispositionType::Inline).await?;
+        if let Some(encoding) = encoding {
+            ctx.resp
+                .headers
+                .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+        if let Some(value) = self.cache_control_of(filename.as_deref()) {
+            ctx.resp.headers.insert(CACHE_CONTROL, value.clone());
+        }
+        Ok(())
+    }
+
+    /// Get "Cache-Control" of a file.
+    fn cache_control_of(&self, filename: Option<&str>) -> Option<&HeaderValue> {
+        let extension = filename
+            .and_then(|name| std::path::Path::new(name).extension())
+            .map(|extension| extension.to_string_lossy());
+        extension
+            .and_then(|extension| {
+                self.cache_control_by_extension
+                    .iter()
+                    .find(|(ext, _)| ext.eq_ignore_ascii_case(&extension))
+                    .map(|(_, value)| value)
+            })
+            .or(self.cache_control.as_ref())
+    }
+
+    /// Render listing of a directory.
+    async fn list<S: State>(
+        &self,
+        ctx: &mut Context<S>,
+        dir: &Path,
+        relative: &str,
+    ) -> Result {
+        let mut entries = Vec::new();
+        let mut read_dir = dir.read_dir().await?;
+        while let Some(entry) = read_dir.next().await {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') && !self.hidden {
+                continue;
+            }
+            let metadata = entry.metadata().await?;
+            entries.push(Entry {
+                name,
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+            });
+        }
+        entries
+            .sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+        let mut base = ctx.uri().path().to_string();
+        if !base.ends_with('/') {
+            base.push('/');
+        }
+        let title = escape_html(&format!("/{}", relative.trim_matches('/')));
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n\
+             <title>Index of {0}</title>\n</head>\n<body>\n<h1>Index of {0}</h1>\n<hr>\n\
+             <table>\n<thead><tr><th>Name</th><th>Size</th></tr></thead>\n<tbody>\n",
+            title
+        );
+        if !relative.trim_matches('/').is_empty() {
+            // absolute, as uri of a directory may not end with "/"
+            let trimmed = base.trim_end_matches('/');
+            let parent = &trimmed[..=trimmed.rfind('/').unwrap_or(0)];
+            html.push_str(&format!(
+                "<tr><td><a href=\"{}\">../</a></td><td>-</td></tr>\n",
+                escape_html(parent)
+            ));
+        }
+        for entry in entries {
+            let (suffix, size) = if entry.is_dir {
+                ("/", "-".to_string())
+            } else {
+                ("", ByteSize(entry.size).to_string())
+            };
+            html.push_str(&format!(
+                "<tr><td><a href=\"{}{}{}\">{}{}</a></td><td>{}</td></tr>\n",
+                escape_html(&base),
+                utf8_percent_encode(&entry.name, SEGMENT),
+                suffix,
+                escape_html(&entry.name),
+                suffix,
+                size
+            ));
+        }
+        html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+        ctx.resp.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/html; charset=utf-8"),
+        );
+        ctx.resp.write(html);
+        Ok(())
+    }
+}
+
+/// Path of a sibling with an extra extension.
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(extension);
+    path.into()
+}
+
+#[async_trait(?Send)]
+impl<'a, S: State> Endpoint<'a, S> for ServeDir {
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        if ctx.method() != Method::GET && ctx.method() != Method::HEAD {
+            return Err(
+                status!(StatusCode::METHOD_NOT_ALLOWED).header(ALLOW, "GET, HEAD")
+            );
+        }
+        let relative = self.relative_path(ctx)?;
+        let path = self.resolve(&relative)?;
+        if path.is_file().await {
+            return self.serve_file(ctx, &path).await;
+        }
+        if path.is_dir().await {
+            for index in self.index.iter() {
+                let index = path.join(index);
+                if index.is_file().await {
+                    return self.serve_file(ctx, &index).await;
+                }
+            }
+            if self.listing {
+                return self.list(ctx, &path, &relative).await;
+            }
+        }
+        match &self.fallback {
+            Some(fallback) => self.serve_file(ctx, &self.root.join(fallback)).await,
+            None => throw!(StatusCode::NOT_FOUND, "path not found"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ServeDir;
+    use crate::http::header::{
+        ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, VARY,
+    };
+    use crate::http::StatusCode;
+    use crate::test::Client;
+    use crate::App;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Create a directory tree for test.
+    fn tree(name: &str) -> std::io::Result<PathBuf> {
+        let root = std::env::temp_dir().join(format!("roa-serve-dir-{}", name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("docs"))?;
+        fs::create_dir_all(root.join("empty"))?;
+        fs::write(root.join("index.html"), "<p>index</p>")?;
+        fs::write(root.join("app.js"), "console.log(1)")?;
+        fs::write(root.join("app.js.gz"), "gzipped")?;
+        fs::write(root.join("app.js.br"), "brotli")?;
+        fs::write(root.join("docs").join("a <b>.txt"), "a b")?;
+        fs::create_dir_all(root.join(".git"))?;
+        fs::write(root.join(".git").join("config"), "secret")?;
+        fs::write(root.join(".env"), "secret")?;
+        Ok(root)
+    }
+
+    #[async_std::test]
+    async fn serve() -> Result<(), Box<dyn std::error::Error>> {
+        let root = tree("serve")?;
+        let client = Client::new(
+            App::new().end(
+                ServeDir::new(root)
+                    .cache_control("max-age=60")
+                    .cache_control_for("html", "no-cache"),
+            ),
+        );
+        let resp = client.get("/app.js").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("max-age=60", resp.headers[CACHE_CONTROL]);
+        assert_eq!("console.log(1)", resp.text().await?);
+
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("no-cache", resp.headers[CACHE_CONTROL]);
+        assert_eq!("<p>index</p>", resp.text().await?);
+
+        let resp = client.get("/docs/a%20%3Cb%3E.txt").send().await;
+        assert_eq!("a b", resp.text().await?);
+
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            client.get("/docs").send().await.status
+        );
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            client.get("/none").send().await.status
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            client.get("/docs/../../etc/passwd").send().await.status
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            client.get("/docs/%2E%2E/index.html").send().await.status
+        );
+        assert_eq!(
+            StatusCode::METHOD_NOT_ALLOWED,
+            client.post("/app.js").send().await.status
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn listing() -> Result<(), Box<dyn std::error::Error>> {
+        let root = tree("listing")?;
+        let client =
+            Client::new(App::new().end(ServeDir::new(root).index(&[]).listing(true)));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("text/html; charset=utf-8", resp.headers[CONTENT_TYPE]);
+        let html = resp.text().await?;
+        assert!(html.contains("<a href=\"/docs/\">docs/</a>"));
+        assert!(html.contains("<a href=\"/app.js\">app.js</a>"));
+        assert!(html.find("empty/").unwrap() < html.find("app.js").unwrap());
+        assert!(!html.contains("../"));
+        assert!(!html.contains(".git") && !html.contains(".env"));
+
+        let html = client.get("/docs").send().await.text().await?;
+        assert!(html.contains("<h1>Index of /docs</h1>"));
+        assert!(html.contains("<a href=\"/\">../</a>"));
+        assert!(html.contains("<a href=\"/docs/a%20%3Cb%3E.txt\">a &lt;b&gt;.txt</a>"));
+
+        let html = client.get("/docs/").send().await.text().await?;
+        assert!(html.contains("<a href=\"/\">../</a>"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn hidden() -> Result<(), Box<dyn std::error::Error>> {
+        let root = tree("hidden")?;
+        let client = Client::new(App::new().end(ServeDir::new(&root).listing(true)));
+        for path in &["/.env", "/.git/config", "/.git", "/.git/", "/docs/../.env"] {
+            let resp = client.get(*path).send().await;
+            assert_ne!(StatusCode::OK, resp.status, "{} should be hidden", path);
+        }
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            client.get("/.git/config").send().await.status
+        );
+
+        let client = Client::new(
+            App::new().end(ServeDir::new(&root).index(&[]).listing(true).hidden(true)),
+        );
+        let resp = client.get("/.git/config").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("secret", resp.text().await?);
+        let html = client.get("/").send().await.text().await?;
+        assert!(html.contains("<a href=\"/.git/\">.git/</a>"));
+        assert!(html.contains("<a href=\"/.env\">.env</a>"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn precompressed() -> Result<(), Box<dyn std::error::Error>> {
+        let root = tree("precompressed")?;
+        let client =
+            Client::new(App::new().end(ServeDir::new(root).precompressed(true)));
+        for (accept_encoding, encoding, body) in &[
+            ("gzip, br", Some("br"), "brotli"),
+            ("gzip", Some("gzip"), "gzipped"),
+            ("deflate", None, "console.log(1)"),
+        ] {
+            let resp = client
+                .get("/app.js")
+                .header(ACCEPT_ENCODING, *accept_encoding)
+                .send()
+                .await;
+            assert_eq!(StatusCode::OK, resp.status);
+            assert_eq!("accept-encoding", resp.headers[VARY]);
+            assert_eq!(
+                *encoding,
+                resp.headers
+                    .get(CONTENT_ENCODING)
+                    .map(|value| value.to_str().unwrap())
+            );
+            assert!(resp.headers[CONTENT_TYPE].to_str()?.contains("javascript"));
+            assert_eq!(*body, resp.text().await?);
+        }
+        let resp = client.get("/app.js").send().await;
+        assert!(resp.headers.get(CONTENT_ENCODING).is_none());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn fallback() -> Result<(), Box<dyn std::error::Error>> {
+        let root = tree("fallback")?;
+        let client =
+            Client::new(App::new().end(ServeDir::new(root).fallback("index.html")));
+        let resp = client.get("/users/1").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("<p>index</p>", resp.text().await?);
+        Ok(())
+    }
+
+    #[cfg(feature = "router")]
+    #[async_std::test]
+    async fn router() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::router::{get, Router};
+        let root = tree("router")?;
+        let router = Router::new().on("/static/*{path}", get(ServeDir::new(root)));
+        let client = Client::new(App::new().end(router.routes("/")?));
+        let resp = client.get("/static/app.js").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("console.log(1)", resp.text().await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+//! This module provides a context extension `Sse`,
+//! which is used to push server-sent events to client.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::sse::{Event, Sse};
+//! use roa::{App, Context, Result};
+//! use futures::stream::{self, StreamExt};
+//!
+//! async fn end(ctx: &mut Context) -> Result {
+//!     // resume from the last event received by client.
+//!     let start: u64 = ctx
+//!         .last_event_id()
+//!         .and_then(|id| id.parse().ok())
+//!         .map(|id: u64| id + 1)
+//!         .unwrap_or(0);
+//!     let events = stream::iter(start..start + 10)
+//!         .map(|id| Event::new().id(id.to_string()).data(format!("tick {}", id)));
+//!     ctx.write_sse(events);
+//!     Ok(())
+//! }
+//!
+//! let app = App::new().end(end);
+//! ```
+
+use crate::http::header::{HeaderValue, CACHE_CONTROL, CONTENT_TYPE};
+use crate::{Context, State};
+use bytes::Bytes;
+use futures::{Future, Stream};
+use futures_timer::Delay;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::pin::Pin;
+use std::task::{self, Poll};
+use std::time::Duration;
+
+/// Default interval of keep-alive comments.
+pub const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// A server-sent event.
+///
+/// ### Example
+/// ```rust
+/// use roa::sse::Event;
+/// use std::time::Duration;
+///
+/// let event = Event::new()
+///     .id("1")
+///     .event("message")
+///     .data("Hello\nWorld")
+///     .retry(Duration::from_secs(3));
+/// assert_eq!(
+///     "id: 1\nevent: message\ndata: Hello\ndata: World\nretry: 3000\n\n",
+///     event.to_string()
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: Option<String>,
+    retry: Option<Duration>,
+    comment: Option<String>,
+}
+
+/// A context extension to write server-sent events.
+pub trait Sse {
+    /// Get the "Last-Event-ID" sent by client when it reconnects.
+    fn last_event_id(&self) -> Option<&str>;
+
+    /// Write a stream of events as "text/event-stream",
+    /// with a keep-alive comment every 15 seconds.
+    ///
+    /// The response is marked by "Cache-Control: no-cache, no-transform",
+    /// so it won't be cached or compressed.
+    fn write_sse<St>(&mut self, stream: St)
+    where
+        St: 'static + Stream<Item = Event> + Sync + Send;
+
+    /// Write a stream of events as "text/event-stream",
+    /// with a custom keep-alive interval, None to disable keep-alive.
+    fn write_sse_with<St>(&mut self, stream: St, keep_alive: Option<Duration>)
+    where
+        St: 'static + Stream<Item = Event> + Sync + Send;
+}
+
+/// Event stream with keep-alive comments.
+struct EventStream<St> {
+    events: St,
+    keep_alive: Option<(Duration, Delay)>,
+}
+
+/// Remove line breaks from a single-line field.
+fn single_line(value: impl Into<String>) -> String {
+    let mut value = value.into();
+    value.retain(|c| c != '\n' && c != '\r');
+    value
+}
+
+impl Event {
+    /// Construct an empty event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set id of this event, line breaks are removed.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(single_line(id));
+        self
+    }
+
+    /// Set type of this event, line breaks are removed.
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(single_line(event));
+        self
+    }
+
+    /// Set data of this event, multi-line data is sent as multiple "data" fields.
+    pub fn data(mut self, data: impl Into<String>) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// Set data of this event as json.
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    pub fn json<T: serde::Serialize>(self, data: &T) -> serde_json::Result<Self> {
+        Ok(self.data(serde_json::to_string(data)?))
+    }
+
+    /// Set reconnection time of client.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Set a comment, which is ignored by client.
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+}
+
+/// Write each line of a multi-line field.
+fn write_lines(f: &mut Formatter<'_>, name: &str, value: &str) -> fmt::Result {
+    for line in value.split('\n') {
+        f.write_fmt(format_args!("{}: {}\n", name, line.trim_end_matches('\r')))?;
+    }
+    Ok(())
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(comment) = &self.comment {
+            write_lines(f, "", comment)?;
+        }
+        if let Some(id) = &self.id {
+            f.write_fmt(format_args!("id: {}\n", id))?;
+        }
+        if let Some(event) = &self.event {
+            f.write_fmt(format_args!("event: {}\n", event))?;
+        }
+        if let Some(data) = &self.data {
+            write_lines(f, "data", data)?;
+        }
+        if let Some(retry) = self.retry {
+            f.write_fmt(format_args!("retry: {}\n", retry.as_millis()))?;
+        }
+        f.write_str("\n")
+    }
+}
+
+impl<St> EventStream<St> {
+    fn new(events: St, keep_alive: Option<Duration>) -> Self {
+        Self {
+            events,
+            keep_alive: keep_alive.map(|interval| (interval, Delay::new(interval))),
+        }
+    }
+
+    /// Postpone the next keep-alive comment.
+    fn reset(&mut self) {
+        if let Some((interval, delay)) = &mut self.keep_alive {
+            delay.reset(*interval);
+        }
+    }
+}
+
+impl<St> Stream for EventStream<St>
+where
+    St: Stream<Item = Event> + Unpin,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.events).poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                self.reset();
+                return Poll::Ready(Some(Ok(event.to_string().into())));
+            }
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => (),
+        }
+        if let Some((_, delay)) = &mut self.keep_alive {
+            if Pin::new(delay).poll(cx).is_ready() {
+                self.reset();
+                return Poll::Ready(Some(Ok(Bytes::from_static(b":\n\n"))));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<S: State> Sse for Context<S> {
+    #[inline]
+    fn last_event_id(&self) -> Option<&str> {
+        self.get("last-event-id")
+    }
+
+    #[inline]
+    fn write_sse<St>(&mut self, stream: St)
+    where
+        St: 'static + Stream<Item = Event> + Sync + Send,
+    {
+        self.write_sse_with(stream, Some(KEEP_ALIVE))
+    }
+
+    #[inline]
+    fn write_sse_with<St>(&mut self, stream: St, keep_alive: Option<Duration>)
+    where
+        St: 'static + Stream<Item = Event> + Sync + Send,
+    {
+        self.resp
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+        self.resp.headers.insert(
+            CACHE_CONTROL,
+            HeaderValue::from_static("no-cache, no-transform"),
+        );
+        self.resp
+            .write_stream(EventStream::new(Box::pin(stream), keep_alive));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Sse};
+    use crate::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+    use crate::http::StatusCode;
+    use crate::test::Client;
+    use crate::{App, Context};
+    use futures::stream;
+    use futures_timer::Delay;
+    use std::time::Duration;
+
+    #[test]
+    fn event_format() {
+        assert_eq!("\n", Event::new().to_string());
+        assert_eq!("data: \n\n", Event::new().data("").to_string());
+        assert_eq!(
+            "data: a\ndata: \ndata: b\n\n",
+            Event::new().data("a\r\n\nb").to_string()
+        );
+        assert_eq!(
+            ": ping\nid: 12\n\n",
+            Event::new().id("1\n2").comment("ping").to_string()
+        );
+    }
+
+    #[async_std::test]
+    async fn write_sse() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let last_id = ctx.last_event_id().unwrap_or("none").to_string();
+            let events = stream::iter(vec![
+                Event::new().id("1").data(last_id),
+                Event::new().event("end").data("bye"),
+            ]);
+            ctx.write_sse(events);
+            Ok(())
+        }
+        let client = Client::new(App::new().end(end));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("text/event-stream", resp.headers[CONTENT_TYPE]);
+        assert_eq!("no-cache, no-transform", resp.headers[CACHE_CONTROL]);
+        assert_eq!(
+            "id: 1\ndata: none\n\nevent: end\ndata: bye\n\n",
+            resp.text().await?
+        );
+
+        let resp = client.get("/").header("last-event-id", "0").send().await;
+        assert!(resp.text().await?.starts_with("id: 1\ndata: 0\n\n"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn keep_alive() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let events = stream::once(async {
+                Delay::new(Duration::from_millis(250)).await;
+                Event::new().data("late")
+            });
+            ctx.write_sse_with(events, Some(Duration::from_millis(100)));
+            Ok(())
+        }
+        let client = Client::new(App::new().end(end));
+        let text = client.get("/").send().await.text().await?;
+        assert!(text.starts_with(":\n\n:\n\n"));
+        assert!(text.ends_with("data: late\n\n"));
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 use super::TcpIncoming;
 use async_std::sync::Arc;
-use roa_core::{App, Endpoint, Executor, Server, State};
+use futures_timer::Delay;
+use roa_core::{App, Endpoint, Executor, GracefulServer, Server, State};
+use std::future::Future;
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
 
 /// An app extension.
 pub trait Listener {
@@ -44,6 +47,45 @@ pub trait Listener {
     /// }
     /// ```
     fn run(self) -> std::io::Result<(SocketAddr, Self::Server)>;
+
+    /// Listen on a socket addr, return a graceful server and the real addr it binds.
+    ///
+    /// Once `signal` resolves, the server stops accepting new connections
+    /// and waits for in-flight connections to finish,
+    /// remaining connections will be closed forcibly after `timeout`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::{App, Context, Shutdown};
+    /// use roa::tcp::Listener;
+    /// use async_std::task::spawn;
+    /// use std::time::Duration;
+    ///
+    /// async fn end(_ctx: &mut Context) -> roa::Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let shutdown = Shutdown::new();
+    ///     let (addr, server) = App::new().end(end).bind_graceful(
+    ///         "127.0.0.1:0",
+    ///         shutdown.signal(),
+    ///         Duration::from_secs(30),
+    ///     )?;
+    ///     let handle = spawn(server);
+    ///     // shutdown on SIGINT, SIGTERM, etc.
+    ///     shutdown.shutdown();
+    ///     handle.await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    fn bind_graceful(
+        self,
+        addr: impl ToSocketAddrs,
+        signal: impl 'static + Send + Future<Output = ()>,
+        timeout: Duration,
+    ) -> std::io::Result<(SocketAddr, GracefulServer)>;
 }
 
 impl<S, E> Listener for App<S, Arc<E>>
@@ -74,4 +116,79 @@ where
     fn run(self) -> std::io::Result<(SocketAddr, Self::Server)> {
         self.bind("127.0.0.1:0")
     }
+
+    fn bind_graceful(
+        self,
+        addr: impl ToSocketAddrs,
+        signal: impl 'static + Send + Future<Output = ()>,
+        timeout: Duration,
+    ) -> std::io::Result<(SocketAddr, GracefulServer)> {
+        let incoming = TcpIncoming::bind(addr)?;
+        let local_addr = incoming.local_addr();
+        let server = self.accept_graceful(incoming, signal, move || Delay::new(timeout));
+        Ok((local_addr, server))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Listener;
+    use crate::{App, Context, Result, Shutdown};
+    use async_std::io::prelude::*;
+    use async_std::net::TcpStream;
+    use async_std::task::{sleep, spawn};
+    use std::net::SocketAddr;
+    use std::time::{Duration, Instant};
+
+    async fn request(addr: SocketAddr) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await?;
+        Ok(resp)
+    }
+
+    #[async_std::test]
+    async fn graceful() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let shutdown = Shutdown::new();
+        let (addr, server) = App::new().end("Hello, World!").bind_graceful(
+            "127.0.0.1:0",
+            shutdown.signal(),
+            Duration::from_secs(10),
+        )?;
+        let handle = spawn(server);
+        let resp = request(addr).await?;
+        assert!(resp.starts_with("HTTP/1.1 200 OK"));
+        assert!(resp.ends_with("Hello, World!"));
+        shutdown.shutdown();
+        handle.await?;
+        assert!(TcpStream::connect(addr).await.is_err());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn force_close() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        async fn end(_ctx: &mut Context) -> Result {
+            sleep(Duration::from_secs(60)).await;
+            Ok(())
+        }
+        let shutdown = Shutdown::new();
+        let (addr, server) = App::new().end(end).bind_graceful(
+            "127.0.0.1:0",
+            shutdown.signal(),
+            Duration::from_millis(100),
+        )?;
+        let handle = spawn(server);
+        let client = spawn(request(addr));
+        sleep(Duration::from_millis(100)).await;
+        let start = Instant::now();
+        shutdown.shutdown();
+        handle.await?;
+        // connection is closed or reset without response.
+        assert!(client.await.map_or(true, |resp| resp.is_empty()));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        Ok(())
+    }
 }
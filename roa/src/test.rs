@@ -0,0 +1,384 @@
+//! This module provides an in-process test client `Client`.
+//!
+//! The client drives `HttpService::serve` directly,
+//! neither binding a TCP port nor spawning a server.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::{App, Context, Result};
+//! use roa::test::Client;
+//! use roa::http::StatusCode;
+//!
+//! async fn end(ctx: &mut Context) -> Result {
+//!     ctx.resp.write("Hello, World");
+//!     Ok(())
+//! }
+//!
+//! #[async_std::main]
+//! async fn main() -> std::io::Result<()> {
+//!     let client = Client::new(App::new().end(end));
+//!     let resp = client.get("/").send().await;
+//!     assert_eq!(StatusCode::OK, resp.status);
+//!     assert_eq!("Hello, World", resp.text().await?);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use crate::http::{self, HeaderMap, Method, StatusCode, Uri, Version};
+use crate::{App, Body, Endpoint, HttpService, Request, State};
+use bytes::{Bytes, BytesMut};
+use futures::StreamExt;
+use std::convert::TryInto;
+use std::fmt::Debug;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+#[cfg(any(feature = "json", feature = "urlencoded"))]
+use serde::Serialize;
+
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
+
+/// An in-process client to test an app.
+pub struct Client<S, E> {
+    service: HttpService<S, E>,
+}
+
+/// A builder of request, constructed by `Client::request`.
+pub struct RequestBuilder<'c, S, E> {
+    client: &'c Client<S, E>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap<HeaderValue>,
+    body: hyper::Body,
+    remote_addr: SocketAddr,
+}
+
+/// The response returned by `RequestBuilder::send`.
+pub struct Response {
+    /// Status code.
+    pub status: StatusCode,
+
+    /// Version of HTTP protocol.
+    pub version: Version,
+
+    /// Raw header map.
+    pub headers: HeaderMap<HeaderValue>,
+
+    body: Body,
+}
+
+impl<S, E> Client<S, E>
+where
+    S: State,
+    E: for<'a> Endpoint<'a, S>,
+{
+    /// Construct a client by an app.
+    pub fn new(app: App<S, Arc<E>>) -> Self {
+        Self {
+            service: app.http_service(),
+        }
+    }
+
+    /// Construct a request builder.
+    ///
+    /// # Panics
+    ///
+    /// Panics if uri is not a valid `http::Uri`.
+    pub fn request<U>(&self, method: Method, uri: U) -> RequestBuilder<'_, S, E>
+    where
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        RequestBuilder {
+            client: self,
+            method,
+            uri: uri.try_into().expect("invalid uri"),
+            headers: HeaderMap::new(),
+            body: hyper::Body::empty(),
+            remote_addr: ([127, 0, 0, 1], 0).into(),
+        }
+    }
+
+    /// Construct a request builder with method GET.
+    #[inline]
+    pub fn get<U>(&self, uri: U) -> RequestBuilder<'_, S, E>
+    where
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        self.request(Method::GET, uri)
+    }
+
+    /// Construct a request builder with method POST.
+    #[inline]
+    pub fn post<U>(&self, uri: U) -> RequestBuilder<'_, S, E>
+    where
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        self.request(Method::POST, uri)
+    }
+
+    /// Construct a request builder with method PUT.
+    #[inline]
+    pub fn put<U>(&self, uri: U) -> RequestBuilder<'_, S, E>
+    where
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        self.request(Method::PUT, uri)
+    }
+
+    /// Construct a request builder with method PATCH.
+    #[inline]
+    pub fn patch<U>(&self, uri: U) -> RequestBuilder<'_, S, E>
+    where
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        self.request(Method::PATCH, uri)
+    }
+
+    /// Construct a request builder with method DELETE.
+    #[inline]
+    pub fn delete<U>(&self, uri: U) -> RequestBuilder<'_, S, E>
+    where
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        self.request(Method::DELETE, uri)
+    }
+
+    /// Construct a request builder with method HEAD.
+    #[inline]
+    pub fn head<U>(&self, uri: U) -> RequestBuilder<'_, S, E>
+    where
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        self.request(Method::HEAD, uri)
+    }
+
+    /// Construct a request builder with method OPTIONS.
+    #[inline]
+    pub fn options<U>(&self, uri: U) -> RequestBuilder<'_, S, E>
+    where
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        self.request(Method::OPTIONS, uri)
+    }
+}
+
+impl<S, E> RequestBuilder<'_, S, E>
+where
+    S: State,
+    E: for<'a> Endpoint<'a, S>,
+{
+    /// Append a header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if name or value is invalid.
+    pub fn header<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        K::Error: Debug,
+        V: TryInto<HeaderValue>,
+        V::Error: Debug,
+    {
+        self.headers.append(
+            name.try_into().expect("invalid header name"),
+            value.try_into().expect("invalid header value"),
+        );
+        self
+    }
+
+    /// Set the remote address of client.
+    pub fn remote_addr(mut self, addr: impl Into<SocketAddr>) -> Self {
+        self.remote_addr = addr.into();
+        self
+    }
+
+    /// Set request body.
+    pub fn body(mut self, body: impl Into<hyper::Body>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Serialize data as json and set it as request body,
+    /// set "Content-Type" as "application/json".
+    ///
+    /// # Panics
+    ///
+    /// Panics if data fails to serialize.
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    pub fn json<B: Serialize>(mut self, data: &B) -> Self {
+        self.headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self.body(serde_json::to_vec(data).expect("fail to serialize json"))
+    }
+
+    /// Serialize data as urlencoded form and set it as request body,
+    /// set "Content-Type" as "application/x-www-form-urlencoded".
+    ///
+    /// # Panics
+    ///
+    /// Panics if data fails to serialize.
+    #[cfg(feature = "urlencoded")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "urlencoded")))]
+    pub fn form<B: Serialize>(mut self, data: &B) -> Self {
+        self.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        self.body(serde_urlencoded::to_string(data).expect("fail to serialize form"))
+    }
+
+    /// Send request to the app and wait for the response.
+    pub async fn send(self) -> Response {
+        let Self {
+            client,
+            method,
+            uri,
+            headers,
+            body,
+            remote_addr,
+        } = self;
+        let mut req = http::Request::new(body);
+        *req.method_mut() = method;
+        *req.uri_mut() = uri;
+        *req.headers_mut() = headers;
+        let mut service = client.service.clone();
+        service.set_remote_addr(remote_addr);
+        let resp = service.serve(Request::from(req)).await;
+        Response {
+            status: resp.status,
+            version: resp.version,
+            headers: resp.headers,
+            body: resp.body,
+        }
+    }
+}
+
+impl Response {
+    /// Read body as `Bytes`.
+    pub async fn bytes(self) -> io::Result<Bytes> {
+        let mut body = self.body;
+        let mut data = BytesMut::new();
+        while let Some(chunk) = body.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data.freeze())
+    }
+
+    /// Read body as utf-8 text.
+    pub async fn text(self) -> io::Result<String> {
+        let data = self.bytes().await?;
+        String::from_utf8(data.to_vec())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Read body as json.
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    pub async fn json<B: DeserializeOwned>(self) -> io::Result<B> {
+        let data = self.bytes().await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Client;
+    use crate::http::header::{CONTENT_TYPE, HOST};
+    use crate::http::{Method, StatusCode};
+    use crate::preload::*;
+    use crate::{throw, App, Context};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct User {
+        id: u64,
+        name: String,
+    }
+
+    #[async_std::test]
+    async fn request() -> Result<(), Box<dyn std::error::Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            assert_eq!(Method::PUT, ctx.method());
+            assert_eq!("/user/0", ctx.uri().path());
+            assert_eq!(Some("github.com"), ctx.get(HOST));
+            assert_eq!("192.168.0.1", ctx.remote_addr.ip().to_string());
+            let data = ctx.read().await?;
+            ctx.resp.write(data);
+            Ok(())
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client
+            .put("/user/0")
+            .header(HOST, "github.com")
+            .remote_addr(([192, 168, 0, 1], 8000))
+            .body("Hello, World")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn status() -> Result<(), Box<dyn std::error::Error>> {
+        async fn test(_ctx: &mut Context) -> crate::Result {
+            throw!(StatusCode::IM_A_TEAPOT, "I'm a teapot!")
+        }
+        let client = Client::new(App::new().end(test));
+        let resp = client.get("/").send().await;
+        assert_eq!(StatusCode::IM_A_TEAPOT, resp.status);
+        assert_eq!("I'm a teapot!", resp.text().await?);
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[async_std::test]
+    async fn json() -> Result<(), Box<dyn std::error::Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            assert_eq!(Some("application/json"), ctx.get(CONTENT_TYPE));
+            let user: User = ctx.read_json().await?;
+            ctx.write_json(&user)
+        }
+        let client = Client::new(App::new().end(test));
+        let user = User {
+            id: 0,
+            name: "Hexilee".to_string(),
+        };
+        let resp = client.post("/").json(&user).send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!(user, resp.json::<User>().await?);
+        Ok(())
+    }
+
+    #[cfg(feature = "urlencoded")]
+    #[async_std::test]
+    async fn form() -> Result<(), Box<dyn std::error::Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let user: User = ctx.read_form().await?;
+            assert_eq!(0, user.id);
+            assert_eq!("Hexilee", user.name);
+            Ok(())
+        }
+        let client = Client::new(App::new().end(test));
+        let user = User {
+            id: 0,
+            name: "Hexilee".to_string(),
+        };
+        let resp = client.post("/").form(&user).send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        Ok(())
+    }
+}
@@ -1,9 +1,12 @@
 use super::{ServerConfig, TlsIncoming};
 use crate::tcp::TcpIncoming;
-use crate::{App, Endpoint, Executor, Server, State};
+use crate::{App, Endpoint, Executor, GracefulServer, Server, State};
+use futures_timer::Delay;
+use std::future::Future;
 use std::io;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::sync::Arc;
+use std::time::Duration;
 
 impl TlsIncoming<TcpIncoming> {
     /// Bind a socket addr.
@@ -69,6 +72,19 @@ pub trait TlsListener {
         self,
         config: ServerConfig,
     ) -> std::io::Result<(SocketAddr, Self::Server)>;
+
+    /// Listen on a socket addr, return a graceful server and the real addr it binds.
+    ///
+    /// Once `signal` resolves, the server stops accepting new connections
+    /// and waits for in-flight connections to finish,
+    /// remaining connections will be closed forcibly after `timeout`.
+    fn bind_tls_graceful(
+        self,
+        addr: impl ToSocketAddrs,
+        config: ServerConfig,
+        signal: impl 'static + Send + Future<Output = ()>,
+        timeout: Duration,
+    ) -> std::io::Result<(SocketAddr, GracefulServer)>;
 }
 
 impl<S, E> TlsListener for App<S, Arc<E>>
@@ -104,6 +120,19 @@ where
     ) -> std::io::Result<(SocketAddr, Self::Server)> {
         self.bind_tls("127.0.0.1:0", config)
     }
+
+    fn bind_tls_graceful(
+        self,
+        addr: impl ToSocketAddrs,
+        config: ServerConfig,
+        signal: impl 'static + Send + Future<Output = ()>,
+        timeout: Duration,
+    ) -> std::io::Result<(SocketAddr, GracefulServer)> {
+        let incoming = TlsIncoming::bind(addr, config)?;
+        let local_addr = incoming.local_addr();
+        let server = self.accept_graceful(incoming, signal, move || Delay::new(timeout));
+        Ok((local_addr, server))
+    }
 }
 
 #[cfg(test)]
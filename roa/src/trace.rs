@@ -0,0 +1,662 @@
+//! This module provides a middleware `Tracer` and a context extension `Trace`.
+//!
+//! `Tracer` opens a `tracing` span for each request,
+//! and enabling feature "trace" also instruments each middleware and endpoint in a chain
+//! with a child span at `DEBUG` level.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::trace::Tracer;
+//! use roa::preload::*;
+//! use roa::http::HeaderMap;
+//! use roa::{App, Context};
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     let mut headers = HeaderMap::new();
+//!     // propagate trace context to the downstream service.
+//!     ctx.trace_context()
+//!         .expect("trace context must be set")
+//!         .inject(&mut headers);
+//!     Ok(())
+//! }
+//!
+//! let app = App::new().gate(Tracer::new()).end(end);
+//! ```
+
+use crate::http::header::HeaderName;
+use crate::http::{HeaderMap, HeaderValue};
+use crate::preload::*;
+use crate::util::random;
+use crate::{async_trait, Context, Middleware, Next, Result, State, Variable};
+use tracing::field::{display, Empty};
+use tracing::{info_span, Instrument};
+
+/// Header of W3C trace context, carrying trace id, parent id and flags.
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Header of W3C trace context, carrying vendor-specific data.
+pub const TRACESTATE: &str = "tracestate";
+
+/// Flag of a sampled trace.
+const SAMPLED: u8 = 1;
+
+/// A private scope.
+struct TraceScope;
+
+/// W3C trace context of a request.
+///
+/// Its span id identifies the request, so it's used as parent id
+/// when propagated to downstream services.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+    flags: u8,
+    state: Option<String>,
+}
+
+/// A context extension to get trace context of the request.
+pub trait Trace {
+    /// Get trace context of the request, None if `Tracer` is not mounted.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::trace::Tracer;
+    /// use roa::preload::*;
+    /// use roa::{App, Context};
+    /// use log::info;
+    ///
+    /// async fn end(ctx: &mut Context) -> roa::Result {
+    ///     if let Some(context) = ctx.trace_context() {
+    ///         info!("query users, trace id: {:032x}", context.trace_id());
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().gate(Tracer::new()).end(end);
+    /// ```
+    fn trace_context(&self) -> Option<Variable<'static, TraceContext>>;
+}
+
+/// A middleware to open a `tracing` span for each request.
+///
+/// The span is named "request", with fields following
+/// OpenTelemetry HTTP semantic conventions:
+///
+/// - `http.method`, `http.target` and `http.flavor` of the request.
+/// - `http.route`: path pattern matched by the router, like `/user/:id`.
+/// - `http.status_code`: status of the response, even if following middlewares
+///   or endpoints throw.
+/// - `http.client_ip`: ip of client, by `Forward::client_ip`.
+/// - `net.peer.ip` and `net.peer.port`: address of the peer.
+/// - `trace_id`, `span_id` and `parent_id`: W3C trace context, in hex.
+/// - `otel.kind`: always "server".
+/// - `otel.status_code`: "ERROR" for 5xx responses.
+///
+/// The trace context is continued from "traceparent" and "tracestate" headers
+/// if they are valid and trusted, or a new trace is started.
+/// It's stored in context, which can be got by `Trace::trace_context`.
+///
+/// It should be the first middleware, so the following middlewares
+/// and endpoints are instrumented by child spans of the request.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::trace::Tracer;
+/// use roa::App;
+///
+/// let app = App::new()
+///     .gate(Tracer::new().trust_incoming(false))
+///     .end("Hello, World");
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Tracer {
+    trust_incoming: bool,
+}
+
+impl TraceContext {
+    /// Start a new sampled trace.
+    fn root() -> Self {
+        let mut trace_id = 0;
+        while trace_id == 0 {
+            trace_id = u128::from(random()) << 64 | u128::from(random());
+        }
+        Self {
+            trace_id,
+            span_id: span_id(),
+            parent_id: None,
+            flags: SAMPLED,
+            state: None,
+        }
+    }
+
+    /// Extract trace context from "traceparent" and "tracestate" headers,
+    /// return `None` if "traceparent" is missing or invalid.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::trace::{TraceContext, TRACEPARENT};
+    /// use roa::http::HeaderMap;
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(
+    ///     TRACEPARENT,
+    ///     "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".parse().unwrap(),
+    /// );
+    /// let context = TraceContext::extract(&headers).unwrap();
+    /// assert_eq!(0x0af7651916cd43dd8448eb211c80319c, context.trace_id());
+    /// assert_eq!(0xb7ad6b7169203331, context.span_id());
+    /// assert!(context.sampled());
+    /// ```
+    pub fn extract(headers: &HeaderMap) -> Option<Self> {
+        let mut values = headers.get_all(TRACEPARENT).iter();
+        let value = values.next()?.to_str().ok()?;
+        // multiple traceparent headers are invalid
+        if values.next().is_some() {
+            return None;
+        }
+        let (trace_id, span_id, flags) = parse(value)?;
+        let mut state = Vec::new();
+        for value in headers.get_all(TRACESTATE) {
+            match value.to_str() {
+                Ok(value) if !value.trim().is_empty() => state.push(value.trim()),
+                Ok(_) => (),
+                Err(_) => {
+                    state.clear();
+                    break;
+                }
+            }
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            parent_id: None,
+            flags,
+            state: if state.is_empty() {
+                None
+            } else {
+                Some(state.join(","))
+            },
+        })
+    }
+
+    /// Construct a child of this context, with the same trace and a new span id.
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            span_id: span_id(),
+            parent_id: Some(self.span_id),
+            flags: self.flags,
+            state: self.state.clone(),
+        }
+    }
+
+    /// Inject "traceparent" and "tracestate" into headers of a downstream request.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        headers.insert(
+            HeaderName::from_static(TRACEPARENT),
+            HeaderValue::from_str(&self.traceparent()).expect("traceparent is valid"),
+        );
+        match self.state.as_ref() {
+            Some(state) => {
+                headers.insert(
+                    HeaderName::from_static(TRACESTATE),
+                    HeaderValue::from_str(state).expect("tracestate is valid"),
+                );
+            }
+            None => {
+                headers.remove(TRACESTATE);
+            }
+        }
+    }
+
+    /// Id of the trace.
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// Id of the span.
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    /// Id of the parent span, `None` if this is a root.
+    pub fn parent_id(&self) -> Option<u64> {
+        self.parent_id
+    }
+
+    /// Is the trace sampled by the caller?
+    pub fn sampled(&self) -> bool {
+        self.flags & SAMPLED == SAMPLED
+    }
+
+    /// Vendor-specific data, propagated as it is.
+    pub fn tracestate(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    /// Value of "traceparent" header with this span as parent.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+/// Generate a non-zero span id.
+fn span_id() -> u64 {
+    let mut id = 0;
+    while id == 0 {
+        id = random();
+    }
+    id
+}
+
+/// Parse lowercase hex of fixed length.
+fn hex(value: &str, len: usize) -> Option<u128> {
+    if value.len() == len
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+    {
+        u128::from_str_radix(value, 16).ok()
+    } else {
+        None
+    }
+}
+
+/// Parse "traceparent", `{version}-{trace-id}-{parent-id}-{flags}`.
+///
+/// Fields appended by future versions are ignored;
+/// all-zero ids and version "ff" are invalid.
+fn parse(value: &str) -> Option<(u128, u64, u8)> {
+    let mut parts = value.trim().splitn(5, '-');
+    let version = parts.next()?;
+    let trace_id = hex(parts.next()?, 32)?;
+    let span_id = hex(parts.next()?, 16)? as u64;
+    let flags = hex(parts.next()?, 2)? as u8;
+    let version = hex(version, 2)?;
+    if version == 0xff
+        || (version == 0 && parts.next().is_some())
+        || trace_id == 0
+        || span_id == 0
+    {
+        return None;
+    }
+    Some((trace_id, span_id, flags))
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Self {
+            trust_incoming: true,
+        }
+    }
+}
+
+impl Tracer {
+    /// Construct a middleware continuing traces from incoming headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continue traces from incoming headers or not, true by default.
+    pub fn trust_incoming(mut self, trust: bool) -> Self {
+        self.trust_incoming = trust;
+        self
+    }
+}
+
+impl<S> Trace for Context<S> {
+    #[inline]
+    fn trace_context(&self) -> Option<Variable<'static, TraceContext>> {
+        self.load_scoped::<TraceScope, TraceContext>("context")
+    }
+}
+
+/// Get path pattern of the matched route.
+#[cfg(feature = "router")]
+fn route<S>(ctx: &Context<S>) -> Option<String> {
+    use crate::router::RouterParam;
+    ctx.route().map(|route| route.to_string())
+}
+
+/// Get path pattern of the matched route, always `None` without a router.
+#[cfg(not(feature = "router"))]
+fn route<S>(_ctx: &Context<S>) -> Option<String> {
+    None
+}
+
+#[async_trait(?Send)]
+impl<'a, S: State> Middleware<'a, S> for Tracer {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let context = if self.trust_incoming {
+            TraceContext::extract(&ctx.req.headers)
+        } else {
+            None
+        }
+        .map(|parent| parent.child())
+        .unwrap_or_else(TraceContext::root);
+
+        let span = info_span!(
+            "request",
+            otel.kind = "server",
+            otel.status_code = Empty,
+            http.method = %ctx.method(),
+            http.target = %ctx.uri(),
+            http.flavor = ?ctx.version(),
+            http.route = Empty,
+            http.status_code = Empty,
+            http.client_ip = %ctx.client_ip(),
+            net.peer.ip = %ctx.remote_addr.ip(),
+            net.peer.port = ctx.remote_addr.port(),
+            trace_id = %format!("{:032x}", context.trace_id),
+            span_id = %format!("{:016x}", context.span_id),
+            parent_id = Empty,
+        );
+        if let Some(parent_id) = context.parent_id {
+            span.record("parent_id", display(format!("{:016x}", parent_id)));
+        }
+        ctx.store_scoped(TraceScope, "context", context);
+
+        let result = next.instrument(span.clone()).await;
+        if let Some(route) = route(ctx) {
+            span.record("http.route", display(route));
+        }
+        let status_code = match &result {
+            Ok(()) => ctx.status(),
+            Err(status) => status.status_code,
+        };
+        span.record("http.status_code", status_code.as_u16());
+        if status_code.is_server_error() {
+            span.record("otel.status_code", "ERROR");
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, TraceContext, Tracer, TRACEPARENT, TRACESTATE};
+    use crate::http::{HeaderMap, StatusCode};
+    use crate::preload::*;
+    use crate::test::Client;
+    use crate::{throw, App, Context, Next};
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::sync::Mutex;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Dispatch, Event, Metadata, Subscriber};
+
+    const PARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    /// A span exported in memory.
+    #[derive(Debug, Clone)]
+    struct SpanData {
+        name: &'static str,
+        parent: Option<u64>,
+        fields: HashMap<&'static str, String>,
+    }
+
+    /// A subscriber exporting spans in memory.
+    #[derive(Default)]
+    struct Exporter {
+        spans: Mutex<Vec<SpanData>>,
+        stack: Mutex<Vec<u64>>,
+    }
+
+    struct Fields<'a>(&'a mut HashMap<&'static str, String>);
+
+    impl Visit for Fields<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(field.name(), format!("{:?}", value));
+        }
+    }
+
+    impl Subscriber for Exporter {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let parent = if let Some(parent) = attrs.parent() {
+                Some(parent.into_u64())
+            } else if attrs.is_contextual() {
+                self.stack.lock().unwrap().last().copied()
+            } else {
+                None
+            };
+            let mut fields = HashMap::new();
+            attrs.record(&mut Fields(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(SpanData {
+                name: attrs.metadata().name(),
+                parent,
+                fields,
+            });
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            let data = &mut spans[span.into_u64() as usize - 1];
+            values.record(&mut Fields(&mut data.fields));
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            self.stack.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _span: &Id) {
+            self.stack.lock().unwrap().pop();
+        }
+    }
+
+    impl Exporter {
+        /// Take all exported spans.
+        fn take(dispatch: &Dispatch) -> Vec<SpanData> {
+            let exporter = dispatch.downcast_ref::<Exporter>().unwrap();
+            std::mem::take(&mut *exporter.spans.lock().unwrap())
+        }
+    }
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        let context = ctx.trace_context().expect("trace context must be set");
+        ctx.resp.write(context.traceparent());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_traceparent() {
+        let (trace_id, span_id, flags) = parse(PARENT).unwrap();
+        assert_eq!(0x0af7651916cd43dd8448eb211c80319c, trace_id);
+        assert_eq!(0xb7ad6b7169203331, span_id);
+        assert_eq!(1, flags);
+        assert!(
+            parse("01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00-x").is_some()
+        );
+
+        for invalid in &[
+            "",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-x",
+            "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+            "00-0af7651916cd43dd8448eb211c8031-b7ad6b7169203331-01",
+            "ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-+7ad6b7169203331-01",
+        ] {
+            assert!(parse(invalid).is_none(), "{} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn propagate_downstream() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT, PARENT.parse().unwrap());
+        headers.append(TRACESTATE, "rojo=00f067aa0ba902b7".parse().unwrap());
+        headers.append(TRACESTATE, "congo=t61rcWkgMzE".parse().unwrap());
+        let context = TraceContext::extract(&headers).unwrap().child();
+        assert_eq!(0x0af7651916cd43dd8448eb211c80319c, context.trace_id());
+        assert_eq!(Some(0xb7ad6b7169203331), context.parent_id());
+        assert_ne!(0xb7ad6b7169203331, context.span_id());
+        assert_eq!(
+            Some("rojo=00f067aa0ba902b7,congo=t61rcWkgMzE"),
+            context.tracestate()
+        );
+
+        let mut downstream = HeaderMap::new();
+        context.inject(&mut downstream);
+        assert_eq!(context.traceparent(), downstream[TRACEPARENT]);
+        assert_eq!(
+            format!(
+                "00-0af7651916cd43dd8448eb211c80319c-{:016x}-01",
+                context.span_id()
+            ),
+            context.traceparent()
+        );
+        assert_eq!(
+            "rojo=00f067aa0ba902b7,congo=t61rcWkgMzE",
+            downstream[TRACESTATE]
+        );
+
+        headers.append(TRACEPARENT, PARENT.parse().unwrap());
+        assert!(TraceContext::extract(&headers).is_none());
+    }
+
+    #[async_std::test]
+    async fn propagate() -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::new(App::new().gate(Tracer::new()).end(end));
+        let resp = client.get("/").header(TRACEPARENT, PARENT).send().await;
+        let traceparent = resp.text().await?;
+        assert!(traceparent.starts_with("00-0af7651916cd43dd8448eb211c80319c-"));
+        assert!(traceparent.ends_with("-01"));
+        assert_ne!(PARENT, traceparent);
+
+        let resp = client.get("/").header(TRACEPARENT, "invalid").send().await;
+        let (trace_id, _, flags) = parse(&resp.text().await?).unwrap();
+        assert_ne!(0x0af7651916cd43dd8448eb211c80319c, trace_id);
+        assert_eq!(1, flags);
+
+        let client = Client::new(
+            App::new()
+                .gate(Tracer::new().trust_incoming(false))
+                .end(end),
+        );
+        let resp = client.get("/").header(TRACEPARENT, PARENT).send().await;
+        let (trace_id, _, _) = parse(&resp.text().await?).unwrap();
+        assert_ne!(0x0af7651916cd43dd8448eb211c80319c, trace_id);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn spans() -> Result<(), Box<dyn std::error::Error>> {
+        async fn gate(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            next.await?;
+            if ctx.req.headers.contains_key("crash") {
+                throw!(StatusCode::INTERNAL_SERVER_ERROR, "crash")
+            }
+            Ok(())
+        }
+
+        let dispatch = Dispatch::new(Exporter::default());
+        let _guard = tracing::dispatcher::set_default(&dispatch);
+        let client = Client::new(App::new().gate(Tracer::new()).gate(gate).end(end));
+        let resp = client
+            .get("/user?id=1")
+            .header(TRACEPARENT, PARENT)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let span_id = &resp.text().await?[36..52];
+
+        let spans = Exporter::take(&dispatch);
+        let index = |name: &str, type_name: &str| {
+            spans
+                .iter()
+                .position(|span| {
+                    span.name == name
+                        && span
+                            .fields
+                            .get("name")
+                            .map_or(false, |name| name.ends_with(type_name))
+                })
+                .unwrap() as u64
+                + 1
+        };
+        let tracer = index("middleware", "Tracer");
+        let request = spans
+            .iter()
+            .position(|span| span.name == "request")
+            .unwrap() as u64
+            + 1;
+        let gate_span = index("middleware", "gate");
+        let end_span = index("endpoint", "end");
+
+        let data = &spans[request as usize - 1];
+        assert_eq!(Some(tracer), data.parent);
+        assert_eq!(Some(request), spans[gate_span as usize - 1].parent);
+        assert_eq!(Some(gate_span), spans[end_span as usize - 1].parent);
+        assert_eq!("server", data.fields["otel.kind"]);
+        assert_eq!("GET", data.fields["http.method"]);
+        assert_eq!("/user?id=1", data.fields["http.target"]);
+        assert_eq!("HTTP/1.1", data.fields["http.flavor"]);
+        assert_eq!("200", data.fields["http.status_code"]);
+        assert_eq!("127.0.0.1", data.fields["net.peer.ip"]);
+        assert_eq!("0af7651916cd43dd8448eb211c80319c", data.fields["trace_id"]);
+        assert_eq!("b7ad6b7169203331", data.fields["parent_id"]);
+        assert_eq!(span_id, data.fields["span_id"]);
+        assert!(!data.fields.contains_key("otel.status_code"));
+
+        let resp = client.get("/").header("crash", "true").send().await;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status);
+        let spans = Exporter::take(&dispatch);
+        let data = spans.iter().find(|span| span.name == "request").unwrap();
+        assert_eq!("500", data.fields["http.status_code"]);
+        assert_eq!("ERROR", data.fields["otel.status_code"]);
+        assert!(!data.fields.contains_key("parent_id"));
+        Ok(())
+    }
+
+    #[cfg(feature = "router")]
+    #[async_std::test]
+    async fn route() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::router::Router;
+
+        let dispatch = Dispatch::new(Exporter::default());
+        let _guard = tracing::dispatcher::set_default(&dispatch);
+        let router = Router::new().on("/user/:id", end);
+        let client =
+            Client::new(App::new().gate(Tracer::new()).end(router.routes("/api")?));
+        let resp = client.get("/api/user/1").send().await;
+        assert_eq!(StatusCode::OK, resp.status);
+        let spans = Exporter::take(&dispatch);
+        let data = spans.iter().find(|span| span.name == "request").unwrap();
+        assert_eq!("/api/user/:id", data.fields["http.route"]);
+
+        let resp = client.get("/api/group/1").send().await;
+        assert_eq!(StatusCode::NOT_FOUND, resp.status);
+        let spans = Exporter::take(&dispatch);
+        let data = spans.iter().find(|span| span.name == "request").unwrap();
+        assert!(!data.fields.contains_key("http.route"));
+        assert_eq!("404", data.fields["http.status_code"]);
+        Ok(())
+    }
+}
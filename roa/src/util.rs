@@ -0,0 +1,43 @@
+//! Helpers shared by modules.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Escape html special characters.
+#[cfg(any(feature = "json", feature = "file"))]
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Get random bits, not cryptographically secure.
+pub(crate) fn random() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    // keys of RandomState are randomly seeded and changed for each instance.
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(feature = "json", feature = "file"))]
+    #[test]
+    fn escape_html() {
+        assert_eq!(
+            "&lt;a href=&quot;/&quot;&gt;Tom &amp; Jerry&#x27;s&lt;/a&gt;",
+            super::escape_html(r#"<a href="/">Tom & Jerry's</a>"#)
+        );
+    }
+}